@@ -0,0 +1,128 @@
+//! Measures heap allocations (rather than wall time) per executed query.
+//!
+//! Small queries finish in microseconds, at which point allocation count is a
+//! better signal for regressions than timing noise.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    Criterion, Throughput,
+};
+use phoebus::Executor;
+
+mod common;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Criterion measurement that counts allocations instead of elapsed time
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> Self::Intermediate {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: Self::Intermediate) -> Self::Value {
+        ALLOCATIONS.load(Ordering::Relaxed) - start
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationsFormatter
+    }
+}
+
+struct AllocationsFormatter;
+
+impl ValueFormatter for AllocationsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        match *throughput {
+            Throughput::Elements(elems) => {
+                for value in values {
+                    *value /= elems as f64;
+                }
+                "allocs/elem"
+            }
+            _ => "allocs",
+        }
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+const QUERY: &str = r#"
+query {
+  person {
+    firstName
+    lastName
+    age
+  }
+}
+"#;
+
+fn query_allocations(c: &mut Criterion<Allocations>) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let executor = Executor::new(&common::schema()).unwrap();
+
+    c.bench_function("simple query", |b| {
+        b.to_async(&rt)
+            .iter(|| async { common::run(&executor, QUERY).await })
+    });
+}
+
+fn allocations() -> Criterion<Allocations> {
+    Criterion::default().with_measurement(Allocations)
+}
+
+criterion_group! {
+    name = benches;
+    config = allocations();
+    targets = query_allocations
+}
+criterion_main!(benches);
@@ -0,0 +1,112 @@
+//! Schema and resolvers shared by the benches, each bench selects the part of
+//! the schema it measures.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use phoebus::{ConstValue, Ctx, Executor, ObjectResolver, Resolved};
+
+/// Number of `fieldN: Int!` fields on `Query`
+pub const FIELD_COUNT: usize = 50;
+
+/// Length of the `nodes` list
+const LIST_LEN: i32 = 1000;
+
+pub fn schema() -> String {
+    let fields = (0..FIELD_COUNT)
+        .map(|i| format!("  field{}: Int!\n", i))
+        .collect::<String>();
+
+    format!(
+        r#"
+schema {{
+  query: Query
+}}
+
+type Query {{
+  hello: String!
+  person: Person!
+  node: Node!
+  nodes: [Node!]!
+{}}}
+
+type Person {{
+  firstName: String!
+  lastName: String!
+  age: Int
+}}
+
+"A node in a chain, `child` nests one level deeper"
+type Node {{
+  value: Int!
+  child: Node
+}}
+"#,
+        fields
+    )
+}
+
+/// Executes `query` against [`QueryResolver`], panicking on errors
+pub async fn run(executor: &Executor, query: &str) -> ConstValue {
+    executor
+        .run(query, QueryResolver, None, HashMap::new())
+        .await
+        .into_result()
+        .unwrap()
+}
+
+struct QueryResolver;
+
+#[async_trait::async_trait]
+impl ObjectResolver for QueryResolver {
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "hello" => Ok(Resolved::string("world")),
+            "person" => Ok(Resolved::object(PersonResolver)),
+            "node" => Ok(Resolved::object(NodeResolver(0))),
+            "nodes" => Ok(Resolved::array(
+                (0..LIST_LEN).map(|ix| Resolved::object(NodeResolver(ix))),
+            )),
+            _ => {
+                let idx: i32 = name
+                    .strip_prefix("field")
+                    .and_then(|i| i.parse().ok())
+                    .ok_or_else(|| anyhow!("invalid field: {}", name))?;
+
+                //nb: yield once so every field is pending on the first poll,
+                //otherwise everything resolves synchronously and the wakeup
+                //path is never exercised
+                tokio::task::yield_now().await;
+
+                Ok(ConstValue::from(idx).into())
+            }
+        }
+    }
+}
+
+struct PersonResolver;
+
+#[async_trait::async_trait]
+impl ObjectResolver for PersonResolver {
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "firstName" => Ok(Resolved::string("Zack")),
+            "lastName" => Ok(Resolved::string("Angelo")),
+            "age" => Ok(ConstValue::from(39).into()),
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
+
+struct NodeResolver(i32);
+
+#[async_trait::async_trait]
+impl ObjectResolver for NodeResolver {
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "value" => Ok(ConstValue::from(self.0).into()),
+            "child" => Ok(Resolved::object(NodeResolver(self.0 + 1))),
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Times the executor end to end: compiling a schema, and executing a single
+//! field, a deeply nested selection and a long list. See `wide_selection` for
+//! wide selection sets.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use phoebus::Executor;
+
+mod common;
+
+const DEPTH: usize = 10;
+
+fn nested_query() -> String {
+    let mut selection = "value".to_owned();
+
+    for _ in 1..DEPTH {
+        selection = format!("value child {{ {} }}", selection);
+    }
+
+    format!("query {{ node {{ {} }} }}", selection)
+}
+
+fn compile_schema(c: &mut Criterion) {
+    let schema = common::schema();
+
+    c.bench_function("compile schema", |b| {
+        b.iter(|| Executor::new(&schema).unwrap())
+    });
+}
+
+fn execute(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let executor = Executor::new(&common::schema()).unwrap();
+    let nested = nested_query();
+
+    let queries = [
+        ("single scalar field", "query { hello }".to_owned()),
+        ("10 level nested selection", nested),
+        ("1000 item list", "query { nodes { value } }".to_owned()),
+    ];
+
+    for (name, query) in queries {
+        c.bench_function(name, |b| {
+            b.to_async(&rt)
+                .iter(|| async { common::run(&executor, &query).await })
+        });
+    }
+}
+
+criterion_group!(benches, compile_schema, execute);
+criterion_main!(benches);
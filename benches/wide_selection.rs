@@ -0,0 +1,31 @@
+//! Times execution of a single 50-field selection set, which stresses how
+//! `ExecuteSelectionSet` drives its field futures.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use phoebus::Executor;
+
+mod common;
+
+use common::FIELD_COUNT;
+
+fn query() -> String {
+    let fields = (0..FIELD_COUNT)
+        .map(|i| format!("field{} ", i))
+        .collect::<String>();
+
+    format!("query {{ {}}}", fields)
+}
+
+fn wide_selection(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let executor = Executor::new(&common::schema()).unwrap();
+    let query = query();
+
+    c.bench_function("50 field selection set", |b| {
+        b.to_async(&rt)
+            .iter(|| async { common::run(&executor, &query).await })
+    });
+}
+
+criterion_group!(benches, wide_selection);
+criterion_main!(benches);
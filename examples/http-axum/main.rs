@@ -1,16 +1,17 @@
 extern crate phoebus;
 
 mod graphiql;
+mod middleware;
 mod resolvers;
 
 use anyhow::Result;
 use graphiql::GraphiQLSource;
-use phoebus::Executor;
+use phoebus::{BatchRequest, BatchResponse, Executor, MultipartUpload};
 use tracing::info;
 
 use axum::{
-    extract::Extension,
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Extension},
+    http::HeaderMap,
     response::{self, IntoResponse},
     routing::{get, post},
     Json, Router, Server,
@@ -24,10 +25,22 @@ async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
     info!("axum http server starting...");
-    let executor = Executor::new(SCHEMA)?;
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .max_depth(32)
+        .query_cache(256)
+        .batch_concurrency(8)
+        .middleware(middleware::TracingMiddleware)
+        .build()?;
     let app = Router::new()
         .route("/", get(graphiql) /*.post(graphql_handler)*/)
         .route("/graphql", post(graphql))
+        //nb: the extractor limits the files to 10MB, axum's own 2MB body
+        //limit would reject most of them first
+        .route(
+            "/graphql/upload",
+            post(graphql_upload).layer(DefaultBodyLimit::disable()),
+        )
         .layer(Extension(executor));
 
     println!("GraphiQL IDE: http://localhost:8000");
@@ -45,73 +58,49 @@ async fn graphiql() -> impl IntoResponse {
 
 async fn graphql(
     executor: Extension<Executor>,
-    Json(graphql_req): Json<http::GraphQLReq>,
-) -> (StatusCode, Json<http::GraphQLResp>) {
-    let variables = graphql_req
-        .variables
-        .map(|vs| {
-            vs.into_iter()
-                .map(|(k, v)| (k, v.try_into().unwrap()))
-                .collect()
-        })
-        .unwrap_or_default();
-    match executor
-        .run(
-            &graphql_req.query,
-            resolvers::QueryResolver,
-            graphql_req.operation_name,
-            variables,
-        )
-        .await
-        .and_then(|r| r.into_json().map_err(anyhow::Error::new))
-    {
-        Ok(result) => (
-            StatusCode::OK,
-            Json(http::GraphQLResp {
-                data: result,
-                errors: None,
-            }),
-        ),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(http::GraphQLResp::generic_error(err)),
-        ),
-    }
-}
+    headers: HeaderMap,
+    Json(batch): Json<BatchRequest>,
+) -> Json<BatchResponse> {
+    //nb: clients opt in to resolver timings in the response extensions
+    let tracing = headers.contains_key("x-apollo-tracing");
 
-mod http {
-    use serde::{Deserialize, Serialize};
-    use serde_json::json;
-    use std::{collections::HashMap, fmt::Display};
-
-    #[derive(Debug, Serialize, Deserialize)]
-    #[serde(rename_all = "camelCase")]
-    pub struct GraphQLReq {
-        pub query: String,
-        pub operation_name: Option<String>,
-        pub variables: Option<HashMap<String, serde_json::Value>>,
-    }
-
-    #[derive(Serialize, Debug, Clone)]
-    #[serde(rename_all = "camelCase")]
-    pub struct GraphQLResp {
-        pub data: serde_json::Value,
+    Json(execute(&executor, batch, tracing).await)
+}
 
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub errors: Option<Vec<serde_json::Value>>,
-    }
+/// Multipart requests carrying files for `Upload` variables, e.g.
+///
+/// ```text
+/// curl localhost:8000/graphql/upload \
+///   -F operations='{ "query": "mutation ($file: Upload!) { uploadFile(file: $file) }", "variables": { "file": null } }' \
+///   -F map='{ "0": ["variables.file"] }' \
+///   -F 0=@schema.graphql
+/// ```
+async fn graphql_upload(
+    executor: Extension<Executor>,
+    MultipartUpload(batch): MultipartUpload,
+) -> Json<BatchResponse> {
+    Json(execute(&executor, batch, false).await)
+}
 
-    impl GraphQLResp {
-        pub fn generic_error<E: Display>(err: E) -> Self {
-            Self {
-                data: Default::default(),
-                errors: Some(
-                    json!([{ "message": format!("{}", err) }])
-                        .as_array()
-                        .unwrap()
-                        .clone(), //TODO fix
-                ),
-            }
-        }
+//nb: operations in a batch run concurrently and fail independently, each
+//gets its own response in the same position as its request
+async fn execute(executor: &Executor, batch: BatchRequest, tracing: bool) -> BatchResponse {
+    match batch {
+        BatchRequest::Single(request) => BatchResponse::Single(
+            executor
+                .execute(request.tracing(tracing), resolvers::QueryResolver)
+                .await,
+        ),
+        BatchRequest::Batch(requests) => BatchResponse::Batch(
+            executor
+                .run_batch(
+                    requests
+                        .into_iter()
+                        .map(|request| request.tracing(tracing))
+                        .collect(),
+                    || resolvers::QueryResolver,
+                )
+                .await,
+        ),
     }
 }
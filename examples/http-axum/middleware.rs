@@ -0,0 +1,23 @@
+use anyhow::Result;
+use phoebus::{Ctx, Resolved, ResolverMiddleware};
+use tracing::debug;
+
+/// Logs every field as it's resolved
+pub struct TracingMiddleware;
+
+#[async_trait::async_trait]
+impl ResolverMiddleware for TracingMiddleware {
+    async fn before_field(&self, ctx: &Ctx) -> Result<()> {
+        debug!(field = ctx.field_name(), "resolving field");
+        Ok(())
+    }
+
+    async fn after_field(&self, ctx: &Ctx, result: &Resolved) -> Result<()> {
+        debug!(
+            field = ctx.field_name(),
+            null = result.is_null(),
+            "resolved field"
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use phoebus::{ConstValue, Ctx, Name, ObjectResolver, Resolved};
+use phoebus::{ConstValue, Ctx, Name, ObjectResolver, Resolved, Upload};
 
 pub struct QueryResolver;
 
@@ -15,6 +15,17 @@ impl ObjectResolver for QueryResolver {
                 bool_arg_value: ctx.arg("testBoolArg"),
             }
             .into()),
+            //nb: the executor resolves the root fields of every operation
+            //type with this resolver, mutations included
+            "uploadFile" => {
+                let upload = ctx.try_arg::<Upload>("file")?;
+                tracing::info!(
+                    filename = %upload.filename,
+                    content_type = ?upload.content_type,
+                    "received upload"
+                );
+                Ok(ConstValue::from(upload.size()).into())
+            }
             _ => Err(anyhow!("invalid field: {}", name)),
         }
     }
@@ -46,10 +57,10 @@ impl ObjectResolver for PersonResolver {
             "intArgVal" => self.av(&self.int_arg_value),
             "floatArgVal" => self.av(&self.float_arg_value),
             "boolArgVal" => self.av(&self.bool_arg_value),
-            "pets" => {
-                let pets: Vec<Resolved> = vec![DogResolver.into(), CatResolver.into()];
-                Ok(pets.into())
-            }
+            "pets" => Ok(Resolved::array([
+                Resolved::object(DogResolver),
+                Resolved::object(CatResolver),
+            ])),
             _ => Err(anyhow!("invalid field {}", name)),
         }
     }
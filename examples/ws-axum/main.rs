@@ -0,0 +1,271 @@
+//! Subscriptions over WebSockets using the `graphql-transport-ws` protocol
+//! (https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md).
+//!
+//! Each `subscribe` message runs `Executor::subscribe` and forwards its
+//! responses as `next` messages until the stream ends (`complete`) or the
+//! client sends `complete`. Queries and mutations aren't served here, they
+//! get an `error` message.
+//!
+//! Run it with `cargo run --example ws-axum` and try it with any graphql-ws
+//! client, e.g. subscribe to `subscription { ticks(count: 5) }` at
+//! ws://localhost:8000/graphql.
+
+extern crate phoebus;
+
+use std::{borrow::Cow, collections::HashMap, time::Duration};
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use phoebus::{
+    ConstValue, Ctx, EventStream, Executor, GraphQLError, Request, Resolved, Response,
+    SubscriptionResolver,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::info;
+
+use axum::{
+    extract::{
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Extension,
+    },
+    response::IntoResponse,
+    routing::get,
+    Router, Server,
+};
+
+const SCHEMA: &str = r#"
+type Query {
+  hello: String!
+}
+
+type Subscription {
+  "Counts up to `count`, one tick every `everyMs` milliseconds"
+  ticks(count: Int = 10, everyMs: Int = 1000): Int!
+}
+"#;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    info!("ws-axum server starting...");
+    let executor = Executor::new(SCHEMA)?;
+    let app = Router::new()
+        .route("/graphql", get(graphql_ws))
+        .layer(Extension(executor));
+
+    println!("Subscriptions: ws://localhost:8000/graphql");
+
+    Server::bind(&"127.0.0.1:8000".parse().unwrap())
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+async fn graphql_ws(executor: Extension<Executor>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.protocols(["graphql-transport-ws"])
+        .on_upgrade(move |socket| serve(socket, executor.0))
+}
+
+/// Runs one connection until either side closes it
+async fn serve(mut socket: WebSocket, executor: Executor) {
+    let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<ServerMessage>();
+    let mut subscriptions: HashMap<String, JoinHandle<()>> = HashMap::new();
+    let mut acknowledged = false;
+
+    loop {
+        tokio::select! {
+            Some(msg) = outgoing_rx.recv() => {
+                if let ServerMessage::Complete { id } | ServerMessage::Error { id, .. } = &msg {
+                    subscriptions.remove(id);
+                }
+
+                if send(&mut socket, &msg).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+
+                let msg = match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(msg) => msg,
+                    Err(err) => {
+                        close(&mut socket, 4400, err.to_string()).await;
+                        break;
+                    }
+                };
+
+                match msg {
+                    ClientMessage::ConnectionInit {} if acknowledged => {
+                        close(&mut socket, 4429, "Too many initialisation requests").await;
+                        break;
+                    }
+                    ClientMessage::ConnectionInit {} => {
+                        acknowledged = true;
+                        if send(&mut socket, &ServerMessage::ConnectionAck).await.is_err() {
+                            break;
+                        }
+                    }
+                    ClientMessage::Ping {} => {
+                        if send(&mut socket, &ServerMessage::Pong).await.is_err() {
+                            break;
+                        }
+                    }
+                    ClientMessage::Pong {} => {}
+                    ClientMessage::Subscribe { .. } if !acknowledged => {
+                        close(&mut socket, 4401, "Unauthorized").await;
+                        break;
+                    }
+                    ClientMessage::Subscribe { id, .. } if subscriptions.contains_key(&id) => {
+                        close(&mut socket, 4409, format!("Subscriber for {} already exists", id)).await;
+                        break;
+                    }
+                    ClientMessage::Subscribe { id, payload } => {
+                        let task = tokio::spawn(subscribe(
+                            executor.clone(),
+                            id.clone(),
+                            payload,
+                            outgoing.clone(),
+                        ));
+                        subscriptions.insert(id, task);
+                    }
+                    //nb: the client is done with it, so no `complete` is sent back
+                    ClientMessage::Complete { id } => {
+                        if let Some(task) = subscriptions.remove(&id) {
+                            task.abort();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for task in subscriptions.into_values() {
+        task.abort();
+    }
+}
+
+/// Runs one subscription, sending its responses until it ends
+async fn subscribe(
+    executor: Executor,
+    id: String,
+    payload: SubscribePayload,
+    outgoing: mpsc::UnboundedSender<ServerMessage>,
+) {
+    let mut request = Request::new(payload.query).variables(payload.variables.unwrap_or_default());
+    if let Some(operation_name) = payload.operation_name {
+        request = request.operation_name(operation_name);
+    }
+
+    let mut responses = match executor.subscribe(request, SubscriptionRoot).await {
+        Ok(responses) => Box::pin(responses),
+        Err(err) => {
+            let _ = outgoing.send(ServerMessage::Error {
+                id,
+                payload: vec![err],
+            });
+            return;
+        }
+    };
+
+    while let Some(response) = responses.next().await {
+        let next = ServerMessage::Next {
+            id: id.clone(),
+            payload: response,
+        };
+
+        if outgoing.send(next).is_err() {
+            return;
+        }
+    }
+
+    let _ = outgoing.send(ServerMessage::Complete { id });
+}
+
+async fn send(socket: &mut WebSocket, msg: &ServerMessage) -> Result<()> {
+    let text = serde_json::to_string(msg)?;
+    socket.send(Message::Text(text)).await?;
+    Ok(())
+}
+
+async fn close(socket: &mut WebSocket, code: u16, reason: impl Into<Cow<'static, str>>) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    ConnectionInit {},
+    Ping {},
+    Pong {},
+    Subscribe {
+        id: String,
+        payload: SubscribePayload,
+    },
+    Complete {
+        id: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscribePayload {
+    query: String,
+    operation_name: Option<String>,
+    variables: Option<HashMap<String, serde_json::Value>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    ConnectionAck,
+    Pong,
+    Next {
+        id: String,
+        payload: Response,
+    },
+    Error {
+        id: String,
+        payload: Vec<GraphQLError>,
+    },
+    Complete {
+        id: String,
+    },
+}
+
+struct SubscriptionRoot;
+
+#[async_trait::async_trait]
+impl SubscriptionResolver for SubscriptionRoot {
+    async fn subscribe(&self, ctx: &Ctx, name: &str) -> Result<EventStream> {
+        match name {
+            "ticks" => {
+                let count = ctx.arg::<i32>("count").unwrap_or(10);
+                let every = Duration::from_millis(ctx.arg::<i32>("everyMs").unwrap_or(1000) as u64);
+
+                let ticks = futures::stream::unfold(0, move |tick| async move {
+                    if tick >= count {
+                        return None;
+                    }
+
+                    tokio::time::sleep(every).await;
+                    Some((Ok(Resolved::Value(ConstValue::from(tick + 1))), tick + 1))
+                });
+
+                Ok(ticks.boxed())
+            }
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
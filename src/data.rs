@@ -0,0 +1,49 @@
+//! Request-scoped values (the authenticated user, a database pool, a locale)
+//! made available to every resolver through [`Ctx::data`](crate::Ctx::data).
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+/// A map holding at most one value of each type
+///
+/// ```ignore
+/// let mut data = Data::new();
+/// data.insert(CurrentUser { id: 1 });
+///
+/// let response = executor
+///     .run_with_data(query, QueryResolver, None, variables, data)
+///     .await;
+///
+/// // in a resolver
+/// let user = ctx.data::<CurrentUser>();
+/// ```
+//nb: values are behind an `Arc` so requests carrying data stay cheap to clone
+#[derive(Default, Clone)]
+pub struct Data(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl Data {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a value, replacing any previous value of the same type
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.0.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.0
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+}
+
+impl Debug for Data {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Data").field("len", &self.0.len()).finish()
+    }
+}
@@ -0,0 +1,290 @@
+//! Batches loads issued by concurrently executing resolvers so that N sibling
+//! fields fetching by key result in one call to the backing store instead of N.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::oneshot;
+
+type BatchFuture<K, V> = Pin<Box<dyn Future<Output = HashMap<K, V>> + Send>>;
+type BatchFn<K, V> = dyn Fn(Vec<K>) -> BatchFuture<K, V> + Send + Sync;
+
+/// Coalesces individual [`load`](DataLoader::load) calls into batches
+///
+/// The first load after an idle period opens a batch and schedules it to be
+/// dispatched after a short delay (a single scheduler tick by default). Every
+/// load issued in the meantime joins that batch, and the batch function is
+/// called once with the de-duplicated set of keys.
+///
+/// Loaded values are cached for the life of the loader, so create one per
+/// request and hand it to resolvers through the request's
+/// [`Data`](crate::Data):
+///
+/// ```ignore
+/// let loader = DataLoader::new(|ids: Vec<i32>| async move {
+///     db.people_by_id(&ids).await
+/// });
+///
+/// let request = Request::new(query).data(loader);
+///
+/// // in a resolver
+/// let loader = ctx.data_unchecked::<DataLoader<i32, Person>>();
+/// let person = loader.load(1).await;
+/// ```
+pub struct DataLoader<K, V> {
+    inner: Arc<DataLoaderInner<K, V>>,
+}
+
+struct DataLoaderInner<K, V> {
+    batch_fn: Box<BatchFn<K, V>>,
+    delay: Duration,
+    max_batch_size: Option<usize>,
+    pending: Mutex<Vec<(K, oneshot::Sender<Option<V>>)>>,
+    cache: Mutex<HashMap<K, Option<V>>>,
+}
+
+impl<K, V> Clone for DataLoader<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> DataLoader<K, V>
+where
+    K: Hash + Eq + Clone + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    /// Creates a loader that resolves batches of keys with `batch_fn`. Keys
+    /// missing from the returned map load as `None`.
+    pub fn new<F, Fut>(batch_fn: F) -> Self
+    where
+        F: Fn(Vec<K>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HashMap<K, V>> + Send + 'static,
+    {
+        Self {
+            inner: Arc::new(DataLoaderInner {
+                batch_fn: Box::new(move |keys| Box::pin(batch_fn(keys))),
+                delay: Duration::ZERO,
+                max_batch_size: None,
+                pending: Mutex::new(Vec::new()),
+                cache: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Sets how long a batch stays open to collect keys before it's
+    /// dispatched. Must be called before the loader is cloned or used.
+    #[must_use]
+    pub fn delay(self, delay: Duration) -> Self {
+        self.configure(|inner| DataLoaderInner { delay, ..inner })
+    }
+
+    /// Caps how many loads go into one batch, a full batch is dispatched
+    /// right away and later loads open a new one. Must be called before the
+    /// loader is cloned or used.
+    #[must_use]
+    pub fn max_batch_size(self, max_batch_size: usize) -> Self {
+        self.configure(|inner| DataLoaderInner {
+            max_batch_size: Some(max_batch_size.max(1)),
+            ..inner
+        })
+    }
+
+    fn configure(self, f: impl FnOnce(DataLoaderInner<K, V>) -> DataLoaderInner<K, V>) -> Self {
+        let inner = Arc::try_unwrap(self.inner).unwrap_or_else(|_| {
+            panic!("data loader must be configured before the loader is shared")
+        });
+
+        Self {
+            inner: Arc::new(f(inner)),
+        }
+    }
+
+    /// Loads a single key, batched with any other loads issued before the
+    /// current batch is dispatched. Keys loaded before are served from the
+    /// cache.
+    pub fn load(&self, key: K) -> impl Future<Output = Option<V>> {
+        let cached = self
+            .inner
+            .cache
+            .lock()
+            .expect("data loader lock poisoned")
+            .get(&key)
+            .cloned();
+
+        let rx = match cached {
+            Some(_) => None,
+            None => Some(self.enqueue(key)),
+        };
+
+        async move {
+            match rx {
+                Some(rx) => rx.await.ok().flatten(),
+                None => cached.flatten(),
+            }
+        }
+    }
+
+    fn enqueue(&self, key: K) -> oneshot::Receiver<Option<V>> {
+        let (tx, rx) = oneshot::channel();
+
+        let (opens_batch, full_batch) = {
+            let mut pending = self
+                .inner
+                .pending
+                .lock()
+                .expect("data loader lock poisoned");
+            pending.push((key, tx));
+
+            let full = self
+                .inner
+                .max_batch_size
+                .is_some_and(|max| pending.len() >= max);
+
+            (
+                pending.len() == 1,
+                full.then(|| std::mem::take(&mut *pending)),
+            )
+        };
+
+        if let Some(batch) = full_batch {
+            let inner = self.inner.clone();
+            tokio::spawn(async move { inner.run_batch(batch).await });
+        } else if opens_batch {
+            let inner = self.inner.clone();
+            tokio::spawn(async move { inner.dispatch().await });
+        }
+
+        rx
+    }
+}
+
+impl<K, V> DataLoaderInner<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    async fn dispatch(&self) {
+        tokio::time::sleep(self.delay).await;
+
+        //nb: the batch may already have been dispatched for being full, in
+        //which case this picks up (and dispatches early) whatever came after
+        let batch = std::mem::take(&mut *self.pending.lock().expect("data loader lock poisoned"));
+        self.run_batch(batch).await;
+    }
+
+    async fn run_batch(&self, batch: Vec<(K, oneshot::Sender<Option<V>>)>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let mut seen = HashSet::new();
+        let keys = batch
+            .iter()
+            .filter(|(key, _)| seen.insert(key.clone()))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        let values = (self.batch_fn)(keys.clone()).await;
+
+        {
+            let mut cache = self.cache.lock().expect("data loader lock poisoned");
+            for key in keys {
+                let value = values.get(&key).cloned();
+                cache.insert(key, value);
+            }
+        }
+
+        for (key, tx) in batch {
+            //nb: the receiver may have been dropped if the requesting field was cancelled
+            let _ = tx.send(values.get(&key).cloned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn concurrent_loads_are_batched() {
+        let batch_calls = Arc::new(AtomicUsize::new(0));
+        let batch_keys = Arc::new(Mutex::new(Vec::new()));
+
+        let loader = {
+            let batch_calls = batch_calls.clone();
+            let batch_keys = batch_keys.clone();
+
+            DataLoader::new(move |keys: Vec<i32>| {
+                batch_calls.fetch_add(1, Ordering::SeqCst);
+                batch_keys.lock().unwrap().extend(keys.iter().copied());
+
+                async move {
+                    keys.into_iter()
+                        .filter(|k| *k != 3)
+                        .map(|k| (k, k * 10))
+                        .collect::<HashMap<_, _>>()
+                }
+            })
+        };
+
+        let results = futures::future::join_all([
+            loader.load(1),
+            loader.load(2),
+            loader.load(1),
+            loader.load(3),
+        ])
+        .await;
+
+        assert_eq!(results, vec![Some(10), Some(20), Some(10), None]);
+        assert_eq!(batch_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(*batch_keys.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn loaded_values_are_cached() {
+        let batch_calls = Arc::new(AtomicUsize::new(0));
+
+        let loader = {
+            let batch_calls = batch_calls.clone();
+
+            DataLoader::new(move |keys: Vec<i32>| {
+                batch_calls.fetch_add(1, Ordering::SeqCst);
+                async move { keys.into_iter().map(|k| (k, k * 10)).collect() }
+            })
+        };
+
+        assert_eq!(loader.load(1).await, Some(10));
+        assert_eq!(loader.load(1).await, Some(10));
+        assert_eq!(batch_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn full_batches_are_dispatched() {
+        let batch_sizes = Arc::new(Mutex::new(Vec::new()));
+
+        let loader = {
+            let batch_sizes = batch_sizes.clone();
+
+            DataLoader::new(move |keys: Vec<i32>| {
+                batch_sizes.lock().unwrap().push(keys.len());
+                async move { keys.into_iter().map(|k| (k, k)).collect() }
+            })
+            .max_batch_size(2)
+        };
+
+        let results = futures::future::join_all((0..5).map(|k| loader.load(k))).await;
+
+        assert_eq!(results, (0..5).map(Some).collect::<Vec<_>>());
+        assert_eq!(*batch_sizes.lock().unwrap(), vec![2, 2, 1]);
+    }
+}
@@ -0,0 +1,77 @@
+//! Handlers for directives used on fields in queries, e.g.
+//! `{ person { name @uppercase } }`
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    middleware::{FieldInfo, Next, ResolverMiddleware},
+    resolver::{Ctx, ObjectResolver, Resolved},
+};
+
+/// Registered by name with
+/// [`ExecutorBuilder::directive`](crate::ExecutorBuilder::directive), a
+/// handler wraps the resolver of every field the directive is used on in a
+/// query. Like [`ResolverMiddleware`] it can inspect or replace the result,
+/// or skip the resolver entirely.
+///
+/// The directive's arguments are read with
+/// [`Ctx::directive_arg`](crate::Ctx::directive_arg):
+///
+/// ```ignore
+/// // directive @truncate(length: Int!) on FIELD
+/// struct Truncate;
+///
+/// #[async_trait]
+/// impl DirectiveHandler for Truncate {
+///     async fn apply(&self, ctx: &Ctx, next: &dyn ObjectResolver) -> Result<Resolved> {
+///         let length = ctx.directive_arg::<i64>("truncate", "length")?.unwrap_or(0);
+///         let resolved = next.resolve_field(ctx, ctx.field_name()).await?;
+///
+///         Ok(resolved.map_value(|value| match value {
+///             ConstValue::String(s) => s.chars().take(length as usize).collect::<String>().into(),
+///             other => other,
+///         }))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait DirectiveHandler: Send + Sync {
+    /// Resolves a field carrying the directive. `next` resolves the field
+    /// (running any remaining handlers and middleware first) whatever field
+    /// name it's called with.
+    async fn apply(&self, ctx: &Ctx, next: &dyn ObjectResolver) -> Result<Resolved>;
+}
+
+/// Runs a [`DirectiveHandler`] around the fields the directive is used on
+pub(crate) struct FieldDirective {
+    pub(crate) directive: String,
+    pub(crate) handler: Box<dyn DirectiveHandler>,
+}
+
+#[async_trait]
+impl ResolverMiddleware for FieldDirective {
+    async fn resolve(&self, ctx: &Ctx, _info: &FieldInfo<'_>, next: Next<'_>) -> Result<Resolved> {
+        let applies = ctx
+            .field()
+            .directives()
+            .iter()
+            .any(|d| d.name() == self.directive);
+
+        if applies {
+            self.handler.apply(ctx, &NextResolver(next)).await
+        } else {
+            next.run(ctx).await
+        }
+    }
+}
+
+/// Presents the rest of the chain to a handler as a resolver
+struct NextResolver<'a>(Next<'a>);
+
+#[async_trait]
+impl<'a> ObjectResolver for NextResolver<'a> {
+    async fn resolve_field(&self, ctx: &Ctx, _name: &str) -> Result<Resolved> {
+        self.0.clone().run(ctx).await
+    }
+}
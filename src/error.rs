@@ -0,0 +1,289 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use indexmap::IndexMap;
+use serde::{Serialize, Serializer};
+
+use crate::value::{ConstValue, Name};
+
+/// An error raised while validating or executing a request
+///
+/// Field errors carry the `path` of the field that failed and the `locations`
+/// of its selection in the document, request errors (validation failures,
+/// limits, timeouts) have neither.
+///
+/// Resolvers keep returning [`anyhow::Result`], a `GraphQLError` returned
+/// through `anyhow` is recovered intact so resolvers can attach `extensions`:
+///
+/// ```ignore
+/// return Err(Error::new("person not found")
+///     .code("NOT_FOUND")
+///     .extension("id", id)
+///     .into());
+/// ```
+///
+/// [Reference](https://spec.graphql.org/October2021/#sec-Errors)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct GraphQLError(Box<ErrorData>);
+
+/// Shorter name for [`GraphQLError`], e.g. `phoebus::Error::new("...")`
+pub type Error = GraphQLError;
+
+//nb: boxed so that `Result<_, GraphQLError>` stays pointer-sized on the
+//happy path, every field future in the executor returns one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ErrorData {
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    locations: Vec<Location>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<Vec<PathSegment>>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    extensions: IndexMap<Name, ConstValue>,
+    #[serde(skip)]
+    kind: ErrorKind,
+}
+
+/// Why an operation failed without completing, see [`GraphQLError::kind`].
+/// Unlike the `code` extension it's only ever set by the executor, so
+/// resolvers can't pass their errors off as a cancellation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Any other error, e.g. one returned by a resolver
+    #[default]
+    Other,
+    /// The request's cancellation token was cancelled
+    Cancelled,
+    /// The request ran past its timeout
+    Timeout,
+}
+
+/// What an error was raised by, passed to the hook set with
+/// [`ExecutorBuilder::map_error`](crate::ExecutorBuilder::map_error)
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorContext<'a> {
+    /// Response path of the field that failed, `None` for request errors
+    pub path: Option<&'a [PathSegment]>,
+    /// Name (not alias) of the field that failed, `None` for request errors
+    pub field_name: Option<&'a str>,
+    /// The error the field's resolver returned, `None` if the error was
+    /// raised by the executor (e.g. a non-null field resolving to null)
+    pub source: Option<&'a anyhow::Error>,
+}
+
+/// Hook set with
+/// [`ExecutorBuilder::map_error`](crate::ExecutorBuilder::map_error)
+pub(crate) type ErrorMapper =
+    Arc<dyn Fn(GraphQLError, &ErrorContext) -> GraphQLError + Send + Sync>;
+
+/// A line and column (both 1-based) in the request document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// One step of an error's path: a response key or a list index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(Name),
+    Index(usize),
+}
+
+impl GraphQLError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(Box::new(ErrorData {
+            message: message.into(),
+            locations: Vec::new(),
+            path: None,
+            extensions: IndexMap::new(),
+            kind: ErrorKind::Other,
+        }))
+    }
+
+    /// Adds an entry to the error's `extensions`
+    #[must_use]
+    pub fn extension(mut self, key: impl AsRef<str>, value: impl Into<ConstValue>) -> Self {
+        self.0.extensions.insert(Name::new(key), value.into());
+        self
+    }
+
+    /// Sets the machine-readable `code` extension, e.g. `NOT_FOUND` or
+    /// `UNAUTHENTICATED`
+    #[must_use]
+    pub fn code(self, code: impl Into<String>) -> Self {
+        self.extension("code", code.into())
+    }
+
+    pub fn message(&self) -> &str {
+        &self.0.message
+    }
+
+    /// Where the failing field was selected in the document
+    pub fn locations(&self) -> &[Location] {
+        &self.0.locations
+    }
+
+    /// Response path of the field that failed, `None` for request errors
+    pub fn path(&self) -> Option<&[PathSegment]> {
+        self.0.path.as_deref()
+    }
+
+    pub fn extensions(&self) -> &IndexMap<Name, ConstValue> {
+        &self.0.extensions
+    }
+
+    /// Whether the executor raised this error for a cancelled or timed out
+    /// request
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind
+    }
+
+    /// Shorthand for `kind() == ErrorKind::Cancelled`
+    pub fn is_cancelled(&self) -> bool {
+        self.0.kind == ErrorKind::Cancelled
+    }
+
+    pub(crate) fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.0.kind = kind;
+        self
+    }
+
+    pub(crate) fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.0.message = message.into();
+        self
+    }
+
+    pub(crate) fn with_location(mut self, location: Location) -> Self {
+        self.0.locations.push(location);
+        self
+    }
+
+    /// Prefixes the error's path with `segment`, used to build the path up as
+    /// the error propagates out of nested selection sets
+    pub(crate) fn prepend_path(mut self, segment: PathSegment) -> Self {
+        self.0.path.get_or_insert_with(Vec::new).insert(0, segment);
+        self
+    }
+}
+
+impl Display for GraphQLError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.message)?;
+
+        if let Some(path) = &self.0.path {
+            f.write_str(" (at ")?;
+            for (ix, segment) in path.iter().enumerate() {
+                if ix > 0 {
+                    f.write_str(".")?;
+                }
+                Display::fmt(segment, f)?;
+            }
+            f.write_str(")")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for GraphQLError {}
+
+impl From<anyhow::Error> for GraphQLError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<GraphQLError>() {
+            Ok(err) => err,
+            Err(err) => Self::new(err.to_string()),
+        }
+    }
+}
+
+//nb: borrows so the resolver's error can still be handed to the error hook,
+//see `ErrorContext::source`
+impl From<&anyhow::Error> for GraphQLError {
+    fn from(err: &anyhow::Error) -> Self {
+        match err.downcast_ref::<GraphQLError>() {
+            Some(err) => err.clone(),
+            None => Self::new(err.to_string()),
+        }
+    }
+}
+
+/// Returned by a resolver for a field it doesn't know, e.g. one added to the
+/// schema before the resolver was updated. The field resolves to `null`
+/// instead of failing, see
+/// [`ObjectResolver::resolve_field_or_null`](crate::ObjectResolver::resolve_field_or_null).
+///
+/// ```ignore
+/// _ => Err(UnknownField(name.to_owned()).into()),
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField(pub String);
+
+impl Display for UnknownField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown field: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownField {}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field(name) => Display::fmt(name, f),
+            Self::Index(ix) => Display::fmt(ix, f),
+        }
+    }
+}
+
+impl Serialize for PathSegment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Field(name) => name.serialize(serializer),
+            Self::Index(ix) => serializer.serialize_u64(*ix as u64),
+        }
+    }
+}
+
+impl Location {
+    /// Converts a byte offset into `source` into a line and column
+    pub(crate) fn from_offset(source: &str, offset: usize) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|ix| ix + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+
+        Self { line, column }
+    }
+}
+
+/// Response path of the position being executed
+///
+/// Each node links back to its parent so that sibling fields share their
+/// common prefix instead of each cloning it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResponsePath(Option<Arc<(ResponsePath, PathSegment)>>);
+
+impl ResponsePath {
+    pub(crate) fn child(&self, segment: PathSegment) -> Self {
+        Self(Some(Arc::new((self.clone(), segment))))
+    }
+
+    pub(crate) fn to_vec(&self) -> Vec<PathSegment> {
+        let mut segments = Vec::new();
+        let mut node = &self.0;
+
+        while let Some(link) = node {
+            let (parent, segment) = link.as_ref();
+            segments.push(segment.clone());
+            node = &parent.0;
+        }
+
+        segments.reverse();
+        segments
+    }
+}
@@ -0,0 +1,92 @@
+//! Per-resolver timings in Apollo's `tracing` response extension, enabled
+//! per request with [`Request::tracing`](crate::Request::tracing)
+//!
+//! https://github.com/apollographql/apollo-tracing
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde_json::json;
+
+use crate::{error::PathSegment, middleware::FieldInfo, scalar::rfc3339, value::ConstValue};
+
+pub(crate) struct ApolloTracing {
+    start_time: SystemTime,
+    start: Instant,
+    resolvers: Mutex<Vec<ResolverTrace>>,
+}
+
+struct ResolverTrace {
+    path: Vec<PathSegment>,
+    parent_type: String,
+    field_name: String,
+    return_type: String,
+    start_offset: Duration,
+    duration: Duration,
+}
+
+impl ApolloTracing {
+    pub(crate) fn new() -> Self {
+        Self {
+            start_time: SystemTime::now(),
+            start: Instant::now(),
+            resolvers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Records a field that was resolved (and completed) between `start` and
+    /// `end`
+    pub(crate) fn record(&self, field: &FieldInfo<'_>, start: Instant, end: Instant) {
+        let trace = ResolverTrace {
+            path: field.path(),
+            parent_type: field.parent_type().unwrap_or_default().to_owned(),
+            field_name: field.name().to_owned(),
+            return_type: field.return_type(),
+            start_offset: start.duration_since(self.start),
+            duration: end.duration_since(start),
+        };
+
+        self.resolvers
+            .lock()
+            .expect("tracing lock poisoned")
+            .push(trace);
+    }
+
+    /// The `tracing` extension, covering execution up to now
+    pub(crate) fn extension(&self) -> ConstValue {
+        let duration = self.start.elapsed();
+        let resolvers = self.resolvers.lock().expect("tracing lock poisoned");
+
+        let resolvers = resolvers
+            .iter()
+            .map(|trace| {
+                json!({
+                    "path": trace.path,
+                    "parentType": trace.parent_type,
+                    "fieldName": trace.field_name,
+                    "returnType": trace.return_type,
+                    "startOffset": nanos(trace.start_offset),
+                    "duration": nanos(trace.duration),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        //nb: documents are parsed and validated when they're prepared (and
+        //then cached), which isn't part of executing the request
+        ConstValue::from(json!({
+            "version": 1,
+            "startTime": rfc3339(self.start_time),
+            "endTime": rfc3339(self.start_time + duration),
+            "duration": nanos(duration),
+            "parsing": { "startOffset": 0, "duration": 0 },
+            "validation": { "startOffset": 0, "duration": 0 },
+            "execution": { "resolvers": resolvers },
+        }))
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use apollo_compiler::hir;
+use async_trait::async_trait;
+
+use crate::{error::GraphQLError, middleware::ResolverMiddleware, resolver::Ctx};
+
+/// Decides whether the current request may resolve a field carrying the
+/// authorization directive, given the directive's `role` argument
+pub(crate) type AuthorizeFn = dyn Fn(&Ctx, Option<&str>) -> bool + Send + Sync;
+
+/// Enforces a directive like `@auth(role: String)` on field definitions,
+/// registered with [`ExecutorBuilder::authorization`](super::ExecutorBuilder::authorization)
+pub(crate) struct DirectiveAuthorization {
+    pub(crate) directive: String,
+    pub(crate) authorize: Box<AuthorizeFn>,
+}
+
+#[async_trait]
+impl ResolverMiddleware for DirectiveAuthorization {
+    async fn before_field(&self, ctx: &Ctx) -> Result<()> {
+        let Some(field_def) = ctx.ectx.field_definition(ctx.field()) else {
+            return Ok(());
+        };
+
+        let Some(directive) = field_def
+            .directives()
+            .iter()
+            .find(|d| d.name() == self.directive)
+        else {
+            return Ok(());
+        };
+
+        let role = match directive.argument_by_name("role") {
+            Some(hir::Value::String { value, .. }) => Some(value.as_str()),
+            _ => None,
+        };
+
+        if (self.authorize)(ctx, role) {
+            Ok(())
+        } else {
+            Err(GraphQLError::new(format!(
+                "not authorized to access field: {}",
+                field_def.name()
+            ))
+            .extension("code", "FORBIDDEN")
+            .into())
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use apollo_compiler::{hir::TypeSystem, ApolloCompiler, HirDatabase};
+
+use super::{
+    authorization::DirectiveAuthorization, hidden::RejectHidden, ExecSchema, Executor,
+    ExecutorConfig,
+};
+use crate::{
+    directive::{DirectiveHandler, FieldDirective},
+    error::{ErrorContext, GraphQLError},
+    middleware::ResolverMiddleware,
+    observer::ExecutionObserver,
+    resolver::Ctx,
+    scalar::{ScalarCodec, ScalarValidator},
+};
+
+/// Configures and constructs an [`Executor`]
+///
+/// ```ignore
+/// let executor = Executor::builder()
+///     .type_system(SCHEMA)
+///     .timeout(Duration::from_secs(10))
+///     .max_depth(16)
+///     .max_complexity(1000)
+///     .enable_introspection(false)
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct ExecutorBuilder {
+    /// SDL documents making up the schema, with the names they're reported
+    /// under in validation errors
+    type_system: Vec<(String, String)>,
+    config: ExecutorConfig,
+}
+
+impl ExecutorBuilder {
+    /// Sets the schema (SDL) the executor will run queries against
+    #[must_use]
+    pub fn type_system(mut self, schema: impl Into<String>) -> Self {
+        self.type_system = vec![(DEFAULT_DOCUMENT_NAME.to_owned(), schema.into())];
+        self
+    }
+
+    /// Adds an SDL document to the schema, for schemas split across several
+    /// files. The documents are validated together, `name` (e.g. the file
+    /// name) identifies the document in validation errors.
+    #[must_use]
+    pub fn type_system_document(
+        mut self,
+        name: impl Into<String>,
+        schema: impl Into<String>,
+    ) -> Self {
+        self.type_system.push((name.into(), schema.into()));
+        self
+    }
+
+    /// Sets the maximum amount of time an operation may take to execute. When
+    /// exceeded, in-flight resolvers are dropped and the request fails with
+    /// a timeout error. Can be overridden per request with
+    /// [`Request::timeout`](crate::Request::timeout).
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Rejects operations whose selection sets nest deeper than `max_depth`
+    /// (fragment spreads are expanded when measuring). Introspection
+    /// meta-fields don't count towards this limit, see
+    /// [`max_introspection_depth`](Self::max_introspection_depth).
+    #[must_use]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.config.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Limits the depth of selections beneath `__schema`/`__type`. The
+    /// standard introspection query is naturally deep, so this is tracked
+    /// separately from [`max_depth`](Self::max_depth).
+    #[must_use]
+    pub fn max_introspection_depth(mut self, max_depth: usize) -> Self {
+        self.config.max_introspection_depth = Some(max_depth);
+        self
+    }
+
+    /// Rejects operations costing more than `max_complexity` before they
+    /// execute. Every field costs 1 plus the cost of its selections, and
+    /// list fields multiply that by their `first` or `limit` argument.
+    /// Weights and multipliers can be set per field with a `@cost` directive
+    /// declared in the schema:
+    ///
+    /// ```graphql
+    /// directive @cost(weight: Int = 1, multipliers: [String!]) on FIELD_DEFINITION
+    ///
+    /// type Query {
+    ///   search(query: String!, pageSize: Int = 10): [Result!]! @cost(weight: 5, multipliers: ["pageSize"])
+    /// }
+    /// ```
+    ///
+    /// The cost of an executed operation is returned in the response's
+    /// `complexity` extension.
+    #[must_use]
+    pub fn max_complexity(mut self, max_complexity: usize) -> Self {
+        self.config.max_complexity = Some(max_complexity);
+        self
+    }
+
+    /// Limits how many operations of a batch run at once, see
+    /// [`Executor::run_batch`]. Unlimited by default.
+    #[must_use]
+    pub fn batch_concurrency(mut self, limit: usize) -> Self {
+        self.config.batch_concurrency = Some(limit.max(1));
+        self
+    }
+
+    /// Limits how many resolvers of an operation run at once, so a wide
+    /// selection set or a long list of objects doesn't flood whatever the
+    /// resolvers call into (e.g. a database). Fields beyond the limit wait
+    /// for a running resolver to return. Unlimited by default.
+    #[must_use]
+    pub fn resolver_concurrency(mut self, limit: usize) -> Self {
+        self.config.resolver_concurrency = Some(limit.max(1));
+        self
+    }
+
+    /// Sets how many compiled query documents are cached (keyed by query
+    /// text) so repeated queries skip parsing and validation. Defaults to
+    /// 512, a size of 0 disables the cache.
+    #[must_use]
+    pub fn query_cache(mut self, size: usize) -> Self {
+        self.config.query_cache_size = Some(size);
+        self
+    }
+
+    /// Enables or disables the `__schema` and `__type` introspection fields
+    /// (enabled by default). When disabled, operations selecting them are
+    /// rejected before execution. `__typename` is always available.
+    #[must_use]
+    pub fn enable_introspection(mut self, enabled: bool) -> Self {
+        self.config.introspection_disabled = !enabled;
+        self
+    }
+
+    /// Registers the codec used to serialize values of the custom scalar
+    /// `name`, see [`ScalarCodec`]
+    #[must_use]
+    pub fn scalar(mut self, name: impl Into<String>, codec: impl ScalarCodec + 'static) -> Self {
+        Arc::make_mut(&mut self.config.scalars).insert(name.into(), Arc::new(codec));
+        self
+    }
+
+    /// Registers a check for values of the custom scalar `name`, for scalars
+    /// that are structurally strings (or numbers) but have a format to
+    /// enforce. Unlike a [`ScalarCodec`] it can't change values, it's run on
+    /// the client's representation after any codec has serialized it (or
+    /// before any codec parses it).
+    ///
+    /// ```ignore
+    /// // scalar Email
+    /// let executor = Executor::builder()
+    ///     .type_system(SCHEMA)
+    ///     .validate_scalar("Email", |v: &ConstValue| match v {
+    ///         ConstValue::String(s) if s.contains('@') => Ok(()),
+    ///         _ => Err(anyhow!("expected an email address")),
+    ///     })
+    ///     .build()?;
+    /// ```
+    ///
+    /// A variable that fails the check is a request error, an argument
+    /// literal or a resolved value fails its field. Errors name the scalar:
+    /// `invalid Email: expected an email address`.
+    #[must_use]
+    pub fn validate_scalar(
+        mut self,
+        name: impl Into<String>,
+        validate: impl Fn(&ConstValue) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        let validator: ScalarValidator = Arc::new(validate);
+        Arc::make_mut(&mut self.config.scalar_validators).insert(name.into(), validator);
+        self
+    }
+
+    /// Adds a [`ResolverMiddleware`], run around every field's resolver after
+    /// any previously added middleware
+    #[must_use]
+    pub fn middleware(mut self, middleware: impl ResolverMiddleware + 'static) -> Self {
+        Arc::make_mut(&mut self.config.middleware).push(Arc::new(middleware));
+        self
+    }
+
+    /// Adds an [`ExecutionObserver`], notified as each field is resolved
+    #[must_use]
+    pub fn observer(mut self, observer: impl ExecutionObserver + 'static) -> Self {
+        Arc::make_mut(&mut self.config.observers).push(Arc::new(observer));
+        self
+    }
+
+    /// Sets a hook run on every error before it's placed in a response, e.g.
+    /// to log the resolver's original error and hide its details from
+    /// clients. Field errors come with their path, field name and the error
+    /// the resolver returned (see [`ErrorContext`]), request errors with
+    /// neither. Hooks set by repeated calls run in order.
+    ///
+    /// ```ignore
+    /// let executor = Executor::builder()
+    ///     .type_system(SCHEMA)
+    ///     .map_error(|err: GraphQLError, ctx: &ErrorContext| {
+    ///         if let Some(source) = ctx.source {
+    ///             tracing::error!(path = ?ctx.path, "resolver failed: {:?}", source);
+    ///         }
+    ///         err
+    ///     })
+    ///     .build()?;
+    /// ```
+    #[must_use]
+    pub fn map_error(
+        mut self,
+        map: impl Fn(GraphQLError, &ErrorContext) -> GraphQLError + Send + Sync + 'static,
+    ) -> Self {
+        Arc::make_mut(&mut self.config.error_mappers).push(Arc::new(map));
+        self
+    }
+
+    /// Replaces the message of errors returned by resolvers with `internal
+    /// server error`, so internal details (SQL, hostnames, ...) don't reach
+    /// clients. Errors given a `code` extension (see
+    /// [`GraphQLError::code`]) are meant for clients and kept as they are.
+    #[must_use]
+    pub fn mask_internal_errors(self) -> Self {
+        self.map_error(|err, ctx| {
+            if ctx.source.is_none() || err.extensions().contains_key("code") {
+                return err;
+            }
+
+            err.with_message("internal server error")
+                .code("INTERNAL_SERVER_ERROR")
+        })
+    }
+
+    /// Hides schema members carrying `@directive` from introspection: types,
+    /// fields, arguments, input fields and enum values. Fields and arguments
+    /// of a hidden type are hidden with it, so nothing visible refers to it.
+    /// Hidden fields still resolve (unless
+    /// [`reject_hidden_fields`](Self::reject_hidden_fields) is set), e.g. for
+    /// trusted callers that know about them.
+    ///
+    /// The directive must be declared in the schema:
+    ///
+    /// ```ignore
+    /// // directive @internal on OBJECT | FIELD_DEFINITION | ENUM_VALUE | ...
+    /// let executor = Executor::builder()
+    ///     .type_system(SCHEMA)
+    ///     .hidden_directive("internal")
+    ///     .build()?;
+    /// ```
+    #[must_use]
+    pub fn hidden_directive(mut self, directive: impl Into<String>) -> Self {
+        self.config.hidden_directive = Some(directive.into().into());
+        self
+    }
+
+    /// Fails fields hidden with [`hidden_directive`](Self::hidden_directive)
+    /// as if they didn't exist, instead of resolving them
+    #[must_use]
+    pub fn reject_hidden_fields(self) -> Self {
+        self.middleware(RejectHidden)
+    }
+
+    /// Guards fields whose definitions carry `@directive`. Before such a
+    /// field is resolved, `authorize` is called with the directive's `role`
+    /// argument, returning false fails the field with a `FORBIDDEN` error.
+    ///
+    /// The directive must be declared in the schema:
+    ///
+    /// ```ignore
+    /// // directive @auth(role: String) on FIELD_DEFINITION
+    /// let executor = Executor::builder()
+    ///     .type_system(SCHEMA)
+    ///     .authorization("auth", |ctx: &Ctx, role: Option<&str>| {
+    ///         ctx.data::<CurrentUser>()
+    ///             .map_or(false, |user| role.map_or(true, |role| user.has_role(role)))
+    ///     })
+    ///     .build()?;
+    /// ```
+    #[must_use]
+    pub fn authorization(
+        self,
+        directive: impl Into<String>,
+        authorize: impl Fn(&Ctx, Option<&str>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.middleware(DirectiveAuthorization {
+            directive: directive.into(),
+            authorize: Box::new(authorize),
+        })
+    }
+
+    /// Registers the handler run around fields that carry `@directive` in a
+    /// query, see [`DirectiveHandler`]. Handlers run as middleware, in
+    /// registration order with any other middleware.
+    ///
+    /// The directive must be declared in the schema:
+    ///
+    /// ```ignore
+    /// // directive @truncate(length: Int!) on FIELD
+    /// let executor = Executor::builder()
+    ///     .type_system(SCHEMA)
+    ///     .directive("truncate", Truncate)
+    ///     .build()?;
+    /// ```
+    #[must_use]
+    pub fn directive(
+        self,
+        directive: impl Into<String>,
+        handler: impl DirectiveHandler + 'static,
+    ) -> Self {
+        self.middleware(FieldDirective {
+            directive: directive.into(),
+            handler: Box::new(handler),
+        })
+    }
+
+    /// Validates the schema and builds the executor
+    pub fn build(self) -> Result<Executor> {
+        if self.type_system.is_empty() {
+            return Err(anyhow!("executor builder requires a type system"));
+        }
+
+        let (type_system, exec_schema) = compile_schema(&self.type_system, &self.config)?;
+
+        Ok(Executor::with_config(type_system, exec_schema, self.config))
+    }
+}
+
+/// Name of a schema given as a single document
+pub(crate) const DEFAULT_DOCUMENT_NAME: &str = "schema.graphql";
+
+/// Validates a schema's SDL documents (as name and source pairs) and extracts
+/// what execution needs from them. Shared by [`ExecutorBuilder::build`] and
+/// [`Executor::replace_schema`].
+pub(crate) fn compile_schema(
+    documents: &[(String, String)],
+    config: &ExecutorConfig,
+) -> Result<(Arc<TypeSystem>, Arc<ExecSchema>)> {
+    let mut compiler = ApolloCompiler::new();
+    for (name, schema) in documents {
+        compiler.add_type_system(schema, name);
+    }
+
+    let diags = compiler.validate();
+    let has_errors = diags.iter().filter(|d| d.data.is_error()).count() > 0;
+
+    for diag in diags.iter() {
+        if diag.data.is_error() {
+            tracing::error!("{}", diag);
+        }
+    }
+
+    if has_errors {
+        return Err(anyhow!("graphql had errors"));
+    }
+
+    let type_system = compiler.db.type_system();
+
+    for name in config.scalars.keys() {
+        if !type_system.definitions.scalars.contains_key(name) {
+            return Err(anyhow!(
+                "scalar codec registered for {} which is not a scalar in the schema",
+                name
+            ));
+        }
+    }
+
+    for name in config.scalar_validators.keys() {
+        if !type_system.definitions.scalars.contains_key(name) {
+            return Err(anyhow!(
+                "scalar validator registered for {} which is not a scalar in the schema",
+                name
+            ));
+        }
+    }
+
+    if let Some(name) = &config.hidden_directive {
+        if !type_system
+            .definitions
+            .directives
+            .contains_key(name.as_ref())
+        {
+            return Err(anyhow!(
+                "hidden directive {} is not declared in the schema",
+                name
+            ));
+        }
+    }
+
+    Ok((type_system, Arc::new(ExecSchema::new(&compiler.db))))
+}
@@ -0,0 +1,191 @@
+//! Coercion of argument values to their declared input types
+//!
+//! https://spec.graphql.org/October2021/#sec-Input-Values
+
+use anyhow::{anyhow, Result};
+use apollo_compiler::hir::{
+    self, EnumTypeDefinition, InputObjectTypeDefinition, OperationDefinition, TypeDefinition,
+};
+use indexmap::IndexMap;
+
+use super::ExecCtx;
+use crate::{
+    scalar::validate_scalar,
+    value::{ConstValue, Name},
+};
+
+/// Coerces an argument value to its declared type
+///
+/// - a single value provided where a list is expected becomes a one element
+///   list, at every level of nesting (`5` for `[[Int]]` is `[[5]]`)
+/// - enum values must name a value of the enum, strings are accepted too
+///   since that's how variables provide them
+/// - input object fields are coerced to their own types, `@oneOf` input
+///   objects must be given exactly one non-null field
+/// - custom scalars are checked by their registered validator and parsed by
+///   their registered [`ScalarCodec`](crate::ScalarCodec)
+pub(crate) fn coerce_input(
+    ectx: &ExecCtx,
+    ty: &hir::Type,
+    value: ConstValue,
+) -> Result<ConstValue> {
+    match (ty, value) {
+        (_, ConstValue::Null) => Ok(ConstValue::Null),
+        (hir::Type::NonNull { ty, .. }, value) => coerce_input(ectx, ty, value),
+        (hir::Type::List { ty, .. }, ConstValue::List(items)) => items
+            .into_iter()
+            .map(|item| coerce_input(ectx, ty, item))
+            .collect::<Result<Vec<_>>>()
+            .map(ConstValue::List),
+        (hir::Type::List { ty, .. }, value) => {
+            Ok(ConstValue::List(vec![coerce_input(ectx, ty, value)?]))
+        }
+        (hir::Type::Named { name, .. }, value) => match ectx.find_type_definition_by_name(name) {
+            Some(TypeDefinition::EnumTypeDefinition(enum_type)) => coerce_enum(enum_type, value),
+            Some(TypeDefinition::InputObjectTypeDefinition(input_type)) => {
+                coerce_input_object(ectx, input_type, value)
+            }
+            Some(TypeDefinition::ScalarTypeDefinition(_)) => {
+                if let Some(validator) = ectx.scalar_validator(name) {
+                    validate_scalar(name, validator, &value)?;
+                }
+
+                match ectx.scalar_codec(name) {
+                    Some(codec) => codec.parse(value),
+                    None => Ok(value),
+                }
+            }
+            _ => Ok(value),
+        },
+    }
+}
+
+fn coerce_enum(enum_type: &EnumTypeDefinition, value: ConstValue) -> Result<ConstValue> {
+    let name = match &value {
+        ConstValue::Enum(name) => name.as_str(),
+        ConstValue::String(name) => name.as_str(),
+        _ => {
+            return Err(anyhow!(
+                "invalid value for enum {}: {}",
+                enum_type.name(),
+                value
+            ))
+        }
+    };
+
+    if enum_type.value(name).is_none() {
+        let expected = enum_type
+            .values()
+            .map(|value_def| value_def.enum_value())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        return Err(anyhow!(
+            "invalid value {} for enum {}, expected one of: {}",
+            name,
+            enum_type.name(),
+            expected
+        ));
+    }
+
+    Ok(ConstValue::Enum(Name::interned(name)))
+}
+
+//nb: anything other than an object is left for the resolver's conversion to
+//reject, like any other mismatched argument
+fn coerce_input_object(
+    ectx: &ExecCtx,
+    input_type: &InputObjectTypeDefinition,
+    value: ConstValue,
+) -> Result<ConstValue> {
+    let ConstValue::Object(fields) = value else {
+        return Ok(value);
+    };
+
+    let fields = fields
+        .into_iter()
+        .map(|(name, value)| match input_type.field(name.as_str()) {
+            Some(field_def) => Ok((
+                Name::interned(field_def.name()),
+                coerce_input(ectx, field_def.ty(), value)?,
+            )),
+            None => Ok((name, value)),
+        })
+        .collect::<Result<IndexMap<_, _>>>()?;
+
+    let one_of = input_type.directives().iter().any(|d| d.name() == "oneOf");
+
+    if one_of {
+        check_one_of(input_type, &fields)?;
+    }
+
+    Ok(ConstValue::Object(fields))
+}
+
+/// A `@oneOf` input object must be given exactly one field, and it can't be
+/// `null`
+///
+/// https://github.com/graphql/graphql-spec/pull/825
+fn check_one_of(
+    input_type: &InputObjectTypeDefinition,
+    fields: &IndexMap<Name, ConstValue>,
+) -> Result<()> {
+    match fields.iter().next() {
+        Some((name, ConstValue::Null)) if fields.len() == 1 => Err(anyhow!(
+            "field {} of oneOf input object {} must not be null",
+            name,
+            input_type.name()
+        )),
+        Some(_) if fields.len() == 1 => Ok(()),
+        _ => Err(anyhow!(
+            "oneOf input object {} must be given exactly one field, got {}",
+            input_type.name(),
+            fields.len()
+        )),
+    }
+}
+
+/// Runs the registered scalar validators over the operation's variables up
+/// front, so an invalid variable fails the whole request rather than each
+/// field that uses it
+pub(crate) fn check_variables(ectx: &ExecCtx, operation: &OperationDefinition) -> Result<()> {
+    if ectx.scalar_validators.is_empty() {
+        return Ok(());
+    }
+
+    for var_def in operation.variables() {
+        if let Some(value) = ectx.variables().get(var_def.name()) {
+            validate_input(ectx, var_def.ty(), value)
+                .map_err(|err| anyhow!("variable ${}: {}", var_def.name(), err))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_input(ectx: &ExecCtx, ty: &hir::Type, value: &ConstValue) -> Result<()> {
+    match (ty, value) {
+        (_, ConstValue::Null) => Ok(()),
+        (hir::Type::NonNull { ty, .. }, value) => validate_input(ectx, ty, value),
+        (hir::Type::List { ty, .. }, ConstValue::List(items)) => items
+            .iter()
+            .try_for_each(|item| validate_input(ectx, ty, item)),
+        (hir::Type::List { ty, .. }, value) => validate_input(ectx, ty, value),
+        (hir::Type::Named { name, .. }, value) => match ectx.find_type_definition_by_name(name) {
+            Some(TypeDefinition::InputObjectTypeDefinition(input_type)) => match value {
+                ConstValue::Object(fields) => fields.iter().try_for_each(|(name, value)| {
+                    match input_type.field(name.as_str()) {
+                        Some(field_def) => validate_input(ectx, field_def.ty(), value),
+                        None => Ok(()),
+                    }
+                }),
+                _ => Ok(()),
+            },
+            Some(TypeDefinition::ScalarTypeDefinition(_)) => match ectx.scalar_validator(name) {
+                Some(validator) => validate_scalar(name, validator, value),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        },
+    }
+}
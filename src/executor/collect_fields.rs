@@ -7,11 +7,17 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::ConstValue;
 
-use super::ExecCtx;
+use super::{
+    incremental::{defer_directive, DeferredFragment},
+    ExecCtx,
+};
 
 /// Collects a selection set's fields and fragments into a flattened represention to
 /// ensure resolvers are not invoked more than once for a given field.
 ///
+/// Fragments marked `@defer` are set aside in `deferred` rather than
+/// collected, without it they're collected in place.
+///
 /// FIXME track visitedFragments according to spec
 ///
 /// https://spec.graphql.org/draft/#sec-Field-Collection
@@ -19,80 +25,263 @@ pub fn collect_fields(
     ectx: &ExecCtx,
     sel_set: &SelectionSet,
     concrete_type: &ObjectTypeDefinition,
+    deferred: Option<&mut Vec<DeferredFragment>>,
 ) -> Result<IndexMap<String, Vec<Arc<Field>>>> {
     fn inner(
         ectx: &ExecCtx,
         sel_set: &SelectionSet,
         concrete_type: &ObjectTypeDefinition,
         grouped_fields: &mut IndexMap<String, Vec<Arc<Field>>>,
+        mut deferred: Option<&mut Vec<DeferredFragment>>,
     ) -> Result<()> {
         for sel in sel_set.selection() {
             if should_skip(sel, ectx.variables())? || !should_include(sel, ectx.variables())? {
                 continue;
             }
 
-            match sel {
+            let (type_cond, frag_sel_set) = match sel {
                 Selection::Field(field) => {
                     let response_key = field.alias().map(|a| a.0.as_str()).unwrap_or(field.name());
-                    let response_key = response_key.to_owned();
-                    let field_entry = grouped_fields.entry(response_key);
-                    field_entry.or_default().push(field.clone());
-                    //TODO what happens when grouped fields have arguments that differ? need to check for that case and handle explictly
+                    let group = grouped_fields.entry(response_key.to_owned()).or_default();
+
+                    if let Some(first) = group.first() {
+                        check_can_merge(response_key, first, field)?;
+                    }
+
+                    group.push(field.clone());
+                    continue;
                 }
                 Selection::FragmentSpread(frag_spread) => {
                     let frag_def = ectx.fragment(frag_spread.name()).ok_or_else(|| {
                         anyhow!("fragment definition not found: {}", frag_spread.name())
                     })?;
 
-                    let type_cond = frag_def.type_condition();
-                    let type_cond_type =
-                        ectx.find_type_definition_by_name(type_cond)
-                            .ok_or_else(|| {
-                                anyhow!(
-                                    "fragment definition type condition type not found: {}",
-                                    type_cond
-                                )
-                            })?;
-
-                    if fragment_type_applies(ectx, concrete_type, &type_cond_type)? {
-                        inner(
-                            ectx,
-                            frag_def.selection_set(),
-                            concrete_type,
-                            grouped_fields,
-                        )?;
-                    }
+                    (Some(frag_def.type_condition()), frag_def.selection_set())
                 }
                 Selection::InlineFragment(inline_frag) => {
-                    if let Some(type_cond) = inline_frag.type_condition() {
-                        let type_cond_type = ectx
-                            .find_type_definition_by_name(type_cond)
-                            .ok_or_else(|| {
-                                anyhow!(
-                                    "inline fragment type condition type not found: {}",
-                                    type_cond
-                                )
-                            })?;
-
-                        if fragment_type_applies(ectx, concrete_type, &type_cond_type)? {
-                            inner(
+                    (inline_frag.type_condition(), inline_frag.selection_set())
+                }
+            };
+
+            //nb: an inline fragment without a type condition always applies
+            if let Some(type_cond) = type_cond {
+                let type_cond_type =
+                    ectx.find_type_definition_by_name(type_cond)
+                        .ok_or_else(|| {
+                            anyhow!("fragment type condition type not found: {}", type_cond)
+                        })?;
+
+                if !fragment_type_applies(ectx, concrete_type, &type_cond_type)? {
+                    continue;
+                }
+            }
+
+            match (
+                defer_directive(ectx, sel_directives(sel), frag_sel_set)?,
+                deferred.as_deref_mut(),
+            ) {
+                (Some(fragment), Some(deferred)) => deferred.push(fragment),
+                (_, deferred) => {
+                    inner(ectx, frag_sel_set, concrete_type, grouped_fields, deferred)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut grouped_fields = IndexMap::new();
+    inner(ectx, sel_set, concrete_type, &mut grouped_fields, deferred)?;
+    Ok(grouped_fields)
+}
+
+/// Collects the merged sub-selections of a field group, i.e. every field that
+/// shares a response key, so that `a { b } a { c }` resolves both `b` and `c`
+/// beneath a single `a`.
+///
+/// https://spec.graphql.org/draft/#CollectSubfields()
+pub fn collect_subfields(
+    ectx: &ExecCtx,
+    fields: &[Arc<Field>],
+    concrete_type: &ObjectTypeDefinition,
+    mut deferred: Option<&mut Vec<DeferredFragment>>,
+) -> Result<IndexMap<String, Vec<Arc<Field>>>> {
+    let mut grouped_fields: IndexMap<String, Vec<Arc<Field>>> = IndexMap::new();
+
+    for field in fields {
+        for (response_key, sub_fields) in collect_fields(
+            ectx,
+            field.selection_set(),
+            concrete_type,
+            deferred.as_deref_mut(),
+        )? {
+            grouped_fields
+                .entry(response_key)
+                .or_default()
+                .extend(sub_fields);
+        }
+    }
+
+    Ok(grouped_fields)
+}
+
+/// Depth of an operation's selection set, see [`selection_depth`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SelectionDepth {
+    /// Deepest nesting of regular fields
+    pub depth: usize,
+    /// Deepest nesting of fields at or beneath an introspection meta-field
+    pub introspection_depth: usize,
+}
+
+/// Measures how deeply a selection set nests, expanding fragment spreads and
+/// inline fragments in place (they don't add a level of their own).
+///
+/// Type conditions and `@skip`/`@include` aren't evaluated, so this is the
+/// worst case depth the operation could reach.
+pub fn selection_depth(ectx: &ExecCtx, sel_set: &SelectionSet) -> Result<SelectionDepth> {
+    //nb: each fragment's depth is measured once and reused (separately beneath
+    //introspection fields), otherwise fragments that spread each other several
+    //times over take exponential time to walk
+    fn inner<'a>(
+        ectx: &'a ExecCtx,
+        sel_set: &'a SelectionSet,
+        introspection: bool,
+        visiting: &mut Vec<&'a str>,
+        fragments: &mut HashMap<(&'a str, bool), SelectionDepth>,
+    ) -> Result<SelectionDepth> {
+        let mut depth = SelectionDepth::default();
+
+        for sel in sel_set.selection() {
+            let sel_depth = match sel {
+                Selection::Field(field) => {
+                    let introspection = introspection || field.name().starts_with("__");
+                    let child = inner(
+                        ectx,
+                        field.selection_set(),
+                        introspection,
+                        visiting,
+                        fragments,
+                    )?;
+
+                    if introspection {
+                        SelectionDepth {
+                            depth: 0,
+                            introspection_depth: child.introspection_depth + 1,
+                        }
+                    } else {
+                        SelectionDepth {
+                            depth: child.depth + 1,
+                            introspection_depth: child.introspection_depth,
+                        }
+                    }
+                }
+                Selection::FragmentSpread(frag_spread) => {
+                    //nb: validation rejects fragment cycles, this only guards
+                    //against recursing forever if one slips through
+                    if visiting.contains(&frag_spread.name()) {
+                        continue;
+                    }
+
+                    let frag_def = ectx.fragment(frag_spread.name()).ok_or_else(|| {
+                        anyhow!("fragment definition not found: {}", frag_spread.name())
+                    })?;
+
+                    let key = (frag_def.name(), introspection);
+                    match fragments.get(&key) {
+                        Some(frag_depth) => *frag_depth,
+                        None => {
+                            visiting.push(frag_def.name());
+                            let frag_depth = inner(
                                 ectx,
-                                inline_frag.selection_set(),
-                                concrete_type,
-                                grouped_fields,
+                                frag_def.selection_set(),
+                                introspection,
+                                visiting,
+                                fragments,
                             )?;
+                            visiting.pop();
+
+                            fragments.insert(key, frag_depth);
+                            frag_depth
                         }
                     }
                 }
+                Selection::InlineFragment(inline_frag) => inner(
+                    ectx,
+                    inline_frag.selection_set(),
+                    introspection,
+                    visiting,
+                    fragments,
+                )?,
             };
+
+            depth.depth = depth.depth.max(sel_depth.depth);
+            depth.introspection_depth =
+                depth.introspection_depth.max(sel_depth.introspection_depth);
         }
 
-        Ok(())
+        Ok(depth)
     }
 
-    let mut grouped_fields = IndexMap::new();
-    inner(ectx, sel_set, concrete_type, &mut grouped_fields)?;
-    Ok(grouped_fields)
+    inner(ectx, sel_set, false, &mut Vec::new(), &mut HashMap::new())
+}
+
+/// Fields sharing a response key are resolved once, so they must be the same
+/// field given the same arguments. Arguments are compared as written:
+/// variables by name, literals by value.
+///
+/// https://spec.graphql.org/October2021/#sec-Field-Selection-Merging
+fn check_can_merge(response_key: &str, first: &Field, field: &Field) -> Result<()> {
+    if first.name() != field.name() {
+        return Err(anyhow!(
+            "fields {} and {} conflict because they share the response key {}",
+            first.name(),
+            field.name(),
+            response_key
+        ));
+    }
+
+    let same_arguments = first.arguments().len() == field.arguments().len()
+        && first.arguments().iter().all(|arg| {
+            field
+                .arguments()
+                .iter()
+                .any(|other| arg.name() == other.name() && same_value(arg.value(), other.value()))
+        });
+
+    if !same_arguments {
+        return Err(anyhow!(
+            "fields with response key {} conflict because they have differing arguments",
+            response_key
+        ));
+    }
+
+    Ok(())
+}
+
+fn same_value(a: &hir::Value, b: &hir::Value) -> bool {
+    use hir::Value;
+
+    match (a, b) {
+        (Value::Variable(a), Value::Variable(b)) => a.name() == b.name(),
+        (Value::Object { value: a, .. }, Value::Object { value: b, .. }) => {
+            a.len() == b.len()
+                && a.iter().all(|(name, a)| {
+                    b.iter()
+                        .any(|(other, b)| name.src() == other.src() && same_value(a, b))
+                })
+        }
+        (Value::List { value: a, .. }, Value::List { value: b, .. }) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b))
+        }
+        (Value::Boolean { value: a, .. }, Value::Boolean { value: b, .. }) => a == b,
+        (Value::String { value: a, .. }, Value::String { value: b, .. }) => a == b,
+        (Value::Int { value: a, .. }, Value::Int { value: b, .. }) => a.get() == b.get(),
+        (Value::Float { value: a, .. }, Value::Float { value: b, .. }) => a.get() == b.get(),
+        (Value::Enum { value: a, .. }, Value::Enum { value: b, .. }) => a.src() == b.src(),
+        (Value::Null { .. }, Value::Null { .. }) => true,
+        _ => false,
+    }
 }
 
 fn sel_directives(selection: &Selection) -> &[Directive] {
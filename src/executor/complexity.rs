@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use apollo_compiler::hir::{self, Field, FieldDefinition, Selection, SelectionSet};
+
+use super::{ExecCtx, ExecutorConfig};
+use crate::value::ConstValue;
+
+/// Arguments that multiply the cost of a list field's selections when it
+/// has no `@cost(multipliers:)` of its own
+const DEFAULT_MULTIPLIERS: [&str; 2] = ["first", "limit"];
+
+/// Rejects operations costing more than the configured
+/// [`max_complexity`](super::ExecutorBuilder::max_complexity) before any
+/// resolver runs, and records the cost for the response's extensions
+pub(crate) fn check_complexity(
+    ectx: &ExecCtx,
+    config: &ExecutorConfig,
+    sel_set: &SelectionSet,
+) -> Result<()> {
+    let Some(max_complexity) = config.max_complexity else {
+        return Ok(());
+    };
+
+    let complexity = selection_complexity(ectx, sel_set)?;
+    let _ = ectx.complexity.set(complexity);
+
+    if complexity > max_complexity {
+        return Err(anyhow!(
+            "query complexity {} exceeds maximum allowed complexity of {}",
+            complexity,
+            max_complexity
+        ));
+    }
+
+    Ok(())
+}
+
+/// The cost of a selection set: each field costs its `@cost(weight:)` (1 by
+/// default) plus the cost of its own selections, all multiplied by the sum
+/// of its multiplier arguments (`first`/`limit` on list fields by default).
+///
+/// Like [`selection_depth`](super::collect_fields::selection_depth), type
+/// conditions and `@skip`/`@include` aren't evaluated so this is the worst
+/// case. Introspection fields are free, they're limited by
+/// [`max_introspection_depth`](super::ExecutorBuilder::max_introspection_depth).
+pub(crate) fn selection_complexity(ectx: &ExecCtx, sel_set: &SelectionSet) -> Result<usize> {
+    //nb: each fragment's cost is measured once and reused, otherwise
+    //fragments that spread each other several times over take exponential
+    //time to walk
+    fn inner<'a>(
+        ectx: &'a ExecCtx,
+        sel_set: &'a SelectionSet,
+        visiting: &mut Vec<&'a str>,
+        fragments: &mut HashMap<&'a str, usize>,
+    ) -> Result<usize> {
+        let mut complexity = 0usize;
+
+        for sel in sel_set.selection() {
+            let sel_complexity = match sel {
+                Selection::Field(field) if field.name().starts_with("__") => 0,
+                Selection::Field(field) => {
+                    let children = inner(ectx, field.selection_set(), visiting, fragments)?;
+                    field_cost(ectx, field, children)
+                }
+                Selection::FragmentSpread(frag_spread) => {
+                    //nb: validation rejects fragment cycles, this only guards
+                    //against recursing forever if one slips through
+                    if visiting.contains(&frag_spread.name()) {
+                        continue;
+                    }
+
+                    let frag_def = ectx.fragment(frag_spread.name()).ok_or_else(|| {
+                        anyhow!("fragment definition not found: {}", frag_spread.name())
+                    })?;
+
+                    match fragments.get(frag_def.name()) {
+                        Some(frag_complexity) => *frag_complexity,
+                        None => {
+                            visiting.push(frag_def.name());
+                            let frag_complexity =
+                                inner(ectx, frag_def.selection_set(), visiting, fragments)?;
+                            visiting.pop();
+
+                            fragments.insert(frag_def.name(), frag_complexity);
+                            frag_complexity
+                        }
+                    }
+                }
+                Selection::InlineFragment(inline_frag) => {
+                    inner(ectx, inline_frag.selection_set(), visiting, fragments)?
+                }
+            };
+
+            complexity = complexity.saturating_add(sel_complexity);
+        }
+
+        Ok(complexity)
+    }
+
+    inner(ectx, sel_set, &mut Vec::new(), &mut HashMap::new())
+}
+
+fn field_cost(ectx: &ExecCtx, field: &Field, children: usize) -> usize {
+    let Some(field_def) = ectx.field_definition(field) else {
+        return 1usize.saturating_add(children);
+    };
+
+    let cost = field_def.directives().iter().find(|d| d.name() == "cost");
+    let directive_arg = |name: &str| cost.and_then(|d| d.argument_by_name(name));
+
+    let weight = directive_arg("weight")
+        .and_then(|weight| int_value(ectx, weight))
+        .unwrap_or(1);
+
+    let multiplier = match directive_arg("multipliers") {
+        Some(hir::Value::List { value: names, .. }) => names
+            .iter()
+            .filter_map(|name| match name {
+                hir::Value::String { value: name, .. } => {
+                    argument_int(ectx, field, field_def, name)
+                }
+                _ => None,
+            })
+            .fold(0usize, usize::saturating_add),
+        _ if is_list(field_def.ty()) => DEFAULT_MULTIPLIERS
+            .iter()
+            .filter_map(|name| argument_int(ectx, field, field_def, name))
+            .fold(0usize, usize::saturating_add),
+        _ => 1,
+    };
+
+    //nb: a list without a page size (or with `first: 0`) is counted once
+    weight
+        .saturating_add(children)
+        .saturating_mul(multiplier.max(1))
+}
+
+/// Value of an integer argument, from the operation (or its variables) or
+/// the argument's default in the schema
+fn argument_int(
+    ectx: &ExecCtx,
+    field: &Field,
+    field_def: &FieldDefinition,
+    name: &str,
+) -> Option<usize> {
+    match field.arguments().iter().find(|arg| arg.name() == name) {
+        Some(arg) => int_value(ectx, arg.value()),
+        None => field_def
+            .arguments()
+            .input_values()
+            .iter()
+            .find(|arg| arg.name() == name)?
+            .default_value()
+            .and_then(|value| int_value(ectx, value)),
+    }
+}
+
+/// A non-negative integer literal or variable, negative values count as 0
+fn int_value(ectx: &ExecCtx, value: &hir::Value) -> Option<usize> {
+    let int = match value {
+        hir::Value::Int { value, .. } => i64::from(value.to_i32_checked()?),
+        hir::Value::Variable(var) => match ectx.variables().get(var.name())? {
+            ConstValue::Number(num) => num.as_i64()?,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(int.max(0) as usize)
+}
+
+fn is_list(ty: &hir::Type) -> bool {
+    match ty {
+        hir::Type::List { .. } => true,
+        hir::Type::NonNull { ty, .. } => is_list(ty),
+        hir::Type::Named { .. } => false,
+    }
+}
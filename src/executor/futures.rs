@@ -3,30 +3,117 @@
 //! for this process which can be challenging when working with async/await.
 
 use crate::{
-    resolver::{ObjectResolver, Resolved},
+    error::{GraphQLError, PathSegment, ResponsePath},
+    introspection::IspObjectResolver,
+    middleware::{FieldInfo, Next},
+    resolver::{ObjectResolver, Resolved, ValueObject},
+    response::IncrementalResult,
+    scalar::validate_scalar,
     value::{self, ConstValue},
     Ctx,
 };
 use anyhow::{anyhow, Result};
 use apollo_compiler::hir::{self, Field, SelectionSet};
-use futures::{stream::FuturesOrdered, TryStreamExt};
+use futures::{
+    stream::{FuturesOrdered, FuturesUnordered},
+    FutureExt, StreamExt,
+};
 use indexmap::IndexMap;
 use std::{
+    any::Any,
     future::Future,
+    panic::AssertUnwindSafe,
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
     time::Instant,
 };
-use tracing::{debug, span, Instrument, Level};
+use tracing::{debug, span, warn, Instrument, Level};
+
+/// Field future tagged with the response key it resolves
+type KeyedFieldFuture<'a> = Pin<Box<dyn Future<Output = (value::Name, Completed)> + Send + 'a>>;
+
+/// Future that completes a field (or list element) to its value
+type ValueFuture<'a> = Pin<Box<dyn Future<Output = Completed> + Send + 'a>>;
+
+/// Outcome of completing a field, list element or selection set
+///
+/// Field errors don't abort execution. They're collected here as they bubble
+/// up (each level prefixing its path segment) while the failed position
+/// becomes `null`, or if it's non-null, `value` is `None` and the null
+/// propagates to the nearest nullable parent.
+///
+/// https://spec.graphql.org/October2021/#sec-Handling-Field-Errors
+#[derive(Debug, Default)]
+pub struct Completed {
+    pub value: Option<ConstValue>,
+    pub errors: Vec<GraphQLError>,
+}
+
+impl Completed {
+    fn value(value: ConstValue) -> Self {
+        Self {
+            value: Some(value),
+            errors: Vec::new(),
+        }
+    }
+
+    fn error(err: GraphQLError) -> Self {
+        Self {
+            value: None,
+            errors: vec![err],
+        }
+    }
+
+    fn prepend_path(self, segment: PathSegment) -> Self {
+        Self {
+            value: self.value,
+            errors: self
+                .errors
+                .into_iter()
+                .map(|err| err.prepend_path(segment.clone()))
+                .collect(),
+        }
+    }
+
+    /// Applies the nullability of the position's type: a `null` in a non-null
+    /// position is an error, and a failure in a nullable one stops propagating
+    fn at_position(mut self, ty: &hir::Type, field: &Field) -> Self {
+        match (&self.value, ty.is_non_null()) {
+            (Some(ConstValue::Null), true) => {
+                self.value = None;
+                self.errors.push(GraphQLError::new(format!(
+                    "cannot return null for non-null field {}.{}",
+                    field.parent_type_name().unwrap_or_default(),
+                    field.name()
+                )));
+            }
+            (None, false) => self.value = Some(ConstValue::Null),
+            _ => {}
+        }
+
+        self
+    }
+}
 
 pub struct ExecuteSelectionSet<'a> {
-    field_futs: IndexMap<String, Pin<Box<dyn Future<Output = Result<ConstValue>> + Send + 'a>>>,
+    //nb: FuturesUnordered only re-polls the field futures that have actually
+    //been woken, so a wide selection set isn't re-polled in full on every wakeup
+    field_futs: FuturesUnordered<KeyedFieldFuture<'a>>,
     output_map: Option<IndexMap<value::Name, ConstValue>>,
-    field_errors: IndexMap<String, anyhow::Error>,
+    field_errors: Vec<GraphQLError>,
+    null_propagated: bool,
+    deferred: Vec<DeferredFragment>,
 }
 
-use super::{collect_fields::collect_fields, ExecCtx};
+use super::{
+    collect_fields::{collect_fields, collect_subfields},
+    incremental::{stream_directive, DeferredFragment, StreamDirective},
+    ExecCtx,
+};
+
+/// All fields in a selection set that share a response key
+pub(crate) type FieldGroup = Arc<[Arc<Field>]>;
 
 impl<'a> ExecuteSelectionSet<'a> {
     pub fn new(
@@ -35,38 +122,95 @@ impl<'a> ExecuteSelectionSet<'a> {
         object_ty: Arc<hir::ObjectTypeDefinition>,
         sel_set: &'a SelectionSet,
     ) -> Result<Pin<Box<Self>>> {
-        let output_map = Some(IndexMap::new());
-        let mut field_errors = IndexMap::new();
-        let mut field_futs = IndexMap::new();
-        let collected_fields = collect_fields(ectx, sel_set, &object_ty)?;
+        Self::fragment(
+            ectx,
+            obj_resolver,
+            object_ty,
+            sel_set,
+            ResponsePath::default(),
+        )
+    }
+
+    /// Executes a (deferred) fragment's selection set against an object
+    /// beneath `path`
+    pub(crate) fn fragment(
+        ectx: &'a ExecCtx,
+        obj_resolver: &'a dyn ObjectResolver,
+        object_ty: Arc<hir::ObjectTypeDefinition>,
+        sel_set: &SelectionSet,
+        path: ResponsePath,
+    ) -> Result<Pin<Box<Self>>> {
+        let mut deferred = Vec::new();
+        let collected_fields = collect_fields(ectx, sel_set, &object_ty, Some(&mut deferred))?;
+        Self::from_collected_fields(ectx, obj_resolver, collected_fields, deferred, path)
+    }
+
+    /// Executes the merged selection sets of a field group against the object
+    /// the group resolved to
+    pub fn merged(
+        ectx: &'a ExecCtx,
+        obj_resolver: &'a dyn ObjectResolver,
+        object_ty: Arc<hir::ObjectTypeDefinition>,
+        fields: &[Arc<Field>],
+        path: ResponsePath,
+    ) -> Result<Pin<Box<Self>>> {
+        let mut deferred = Vec::new();
+        let collected_fields = collect_subfields(ectx, fields, &object_ty, Some(&mut deferred))?;
+        Self::from_collected_fields(ectx, obj_resolver, collected_fields, deferred, path)
+    }
+
+    /// Fragments marked `@defer` that were left out of this selection set,
+    /// they're up to whoever owns the resolver to execute, see
+    /// [`defer_fragments`]
+    pub(crate) fn take_deferred(&mut self) -> Vec<DeferredFragment> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    fn from_collected_fields(
+        ectx: &'a ExecCtx,
+        obj_resolver: &'a dyn ObjectResolver,
+        collected_fields: IndexMap<String, Vec<Arc<Field>>>,
+        deferred: Vec<DeferredFragment>,
+        path: ResponsePath,
+    ) -> Result<Pin<Box<Self>>> {
+        let mut output_map = IndexMap::with_capacity(collected_fields.len());
+        let field_futs = FuturesUnordered::new();
 
-        //TODO merge selection sets in field groups
         for (response_key, fields) in collected_fields {
-            let field = fields
-                .first()
-                .ok_or(anyhow!(
+            if fields.is_empty() {
+                return Err(anyhow!(
                     "response key {} in collected fields contained an empty set",
                     response_key
-                ))?
-                .clone();
+                ));
+            }
 
-            let field_fut = resolve_field(ectx, obj_resolver, field.clone());
+            //nb: only field names are interned, aliases are whatever the
+            //client chose
+            let response_key = if fields[0].alias().is_some() {
+                value::Name::new(response_key)
+            } else {
+                value::Name::interned(response_key)
+            };
 
-            //FIXME fields out of order when constructed in this way, need to pre-arrange fields in ::new()
-            match field_fut {
-                Ok(ffut) => {
-                    field_futs.insert(response_key, ffut);
-                }
-                Err(err) => {
-                    field_errors.insert(response_key, err);
-                }
-            }
+            //nb: fields complete in any order, each key is reserved up front so
+            //the response keeps the order the fields were selected in
+            output_map.insert(response_key.clone(), ConstValue::Null);
+
+            let field_path = path.child(PathSegment::Field(response_key.clone()));
+            let field_fut = resolve_field(ectx, obj_resolver, fields.into(), field_path);
+            let keyed_fut: KeyedFieldFuture<'a> =
+                Box::pin(field_fut.map(move |completed| (response_key, completed)));
+            field_futs.push(keyed_fut);
         }
 
+        let output_map = Some(output_map);
+
         let fut = Self {
             field_futs,
             output_map,
-            field_errors,
+            field_errors: Vec::new(),
+            null_propagated: false,
+            deferred,
         };
 
         Ok(Box::pin(fut))
@@ -74,152 +218,599 @@ impl<'a> ExecuteSelectionSet<'a> {
 }
 
 impl<'a> Future for ExecuteSelectionSet<'a> {
-    type Output = Result<ConstValue>;
+    type Output = Completed;
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        //nb: reference gymnastics necessary here because of
-        //mut borrowing multiple fields behind Pin, see: https://github.com/rust-lang/rust/issues/89982
-        let self_mut = &mut *self;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let self_mut = self.get_mut();
         let output_map = self_mut.output_map.as_mut().expect("output_map missing");
-        let field_errors = &mut self_mut.field_errors;
-        let field_futs = &mut self_mut.field_futs;
 
-        field_futs.retain(|k, f| {
-            let field_poll = f.as_mut().poll(cx);
+        loop {
+            match self_mut.field_futs.poll_next_unpin(cx) {
+                Poll::Ready(Some((response_key, completed))) => {
+                    let completed =
+                        completed.prepend_path(PathSegment::Field(response_key.clone()));
 
-            match field_poll {
-                Poll::Ready(Ok(field_val)) => {
-                    output_map.insert(value::Name::new(k), field_val);
-                    false
-                }
-                Poll::Ready(Err(field_err)) => {
-                    field_errors.insert(k.clone(), field_err);
-                    false
+                    self_mut.field_errors.extend(completed.errors);
+
+                    match completed.value {
+                        Some(field_val) => {
+                            output_map.insert(response_key, field_val);
+                        }
+                        None => self_mut.null_propagated = true,
+                    }
                 }
-                Poll::Pending => true,
+                Poll::Ready(None) => break,
+                Poll::Pending => return Poll::Pending,
             }
-        });
+        }
 
-        let poll = if self.field_futs.is_empty() {
-            if !self.field_errors.is_empty() {
-                Poll::Ready(Err(anyhow!("field errors: {:?}", self.field_errors)))
-            } else {
-                let result = self.output_map.take().expect("output map state error");
-                Poll::Ready(Ok(result.into())) //TODO remove clone
-            }
-        } else {
-            Poll::Pending
-        };
+        let result = self_mut.output_map.take().expect("output map state error");
 
-        poll
+        Poll::Ready(Completed {
+            value: (!self_mut.null_propagated).then(|| result.into()),
+            errors: std::mem::take(&mut self_mut.field_errors),
+        })
     }
 }
 
 fn resolve_field<'a>(
     ectx: &'a ExecCtx,
     resolver: &'a dyn ObjectResolver,
-    field: Arc<Field>,
-) -> Result<Pin<Box<dyn Future<Output = Result<ConstValue>> + Send + 'a>>> {
+    fields: FieldGroup,
+    path: ResponsePath,
+) -> ValueFuture<'a> {
+    //nb: fields in a group are guaranteed by validation to have the same name
+    //and arguments, so the first one stands in for the group when resolving
+    let field = fields[0].clone();
     let span = span!(Level::INFO, "field", "{}", field.name());
-    Ok(Box::pin(
+    Box::pin(
         async move {
+            let field_ty = match ectx.field_type(&field) {
+                Some(field_ty) => field_ty,
+                None => {
+                    let err = GraphQLError::new(format!(
+                        "field definition not found for field: {}",
+                        field.name()
+                    ));
+                    return Completed::error(ectx.locate(err, &field));
+                }
+            };
+
+            warn_if_deprecated(ectx, &field);
+
             let ctx = Ctx {
-                variables: ectx.variables.clone(),
-                field: field.clone(),
+                ectx: ectx.clone(),
+                fields: fields.clone(),
+                path: path.clone(),
+            };
+
+            let info = FieldInfo {
+                field: ctx.field(),
+                ty: field_ty,
+                path: &ctx.path,
+            };
+
+            for observer in ectx.observers.iter() {
+                observer.on_field_start(&info);
+            }
+
+            //nb: only held while the resolver runs, not while its value is
+            //completed, so nested fields never wait on their parent's permit
+            let permit = match &ectx.resolver_permits {
+                Some(permits) => permits.acquire().await.ok(),
+                None => None,
             };
 
             let start = Instant::now();
-            let resolved = resolver.resolve_field(&ctx, field.name()).await?;
+            //nb: a panicking resolver only fails its own field rather than
+            //taking down the execution task (and every other field with it)
+            let resolved = AssertUnwindSafe(call_resolver(ectx, resolver, &ctx, &info))
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|panic| Err(panic_error("resolver", panic).into()));
             let self_end = Instant::now();
-            let v = resolve_to_value(ectx, field, resolved).await;
+            drop(permit);
+
+            let mut source = None;
+            let completed = match resolved {
+                Ok(resolved) if ectx.incremental.is_some() => {
+                    match stream_directive(ectx, &field) {
+                        Ok(Some(stream)) => {
+                            stream_list(ectx, fields, field_ty, resolved, path, stream).await
+                        }
+                        Ok(None) => resolve_to_value(ectx, fields, field_ty, resolved, path).await,
+                        Err(err) => Completed::error(err.into()).at_position(field_ty, &field),
+                    }
+                }
+                Ok(resolved) => resolve_to_value(ectx, fields, field_ty, resolved, path).await,
+                Err(err) => {
+                    let completed =
+                        Completed::error(GraphQLError::from(&err)).at_position(field_ty, &field);
+                    source = Some(err);
+                    completed
+                }
+            };
+
             let end = Instant::now();
             debug!(
                 self_us = self_end.duration_since(start).as_micros(),
                 full_us = end.duration_since(start).as_micros(),
                 "resolve complete",
             );
-            v
+
+            //nb: errors raised beneath a nested selection set were already
+            //located (and observed) at their own field, the rest were raised
+            //completing this one
+            let errors = completed
+                .errors
+                .into_iter()
+                .map(|err| {
+                    if !err.locations().is_empty() {
+                        return err;
+                    }
+
+                    let err = ectx.locate(err, &field);
+                    for observer in ectx.observers.iter() {
+                        observer.on_error(&info, &err);
+                    }
+                    ectx.map_field_error(err, &field, &ctx.path, source.as_ref())
+                })
+                .collect();
+
+            for observer in ectx.observers.iter() {
+                observer.on_field_end(&info, end.duration_since(start));
+            }
+
+            if let Some(tracing) = &ectx.tracing {
+                tracing.record(&info, start, end);
+            }
+
+            Completed {
+                value: completed.value,
+                errors,
+            }
         }
         .instrument(span),
-    ))
+    )
+}
+
+/// Logs a warning when a field marked `@deprecated` in the schema is
+/// resolved, so operators can tell which deprecated fields are still in use
+/// before removing them
+fn warn_if_deprecated(ectx: &ExecCtx, field: &Field) {
+    let Some(field_def) = ectx.field_definition(field) else {
+        return;
+    };
+
+    let Some(deprecated) = field_def
+        .directives()
+        .iter()
+        .find(|d| d.name() == "deprecated")
+    else {
+        return;
+    };
+
+    let reason = match deprecated.argument_by_name("reason") {
+        Some(hir::Value::String { value, .. }) => value.as_str(),
+        _ => "No longer supported",
+    };
+
+    warn!(
+        field = field.name(),
+        parent_type = field.parent_type_name(),
+        reason,
+        "deprecated field selected"
+    );
+}
+
+/// Calls the resolver for the field in `ctx`, wrapped in any middleware
+async fn call_resolver(
+    ectx: &ExecCtx,
+    resolver: &dyn ObjectResolver,
+    ctx: &Ctx,
+    info: &FieldInfo<'_>,
+) -> Result<Resolved> {
+    let next = Next {
+        middleware: &ectx.middleware,
+        resolver,
+        info,
+    };
+
+    next.run(ctx).await
+}
+
+/// Runs a value through the codec registered for its scalar type, if any,
+/// numbers returned for `Int`, `Float` and `ID` are coerced to their type (see
+/// [`coerce_number`]). The result is checked by the scalar's registered
+/// validator. Lists resolved as a single value are serialized
+/// element-wise.
+fn serialize_scalar(ectx: &ExecCtx, ty: &hir::Type, value: ConstValue) -> Result<ConstValue> {
+    match (ty, value) {
+        (_, ConstValue::Null) => Ok(ConstValue::Null),
+        (hir::Type::NonNull { ty, .. }, value) => serialize_scalar(ectx, ty, value),
+        (hir::Type::List { ty, .. }, ConstValue::List(items)) => items
+            .into_iter()
+            .map(|item| serialize_scalar(ectx, ty, item))
+            .collect::<Result<Vec<_>>>()
+            .map(ConstValue::List),
+        (hir::Type::Named { name, .. }, value) => {
+            let value = match ectx.scalar_codec(name) {
+                Some(codec) => codec.serialize(value)?,
+                None => coerce_number(name, value)?,
+            };
+
+            if let Some(validator) = ectx.scalar_validator(name) {
+                validate_scalar(name, validator, &value)?;
+            }
+
+            Ok(value)
+        }
+        (_, value) => Ok(value),
+    }
+}
+
+/// Result coercion of the built-in numeric scalars. An `Int` is always
+/// integral, an integral float (`4.0`) is accepted but one with a fraction
+/// or outside the 32-bit range is a field error. A `Float` always has a
+/// decimal point in the response, so an integer returned for one is
+/// serialized as `1.0` rather than `1`. An integer returned for an `ID` is
+/// serialized as a string.
+///
+/// https://spec.graphql.org/October2021/#sec-Int.Result-Coercion
+fn coerce_number(name: &str, value: ConstValue) -> Result<ConstValue> {
+    match (name, value) {
+        ("Int", value @ ConstValue::Number(_)) => value
+            .coerce_to_int()
+            .and_then(|int| i32::try_from(int).ok())
+            .map(ConstValue::from)
+            .ok_or_else(|| anyhow!("Int cannot represent value: {}", value)),
+        ("Float", value @ ConstValue::Number(_)) => value
+            .coerce_to_float()
+            .and_then(value::Number::from_f64)
+            .map(ConstValue::Number)
+            .ok_or_else(|| anyhow!("Float cannot represent value: {}", value)),
+        ("ID", ConstValue::Number(num)) if num.is_i64() || num.is_u64() => {
+            Ok(ConstValue::String(num.to_string()))
+        }
+        (_, value) => Ok(value),
+    }
+}
+
+/// Logs a caught panic and converts it into an error that doesn't leak the
+/// panic message (or whatever else the payload holds) to clients
+pub(super) fn panic_error(source: &str, panic: Box<dyn Any + Send>) -> GraphQLError {
+    let message = if let Some(msg) = panic.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg
+    } else {
+        "unknown panic"
+    };
+
+    tracing::error!("{} panicked: {}", source, message);
+
+    GraphQLError::new("internal server error").extension("code", "INTERNAL_SERVER_ERROR")
 }
 
 fn resolve_to_value<'a>(
     ectx: &'a ExecCtx,
-    field: Arc<Field>,
+    fields: FieldGroup,
+    ty: &'a hir::Type,
     resolved: Resolved,
-) -> Pin<Box<dyn Future<Output = Result<ConstValue>> + Send + 'a>> {
-    use futures::FutureExt;
-    use hir::TypeDefinition::*;
+    path: ResponsePath,
+) -> ValueFuture<'a> {
+    let field = fields[0].clone();
+
+    //nb: object values (e.g. from `Resolved::from_serialize`) returned for
+    //composite fields are completed against the selection set like resolvers
+    let resolved = match resolved.force() {
+        Resolved::Value(ConstValue::Object(map)) if ectx.is_composite_type(&ty.name()) => {
+            Resolved::object(ValueObject(map))
+        }
+        Resolved::Value(ConstValue::List(items)) if ectx.is_composite_type(&ty.name()) => {
+            items.into_iter().collect()
+        }
+        resolved => resolved,
+    };
 
-    match resolved {
-        Resolved::Value(v) => Box::pin(futures::future::ready(Ok(v))),
+    let completed: ValueFuture<'a> = match resolved {
+        Resolved::Value(v) => {
+            let completed = match serialize_scalar(ectx, ty, v) {
+                Ok(v) => Completed::value(v),
+                Err(err) => Completed::error(err.into()),
+            };
+            Box::pin(futures::future::ready(completed))
+        }
         Resolved::Array(arr) => {
+            let Some(item_ty) = list_item_type(ty) else {
+                let err = GraphQLError::new(format!(
+                    "type mismatch: list returned for field {} of type {}",
+                    field.name(),
+                    ty.name()
+                ));
+                return Box::pin(futures::future::ready(Completed::error(err)));
+            };
+
             let mut futs = FuturesOrdered::new();
 
-            let mut ix = 0;
-            for element in arr {
+            for (ix, element) in arr.into_iter().enumerate() {
                 let span = span!(Level::DEBUG, "ix", "{}", ix);
-                let fut = resolve_to_value(ectx, field.clone(), element).instrument(span);
+                let item_path = path.child(PathSegment::Index(ix));
+                let fut = resolve_to_value(ectx, fields.clone(), item_ty, element, item_path)
+                    .map(move |completed| completed.prepend_path(PathSegment::Index(ix)))
+                    .instrument(span);
                 futs.push_back(fut);
-                ix = ix + 1;
             }
 
-            let vals = futs
-                .try_collect()
-                .map(|vs: Result<Vec<_>>| vs.map(|vs| ConstValue::List(vs))); //FIXME should not short-circuit here, need to collect errors from each element
+            Box::pin(futs.collect::<Vec<Completed>>().map(|items| {
+                let mut errors = Vec::new();
+                let mut values = Some(Vec::with_capacity(items.len()));
 
-            Box::pin(vals)
-        }
-        Resolved::Object(obj_resolver) => {
-            Box::pin(async move {
-                let field_def = ectx.field_definition(&field).ok_or_else(|| {
-                    anyhow!(
-                        "field definition not found for field: {:#?}",
-                        field.as_ref()
-                    )
-                })?;
-
-                let field_ty = field_def.ty();
-
-                let field_type_def = ectx
-                    .find_type_definition_by_name(&field_ty.name()) // TODO why String instead of &str?
-                    .ok_or_else(|| anyhow!("field type definition not found"))?;
-
-                let object_ty = match field_type_def {
-                    ObjectTypeDefinition(o) => o,
-                    InterfaceTypeDefinition(iface) => {
-                        let type_name =
-                            obj_resolver.resolve_type_name().await?.ok_or_else(|| {
-                                anyhow!(
-                                    "resolver did not return concrete type for {}",
-                                    iface.name()
-                                )
-                            })?;
-
-                        ectx.find_object_type_definition(type_name).ok_or_else(|| {
-                            anyhow!("concrete object type not found: {}", type_name)
-                        })?
+                for item in items {
+                    errors.extend(item.errors);
+
+                    //nb: a non-null element that failed nulls out the whole list
+                    match (item.value, values.as_mut()) {
+                        (Some(value), Some(values)) => values.push(value),
+                        (None, _) => values = None,
+                        _ => {}
                     }
-                    _ => return Err(anyhow!("type mismatch: object type expected")),
-                };
+                }
+
+                Completed {
+                    value: values.map(ConstValue::List),
+                    errors,
+                }
+            }))
+        }
+        Resolved::Object(obj_resolver) => Box::pin(async move {
+            complete_object(ectx, fields, ty, obj_resolver, path)
+                .await
+                .unwrap_or_else(Completed::error)
+        }),
+        Resolved::Lazy(_) => unreachable!("lazy values are forced above"),
+    };
+
+    Box::pin(completed.map(move |completed| completed.at_position(ty, &field)))
+}
+
+fn list_item_type(ty: &hir::Type) -> Option<&hir::Type> {
+    match ty {
+        hir::Type::NonNull { ty, .. } => match ty.as_ref() {
+            hir::Type::List { ty, .. } => Some(ty.as_ref()),
+            _ => None,
+        },
+        hir::Type::List { ty, .. } => Some(ty.as_ref()),
+        hir::Type::Named { .. } => None,
+    }
+}
 
-                let object_ty = Arc::new(object_ty.clone());
+/// Completes a list field marked `@stream`: its first `initialCount` items
+/// are completed inline, the rest are completed separately and queued for
+/// incremental delivery (in order, with the path of each item)
+fn stream_list<'a>(
+    ectx: &'a ExecCtx,
+    fields: FieldGroup,
+    ty: &'a hir::Type,
+    resolved: Resolved,
+    path: ResponsePath,
+    stream: StreamDirective,
+) -> ValueFuture<'a> {
+    let (Some(item_ty), Some(queue)) = (list_item_type(ty), &ectx.incremental) else {
+        return resolve_to_value(ectx, fields, ty, resolved, path);
+    };
+
+    let mut items = match resolved.force() {
+        Resolved::Array(items) => items,
+        resolved => return resolve_to_value(ectx, fields, ty, resolved, path),
+    };
+
+    let rest = items.split_off(stream.initial_count.min(items.len()));
+
+    if !rest.is_empty() {
+        let streamed_items = rest
+            .into_iter()
+            .enumerate()
+            .map(|(ix, item)| {
+                //nb: streamed items outlive this field's future, so each one
+                //owns what it needs to complete
+                let ectx = ectx.clone();
+                let fields = fields.clone();
+                let item_ty = item_ty.clone();
+                let item_path = path.child(PathSegment::Index(stream.initial_count + ix));
+                let label = stream.label.clone();
+
+                async move {
+                    let field = fields[0].clone();
+                    let completed =
+                        resolve_to_value(&ectx, fields, &item_ty, item, item_path.clone()).await;
 
-                let obj_resolver = crate::introspection::IspObjectResolver {
-                    type_def: object_ty.clone(),
-                    inner: obj_resolver.as_ref(),
-                };
+                    let info = FieldInfo {
+                        field: &field,
+                        ty: &item_ty,
+                        path: &item_path,
+                    };
 
-                let obj_fut = ExecuteSelectionSet::new(
-                    ectx,
-                    &obj_resolver,
-                    object_ty,
-                    field.selection_set(),
-                )?;
+                    //nb: like `resolve_field`, errors raised beneath the item's
+                    //selection set were already located, observed and mapped
+                    let segments = item_path.to_vec();
+                    let errors = completed
+                        .errors
+                        .into_iter()
+                        .map(|err| {
+                            let err = segments
+                                .iter()
+                                .rev()
+                                .fold(err, |err, segment| err.prepend_path(segment.clone()));
+                            if !err.locations().is_empty() {
+                                return err;
+                            }
 
-                Ok(obj_fut.await?)
+                            let err = ectx.locate(err, &field);
+                            for observer in ectx.observers.iter() {
+                                observer.on_error(&info, &err);
+                            }
+                            ectx.map_field_error(err, &field, &item_path, None)
+                        })
+                        .collect();
+
+                    IncrementalResult {
+                        items: completed.value.map(|value| vec![value]),
+                        data: None,
+                        path: segments,
+                        label,
+                        errors,
+                    }
+                }
             })
+            .collect::<FuturesOrdered<_>>();
+
+        queue.push(Box::pin(streamed_items));
+    }
+
+    resolve_to_value(ectx, fields, ty, Resolved::Array(items), path)
+}
+
+async fn complete_object(
+    ectx: &ExecCtx,
+    fields: FieldGroup,
+    ty: &hir::Type,
+    obj_resolver: Box<dyn ObjectResolver>,
+    path: ResponsePath,
+) -> Result<Completed, GraphQLError> {
+    use hir::TypeDefinition::*;
+
+    let field_type_def = ectx
+        .find_type_definition_by_name(&ty.name()) // TODO why String instead of &str?
+        .ok_or_else(|| anyhow!("field type definition not found"))?;
+
+    let object_ty = match field_type_def {
+        ObjectTypeDefinition(o) => o,
+        abstract_ty @ (InterfaceTypeDefinition(_) | UnionTypeDefinition(_)) => {
+            let abstract_name = abstract_ty.name();
+            let type_name = obj_resolver.resolve_type_name().await?.ok_or_else(|| {
+                anyhow!(
+                    "resolver did not return concrete type for {}",
+                    abstract_name
+                )
+            })?;
+
+            //nb: the concrete type drives fragment matching and `__typename`
+            //beneath this field, so it has to be a member of the abstract type
+            if !ectx.is_subtype(type_name, abstract_name) {
+                return Err(GraphQLError::new(format!(
+                    "resolved type {} is not a possible type of {}",
+                    type_name, abstract_name
+                )));
+            }
+
+            ectx.find_object_type_definition(type_name)
+                .ok_or_else(|| anyhow!("concrete object type not found: {}", type_name))?
         }
+        _ => return Err(GraphQLError::new("type mismatch: object type expected")),
+    };
+
+    let object_ty = Arc::new(object_ty.clone());
+
+    if ectx.incremental.is_none() {
+        let obj_resolver = IspObjectResolver {
+            type_def: object_ty.clone(),
+            inner: obj_resolver.as_ref(),
+        };
+
+        let obj_fut = ExecuteSelectionSet::merged(ectx, &obj_resolver, object_ty, &fields, path)?;
+        return Ok(obj_fut.await);
+    }
+
+    //nb: deferred fragments are executed after this field completes, so they
+    //share ownership of its resolver
+    let obj_resolver: Arc<dyn ObjectResolver> = obj_resolver.into();
+
+    let isp_resolver = IspObjectResolver {
+        type_def: object_ty.clone(),
+        inner: obj_resolver.as_ref(),
+    };
+
+    let mut obj_fut = ExecuteSelectionSet::merged(
+        ectx,
+        &isp_resolver,
+        object_ty.clone(),
+        &fields,
+        path.clone(),
+    )?;
+    defer_fragments(
+        ectx,
+        obj_resolver.clone(),
+        object_ty,
+        obj_fut.take_deferred(),
+        path,
+    );
+
+    Ok(obj_fut.await)
+}
+
+/// Queues the execution of fragments marked `@defer` against an object for
+/// incremental delivery, each as a payload of its own with the object's path
+pub(crate) fn defer_fragments(
+    ectx: &ExecCtx,
+    obj_resolver: Arc<dyn ObjectResolver>,
+    object_ty: Arc<hir::ObjectTypeDefinition>,
+    deferred: Vec<DeferredFragment>,
+    path: ResponsePath,
+) {
+    let Some(queue) = &ectx.incremental else {
+        return;
+    };
+
+    for fragment in deferred {
+        let ectx = ectx.clone();
+        let obj_resolver = obj_resolver.clone();
+        let object_ty = object_ty.clone();
+        let path = path.clone();
+
+        let result = async move {
+            let isp_resolver = IspObjectResolver {
+                type_def: object_ty.clone(),
+                inner: obj_resolver.as_ref(),
+            };
+
+            let completed = match ExecuteSelectionSet::fragment(
+                &ectx,
+                &isp_resolver,
+                object_ty.clone(),
+                &fragment.selection_set,
+                path.clone(),
+            ) {
+                Ok(mut fut) => {
+                    //nb: fragments deferred beneath this one follow it
+                    let nested = fut.take_deferred();
+                    defer_fragments(&ectx, obj_resolver.clone(), object_ty, nested, path.clone());
+                    fut.await
+                }
+                Err(err) => Completed::error(err.into()),
+            };
+
+            let segments = path.to_vec();
+            let errors = completed
+                .errors
+                .into_iter()
+                .map(|err| {
+                    segments
+                        .iter()
+                        .rev()
+                        .fold(err, |err, segment| err.prepend_path(segment.clone()))
+                })
+                .collect();
+
+            IncrementalResult {
+                items: None,
+                data: Some(completed.value.unwrap_or_default()),
+                path: segments,
+                label: fragment.label,
+                errors,
+            }
+        };
+
+        queue.push(Box::pin(result.into_stream()));
     }
 }
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use apollo_compiler::hir::{self, EnumValueDefinition, FieldDefinition, InputValueDefinition};
+use async_trait::async_trait;
+
+use super::ExecCtx;
+use crate::{error::GraphQLError, middleware::ResolverMiddleware, resolver::Ctx};
+
+/// Rejects fields hidden from introspection, registered with
+/// [`ExecutorBuilder::reject_hidden_fields`](super::ExecutorBuilder::reject_hidden_fields)
+pub(crate) struct RejectHidden;
+
+#[async_trait]
+impl ResolverMiddleware for RejectHidden {
+    async fn before_field(&self, ctx: &Ctx) -> Result<()> {
+        let Some(field_def) = ctx.ectx.field_definition(ctx.field()) else {
+            return Ok(());
+        };
+
+        if !ctx.ectx.is_field_hidden(field_def) {
+            return Ok(());
+        }
+
+        //nb: the same error as for a field that doesn't exist, so hidden
+        //fields can't be discovered by probing for them
+        Err(GraphQLError::new(format!(
+            "cannot query field {} on type {}",
+            field_def.name(),
+            ctx.field().parent_type_name().unwrap_or_default()
+        ))
+        .into())
+    }
+}
+
+/// Visibility of schema members marked with the directive set with
+/// [`ExecutorBuilder::hidden_directive`](super::ExecutorBuilder::hidden_directive).
+/// Anything referring to a hidden type is hidden along with it.
+impl ExecCtx {
+    fn has_hidden_directive<'a>(
+        &self,
+        mut directives: impl Iterator<Item = &'a hir::Directive>,
+    ) -> bool {
+        match &self.hidden_directive {
+            Some(hidden) => directives.any(|d| d.name() == hidden.as_ref()),
+            None => false,
+        }
+    }
+
+    pub(crate) fn is_type_hidden(&self, name: &str) -> bool {
+        use hir::TypeDefinition::*;
+
+        if self.hidden_directive.is_none() {
+            return false;
+        }
+
+        match self.find_type_definition_by_name(name) {
+            Some(ScalarTypeDefinition(ty)) => self.has_hidden_directive(ty.directives()),
+            Some(ObjectTypeDefinition(ty)) => self.has_hidden_directive(ty.directives()),
+            Some(InterfaceTypeDefinition(ty)) => self.has_hidden_directive(ty.directives()),
+            Some(UnionTypeDefinition(ty)) => self.has_hidden_directive(ty.directives()),
+            Some(EnumTypeDefinition(ty)) => self.has_hidden_directive(ty.directives()),
+            Some(InputObjectTypeDefinition(ty)) => self.has_hidden_directive(ty.directives()),
+            None => false,
+        }
+    }
+
+    pub(crate) fn is_field_hidden(&self, field_def: &FieldDefinition) -> bool {
+        self.has_hidden_directive(field_def.directives().iter())
+            || self.is_type_hidden(&field_def.ty().name())
+    }
+
+    pub(crate) fn is_input_value_hidden(&self, input_value: &InputValueDefinition) -> bool {
+        self.has_hidden_directive(input_value.directives().iter())
+            || self.is_type_hidden(&input_value.ty().name())
+    }
+
+    pub(crate) fn is_enum_value_hidden(&self, enum_value: &EnumValueDefinition) -> bool {
+        self.has_hidden_directive(enum_value.directives().iter())
+    }
+}
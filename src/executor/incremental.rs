@@ -0,0 +1,166 @@
+//! Incremental delivery: parts of a response (streamed list items and
+//! deferred fragments) that are sent after the initial payload, see
+//! [`Executor::run_incremental`](crate::Executor::run_incremental)
+
+use std::{
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use anyhow::{anyhow, Result};
+use apollo_compiler::hir::{Directive, Field, SelectionSet};
+use futures::{channel::mpsc, Stream};
+
+use super::{AbortOnDrop, ExecCtx};
+use crate::{
+    resolver::resolve_value,
+    response::{IncrementalResponse, IncrementalResult},
+    value::ConstValue,
+};
+
+/// Results that will be delivered after the initial payload
+pub(crate) type PendingResults = Pin<Box<dyn Stream<Item = IncrementalResult> + Send>>;
+
+/// Incremental work queued while executing, picked up by the task that sends
+/// payloads
+#[derive(Default)]
+pub(crate) struct IncrementalQueue(Mutex<Vec<PendingResults>>);
+
+impl IncrementalQueue {
+    pub(crate) fn push(&self, pending: PendingResults) {
+        self.0
+            .lock()
+            .expect("incremental queue lock poisoned")
+            .push(pending);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0
+            .lock()
+            .expect("incremental queue lock poisoned")
+            .is_empty()
+    }
+
+    pub(crate) fn drain(&self) -> Vec<PendingResults> {
+        std::mem::take(&mut *self.0.lock().expect("incremental queue lock poisoned"))
+    }
+}
+
+/// Arguments of a `@stream` directive on a list field
+///
+/// The directive isn't built in, schemas that support it declare:
+///
+/// ```graphql
+/// directive @stream(initialCount: Int = 0, if: Boolean = true, label: String) on FIELD
+/// ```
+pub(crate) struct StreamDirective {
+    pub(crate) initial_count: usize,
+    pub(crate) label: Option<String>,
+}
+
+/// Reads the `@stream` directive on `field`, `None` if it's absent or
+/// disabled by its `if` argument
+pub(crate) fn stream_directive(ectx: &ExecCtx, field: &Field) -> Result<Option<StreamDirective>> {
+    let Some(directive) = field.directives().iter().find(|d| d.name() == "stream") else {
+        return Ok(None);
+    };
+
+    if let Some(ConstValue::Boolean(false)) = directive_arg(ectx, directive, "if")? {
+        return Ok(None);
+    }
+
+    let initial_count = match directive_arg(ectx, directive, "initialCount")? {
+        None | Some(ConstValue::Null) => 0,
+        Some(value) => value
+            .coerce_to_int()
+            .and_then(|count| usize::try_from(count).ok())
+            .ok_or_else(|| anyhow!("@stream initialCount must be a non-negative integer"))?,
+    };
+
+    Ok(Some(StreamDirective {
+        initial_count,
+        label: label_arg(ectx, directive)?,
+    }))
+}
+
+/// A fragment marked `@defer`, its selection set is executed against the
+/// same object after the initial payload
+///
+/// The directive isn't built in, schemas that support it declare:
+///
+/// ```graphql
+/// directive @defer(if: Boolean = true, label: String) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+/// ```
+pub(crate) struct DeferredFragment {
+    pub(crate) selection_set: SelectionSet,
+    pub(crate) label: Option<String>,
+}
+
+/// Reads the `@defer` directive in a fragment's `directives`, `None` if it's
+/// absent, disabled by its `if` argument or the response isn't delivered
+/// incrementally (the fragment is then collected in place)
+pub(crate) fn defer_directive(
+    ectx: &ExecCtx,
+    directives: &[Directive],
+    selection_set: &SelectionSet,
+) -> Result<Option<DeferredFragment>> {
+    if ectx.incremental.is_none() {
+        return Ok(None);
+    }
+
+    let Some(directive) = directives.iter().find(|d| d.name() == "defer") else {
+        return Ok(None);
+    };
+
+    if let Some(ConstValue::Boolean(false)) = directive_arg(ectx, directive, "if")? {
+        return Ok(None);
+    }
+
+    Ok(Some(DeferredFragment {
+        selection_set: selection_set.clone(),
+        label: label_arg(ectx, directive)?,
+    }))
+}
+
+fn directive_arg(ectx: &ExecCtx, directive: &Directive, name: &str) -> Result<Option<ConstValue>> {
+    directive
+        .argument_by_name(name)
+        .map(|value| resolve_value(ectx.variables(), value))
+        .transpose()
+}
+
+fn label_arg(ectx: &ExecCtx, directive: &Directive) -> Result<Option<String>> {
+    Ok(match directive_arg(ectx, directive, "label")? {
+        Some(ConstValue::String(label)) => Some(label),
+        _ => None,
+    })
+}
+
+/// Payloads of an incrementally delivered response, returned by
+/// [`Executor::run_incremental`](crate::Executor::run_incremental).
+/// Dropping it stops execution.
+pub(crate) struct IncrementalPayloads {
+    pub(super) payloads: mpsc::UnboundedReceiver<IncrementalResponse>,
+    pub(super) _task: Option<AbortOnDrop<()>>,
+}
+
+impl Stream for IncrementalPayloads {
+    type Item = IncrementalResponse;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.payloads).poll_next(cx)
+    }
+}
+
+/// Drops whatever is left in the queue when delivery stops early, the queued
+/// streams hold on to the execution context that holds the queue
+pub(crate) struct ClearQueueOnDrop(pub(crate) ExecCtx);
+
+impl Drop for ClearQueueOnDrop {
+    fn drop(&mut self) {
+        if let Some(queue) = &self.0.incremental {
+            queue.drain();
+        }
+    }
+}
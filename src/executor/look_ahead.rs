@@ -0,0 +1,134 @@
+use anyhow::Result;
+use apollo_compiler::hir::{Field, ObjectTypeDefinition, TypeDefinition};
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+use crate::{
+    resolver::resolve_value,
+    value::{ConstValue, Name},
+};
+
+use super::{coerce_input, collect_fields::collect_subfields, ExecCtx};
+
+/// A field selected beneath the one being resolved, see
+/// [`Ctx::selected_fields`](crate::Ctx::selected_fields)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedField {
+    name: String,
+    alias: Option<String>,
+    arguments: IndexMap<Name, ConstValue>,
+    selected_fields: Vec<SelectedField>,
+}
+
+impl SelectedField {
+    /// Name of the field in the schema
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    /// Key the field's value is returned under, its alias if it has one
+    pub fn response_key(&self) -> &str {
+        self.alias().unwrap_or(&self.name)
+    }
+
+    /// Argument values with variables substituted. Arguments bound to a
+    /// variable that wasn't provided, or with invalid values, are left out.
+    pub fn arguments(&self) -> &IndexMap<Name, ConstValue> {
+        &self.arguments
+    }
+
+    /// Fields selected beneath this one
+    pub fn selected_fields(&self) -> &[SelectedField] {
+        &self.selected_fields
+    }
+
+    /// The first field named `name` selected beneath this one
+    pub fn field(&self, name: &str) -> Option<&SelectedField> {
+        self.selected_fields.iter().find(|field| field.name == name)
+    }
+
+    /// True if a field named `name` is selected beneath this one
+    pub fn selects(&self, name: &str) -> bool {
+        self.field(name).is_some()
+    }
+}
+
+/// Collects the fields selected beneath a field group, recursively
+///
+/// Sub-selections are collected against every object type the group's
+/// return type could resolve to, and merged by response key.
+pub(crate) fn look_ahead(ectx: &ExecCtx, fields: &[Arc<Field>]) -> Result<Vec<SelectedField>> {
+    let Some(type_name) = fields
+        .first()
+        .and_then(|field| ectx.field_type(field))
+        .map(|ty| ty.name())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut grouped_fields: IndexMap<String, Vec<Arc<Field>>> = IndexMap::new();
+
+    for object_type in possible_types(ectx, &type_name) {
+        for (response_key, sub_fields) in collect_subfields(ectx, fields, object_type, None)? {
+            let group = grouped_fields.entry(response_key).or_default();
+
+            //nb: fields outside of type conditions are collected once per
+            //possible type, only keep one copy of each
+            for field in sub_fields {
+                if !group.iter().any(|f| Arc::ptr_eq(f, &field)) {
+                    group.push(field);
+                }
+            }
+        }
+    }
+
+    grouped_fields
+        .into_values()
+        .map(|group| {
+            let field = &group[0];
+
+            Ok(SelectedField {
+                name: field.name().to_owned(),
+                alias: field.alias().map(|a| a.0.clone()),
+                arguments: arguments(ectx, field),
+                selected_fields: look_ahead(ectx, &group)?,
+            })
+        })
+        .collect()
+}
+
+fn possible_types<'a>(ectx: &'a ExecCtx, type_name: &str) -> Vec<&'a ObjectTypeDefinition> {
+    match ectx.find_type_definition_by_name(type_name) {
+        Some(TypeDefinition::ObjectTypeDefinition(object_type)) => vec![object_type.as_ref()],
+        Some(TypeDefinition::InterfaceTypeDefinition(_))
+        | Some(TypeDefinition::UnionTypeDefinition(_)) => ectx
+            .schema
+            .ts
+            .definitions
+            .objects
+            .values()
+            .filter(|object_type| ectx.is_subtype(object_type.name(), type_name))
+            .map(|object_type| object_type.as_ref())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn arguments(ectx: &ExecCtx, field: &Field) -> IndexMap<Name, ConstValue> {
+    field
+        .arguments()
+        .iter()
+        .filter_map(|arg| {
+            let value = resolve_value(ectx.variables(), arg.value()).ok()?;
+            let value = match ectx.argument_type(field, arg.name()) {
+                Some(ty) => coerce_input(ectx, ty, value).ok()?,
+                None => value,
+            };
+            Some((Name::interned(arg.name()), value))
+        })
+        .collect()
+}
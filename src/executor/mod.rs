@@ -1,68 +1,165 @@
 use crate::{
-    introspection::{IspObjectResolver, IspRootResolver},
-    resolver::ObjectResolver,
+    data::Data,
+    error::{ErrorContext, ErrorMapper, GraphQLError, Location, ResponsePath},
+    introspection::{NoopResolver, INTROSPECTION_QUERY},
+    middleware::ResolverMiddleware,
+    observer::ExecutionObserver,
+    request::Request,
+    resolver::{ObjectResolver, SubscriptionResolver},
+    response::{IncrementalResponse, Response},
+    scalar::{ScalarCodec, ScalarValidator},
     value::ConstValue,
 };
-use anyhow::{anyhow, Result};
+use ::futures::{Stream, StreamExt};
+use anyhow::{anyhow, Context, Result};
 use apollo_compiler::{
     hir::{
-        Field, FieldDefinition, FragmentDefinition, ObjectTypeDefinition, TypeDefinition,
-        TypeSystem,
+        self, Field, FieldDefinition, FragmentDefinition, ImplementsInterface,
+        ObjectTypeDefinition, SelectionSet, TypeDefinition, TypeSystem,
     },
     validation::ValidationDatabase,
     ApolloCompiler, HirDatabase, RootDatabase,
 };
-use std::{collections::HashMap, time::Instant};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Semaphore, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 
-use std::sync::Arc;
+use std::{
+    ops::Deref,
+    sync::{Arc, OnceLock, RwLock},
+};
 
+mod apollo_tracing;
+mod authorization;
+mod builder;
+mod coerce;
 mod collect_fields;
+mod complexity;
 mod futures;
-
+mod hidden;
+mod incremental;
+mod look_ahead;
+mod prepared;
+mod query_cache;
+mod subscription;
+#[cfg(test)]
+mod tests;
+
+use apollo_tracing::ApolloTracing;
+pub use builder::ExecutorBuilder;
+use builder::{compile_schema, DEFAULT_DOCUMENT_NAME};
+use coerce::check_variables;
+pub(crate) use coerce::coerce_input;
+pub(crate) use futures::FieldGroup;
+use incremental::{IncrementalPayloads, IncrementalQueue};
+pub(crate) use look_ahead::look_ahead;
+pub use look_ahead::SelectedField;
+pub(crate) use prepared::ExecOptions;
+pub use prepared::PreparedQuery;
+use query_cache::{IntrospectionCache, QueryCache};
+
+/// Executes GraphQL requests against a schema
+///
+/// Clones share the same schema, so a schema swapped in with
+/// [`replace_schema`](Executor::replace_schema) is seen by every clone.
 #[derive(Clone)]
 pub struct Executor {
+    //nb: swapped wholesale on reload, requests take a snapshot when they're
+    //prepared and finish on the schema they started with
+    schema: Arc<RwLock<Arc<LoadedSchema>>>,
+    config: ExecutorConfig,
+}
+
+/// A schema along with the documents prepared against it, kept together so
+/// a reload can never serve a document validated against the old schema
+pub(crate) struct LoadedSchema {
     type_system: Arc<TypeSystem>,
     exec_schema: Arc<ExecSchema>,
+    query_cache: Option<QueryCache>,
+    introspection_cache: Arc<IntrospectionCache>,
+}
+
+/// Options set through [`ExecutorBuilder`]
+#[derive(Clone, Default)]
+pub(crate) struct ExecutorConfig {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) max_introspection_depth: Option<usize>,
+    pub(crate) max_complexity: Option<usize>,
+    pub(crate) query_cache_size: Option<usize>,
+    pub(crate) batch_concurrency: Option<usize>,
+    pub(crate) resolver_concurrency: Option<usize>,
+    pub(crate) introspection_disabled: bool,
+    pub(crate) scalars: Arc<HashMap<String, Arc<dyn ScalarCodec>>>,
+    pub(crate) scalar_validators: Arc<HashMap<String, ScalarValidator>>,
+    pub(crate) middleware: Arc<Vec<Arc<dyn ResolverMiddleware>>>,
+    pub(crate) observers: Arc<Vec<Arc<dyn ExecutionObserver>>>,
+    pub(crate) error_mappers: Arc<Vec<ErrorMapper>>,
+    pub(crate) hidden_directive: Option<Arc<str>>,
+}
+
+impl ExecutorConfig {
+    /// Runs the [`map_error`](ExecutorBuilder::map_error) hook on an error
+    /// that failed the whole request
+    pub(crate) fn map_request_error(&self, err: GraphQLError) -> GraphQLError {
+        let ctx = ErrorContext {
+            path: None,
+            field_name: None,
+            source: None,
+        };
+
+        self.error_mappers
+            .iter()
+            .fold(err, |err, map| map(err, &ctx))
+    }
 }
 
 impl Executor {
     pub fn new(schema: &str) -> Result<Self> {
-        let mut compiler = ApolloCompiler::new();
-        compiler.add_type_system(schema, "schema.graphql");
-
-        let diags = compiler.validate();
-        let has_errors = diags.iter().filter(|d| d.data.is_error()).count() > 0;
+        Self::builder().type_system(schema).build()
+    }
 
-        for diag in diags.iter() {
-            if diag.data.is_error() {
-                tracing::error!("{}", diag);
-            }
-        }
+    /// Builds an executor from a schema split across several SDL files,
+    /// validated together. Validation errors refer to each file by its file
+    /// name.
+    ///
+    /// ```ignore
+    /// let executor = Executor::from_sdl_files(&[
+    ///     Path::new("schema/query.graphql"),
+    ///     Path::new("schema/people.graphql"),
+    /// ])?;
+    /// ```
+    pub fn from_sdl_files(paths: &[&Path]) -> Result<Self> {
+        let mut builder = Self::builder();
+
+        for path in paths {
+            let schema = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read schema file {}", path.display()))?;
+            let name = path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
 
-        if has_errors {
-            return Err(anyhow!("graphql had errors"));
+            builder = builder.type_system_document(name, schema);
         }
 
-        // let type_system = compiler.db.type_system();
-        // let exec_schema = Arc::new(ExecSchema::new(&compiler.db));
-
-        // Ok(Self {
-        //     // compiler,
-        //     type_system,
-        //     exec_schema,
-        // })
+        builder.build()
+    }
 
-        Ok(Self::from_hir(&compiler.db))
+    pub fn builder() -> ExecutorBuilder {
+        ExecutorBuilder::default()
     }
 
     pub fn from_hir(db: &RootDatabase) -> Self {
         let type_system = db.type_system();
         let exec_schema = Arc::new(ExecSchema::new(db));
 
-        Self {
-            type_system,
-            exec_schema,
-        }
+        Self::with_config(type_system, exec_schema, Default::default())
     }
 
     pub fn from_type_system(type_system: Arc<TypeSystem>) -> Self {
@@ -71,21 +168,321 @@ impl Executor {
 
         let exec_schema = Arc::new(ExecSchema::new(&compiler.db));
 
+        Self::with_config(type_system, exec_schema, Default::default())
+    }
+
+    pub(crate) fn with_config(
+        type_system: Arc<TypeSystem>,
+        exec_schema: Arc<ExecSchema>,
+        config: ExecutorConfig,
+    ) -> Self {
+        let schema = LoadedSchema::new(type_system, exec_schema, &config);
+
         Self {
-            type_system,
-            exec_schema,
+            schema: Arc::new(RwLock::new(Arc::new(schema))),
+            config,
         }
     }
 
+    /// Validates `schema` and swaps it in for all subsequent requests.
+    /// Requests already in flight complete against the previous schema. On
+    /// error the current schema is kept.
+    pub fn replace_schema(&self, schema: &str) -> Result<()> {
+        let documents = [(DEFAULT_DOCUMENT_NAME.to_owned(), schema.to_owned())];
+        let (type_system, exec_schema) = compile_schema(&documents, &self.config)?;
+        let schema = LoadedSchema::new(type_system, exec_schema, &self.config);
+
+        *self.schema.write().expect("executor schema lock poisoned") = Arc::new(schema);
+        Ok(())
+    }
+
+    fn loaded_schema(&self) -> Arc<LoadedSchema> {
+        self.schema
+            .read()
+            .expect("executor schema lock poisoned")
+            .clone()
+    }
+
     pub async fn run<'a, R: ObjectResolver + 'static>(
         &'a self,
         query: &'a str,
         query_resolver: R,
         operation_name: Option<String>,
         variables: HashMap<String, ConstValue>,
-    ) -> Result<ConstValue> {
+    ) -> Response {
+        self.run_with_data(
+            query,
+            query_resolver,
+            operation_name,
+            variables,
+            Data::new(),
+        )
+        .await
+    }
+
+    /// Like [`run`](Self::run), taking the variables as they arrive in a
+    /// JSON request body. They go through the same input coercion as any
+    /// other variables: JSON integers are accepted for both `Int` and
+    /// `Float`, while numbers with a fraction or exponent are only valid
+    /// `Float`s (`4.0` is still accepted for an `Int` argument).
+    pub async fn run_json<'a, R: ObjectResolver + 'static>(
+        &'a self,
+        query: &'a str,
+        query_resolver: R,
+        operation_name: Option<String>,
+        variables: serde_json::Map<String, serde_json::Value>,
+    ) -> Response {
+        let mut request = Request::new(query).json_variables(variables);
+        request.operation_name = operation_name;
+
+        self.execute(request, query_resolver).await
+    }
+
+    /// Like [`run`](Self::run), making `data` available to every resolver
+    /// through [`Ctx::data`](crate::Ctx::data)
+    pub async fn run_with_data<'a, R: ObjectResolver + 'static>(
+        &'a self,
+        query: &'a str,
+        query_resolver: R,
+        operation_name: Option<String>,
+        variables: HashMap<String, ConstValue>,
+        data: Data,
+    ) -> Response {
+        let mut request = Request::new(query).variables(variables).with_data(data);
+        request.operation_name = operation_name;
+
+        self.execute(request, query_resolver).await
+    }
+
+    /// Like [`run`](Self::run), deserializing the response data into `T`.
+    /// Mostly useful for tests asserting on a server's own results:
+    ///
+    /// ```ignore
+    /// #[derive(Deserialize)]
+    /// struct PeopleCount {
+    ///     #[serde(rename = "peopleCount")]
+    ///     people_count: i32,
+    /// }
+    ///
+    /// let data: PeopleCount = executor
+    ///     .run_typed("query { peopleCount }", QueryResolver, None, HashMap::new())
+    ///     .await?;
+    /// ```
+    ///
+    /// Fails with the first error if the response has any errors, or if the
+    /// data doesn't deserialize into `T`.
+    pub async fn run_typed<'a, T: DeserializeOwned, R: ObjectResolver + 'static>(
+        &'a self,
+        query: &'a str,
+        query_resolver: R,
+        operation_name: Option<String>,
+        variables: HashMap<String, ConstValue>,
+    ) -> Result<T, GraphQLError> {
+        let data = self
+            .run(query, query_resolver, operation_name, variables)
+            .await
+            .into_result()?;
+
+        serde_json::from_value(data.to_json()).map_err(|err| {
+            GraphQLError::new(format!("failed to deserialize response data: {}", err))
+        })
+    }
+
+    pub async fn execute<R: ObjectResolver + 'static>(
+        &self,
+        request: Request,
+        query_resolver: R,
+    ) -> Response {
+        match self.prepare(&request.query) {
+            Ok(prepared) => {
+                prepared
+                    .execute_with(
+                        query_resolver,
+                        request.operation_name,
+                        request.variables,
+                        ExecOptions {
+                            data: request.data,
+                            timeout: request.timeout,
+                            cancellation_token: request.cancellation_token.unwrap_or_default(),
+                            tracing: request.tracing,
+                        },
+                    )
+                    .await
+            }
+            Err(err) => Response::from_request_error(self.config.map_request_error(err)),
+        }
+    }
+
+    /// Executes a batch of requests (e.g. a JSON array of operations sent in
+    /// one HTTP request, see [`BatchRequest`](crate::BatchRequest)), each
+    /// with a resolver from `resolver`. Responses are in the same order as
+    /// the requests.
+    ///
+    /// Operations run concurrently, up to the
+    /// [`batch_concurrency`](ExecutorBuilder::batch_concurrency) limit, and
+    /// fail independently: an operation that fails only affects its own
+    /// response.
+    pub async fn run_batch<R: ObjectResolver + 'static>(
+        &self,
+        requests: Vec<Request>,
+        resolver: impl Fn() -> R,
+    ) -> Vec<Response> {
+        let limit = self
+            .config
+            .batch_concurrency
+            .unwrap_or(requests.len())
+            .max(1);
+
+        ::futures::stream::iter(requests)
+            .map(|request| self.execute(request, resolver()))
+            .buffered(limit)
+            .collect()
+            .await
+    }
+
+    /// Executes a request whose response may be delivered in parts: list
+    /// fields marked `@stream(initialCount: n)` are returned with their first
+    /// `n` items, and the rest follow as incremental payloads. Fragments
+    /// marked `@defer` are left out of the initial payload and follow with
+    /// the path of the object they were spread on.
+    ///
+    /// ```ignore
+    /// let mut payloads = executor.run_incremental(request, QueryResolver);
+    ///
+    /// while let Some(payload) = payloads.next().await {
+    ///     send(serde_json::to_string(&payload)?).await?;
+    /// }
+    /// ```
+    ///
+    /// The request's timeout applies to the initial payload. Dropping the
+    /// stream stops execution.
+    pub fn run_incremental<R: ObjectResolver + 'static>(
+        &self,
+        request: Request,
+        query_resolver: R,
+    ) -> impl Stream<Item = IncrementalResponse> + Send + 'static {
+        match self.prepare(&request.query) {
+            Ok(prepared) => prepared.execute_incremental(
+                query_resolver,
+                request.operation_name,
+                request.variables,
+                ExecOptions {
+                    data: request.data,
+                    timeout: request.timeout,
+                    cancellation_token: request.cancellation_token.unwrap_or_default(),
+                    tracing: false,
+                },
+            ),
+            Err(err) => {
+                let (payloads, receiver) = ::futures::channel::mpsc::unbounded();
+                let _ = payloads.unbounded_send(IncrementalResponse {
+                    data: None,
+                    errors: vec![self.config.map_request_error(err)],
+                    incremental: Vec::new(),
+                    has_next: false,
+                });
+
+                IncrementalPayloads {
+                    payloads: receiver,
+                    _task: None,
+                }
+            }
+        }
+    }
+
+    /// Executes a subscription operation: `subscription_resolver` creates
+    /// the stream of events for the subscribed root field, and the
+    /// operation's selection set is executed against each event to produce
+    /// a response.
+    ///
+    /// ```ignore
+    /// let mut responses = executor
+    ///     .subscribe(Request::new("subscription { messageAdded { text } }"), SubscriptionRoot)
+    ///     .await?;
+    ///
+    /// while let Some(response) = responses.next().await {
+    ///     send(serde_json::to_string(&response)?).await?;
+    /// }
+    /// ```
+    ///
+    /// Fails if the request isn't a valid subscription or the resolver can't
+    /// create the stream. The request's timeout applies to executing each
+    /// event. Dropping the stream ends the subscription.
+    pub async fn subscribe<R: SubscriptionResolver + 'static>(
+        &self,
+        request: Request,
+        subscription_resolver: R,
+    ) -> Result<impl Stream<Item = Response> + Send + 'static, GraphQLError> {
+        let prepared = self
+            .prepare(&request.query)
+            .map_err(|err| self.config.map_request_error(err))?;
+
+        prepared
+            .subscribe(
+                subscription_resolver,
+                request.operation_name,
+                request.variables,
+                ExecOptions {
+                    data: request.data,
+                    timeout: request.timeout,
+                    cancellation_token: request.cancellation_token.unwrap_or_default(),
+                    tracing: request.tracing,
+                },
+            )
+            .await
+            .map_err(|err| self.config.map_request_error(err))
+    }
+
+    /// Runs the standard full introspection query and returns its data, e.g.
+    /// to write the `schema.json` that client code generators consume:
+    ///
+    /// ```ignore
+    /// let introspection = executor.introspect().await?;
+    /// std::fs::write("schema.json", serde_json::to_string_pretty(&introspection)?)?;
+    /// ```
+    ///
+    /// Fails if introspection has been disabled.
+    pub async fn introspect(&self) -> Result<ConstValue, GraphQLError> {
+        self.execute(Request::new(INTROSPECTION_QUERY), NoopResolver)
+            .await
+            .into_result()
+    }
+
+    /// Prints the schema the executor is serving as SDL, e.g. for a
+    /// federation `_service { sdl }` field or publishing to a schema
+    /// registry.
+    ///
+    /// Built-in scalars, directives and introspection types are left out.
+    /// Definitions are sorted by name (after the schema definition and then
+    /// directive definitions), so the output is stable enough to snapshot.
+    pub fn sdl(&self) -> String {
+        crate::sdl::print_type_system(&self.loaded_schema().type_system)
+    }
+
+    /// Parses and validates a query document so that it can be executed any
+    /// number of times without repeating that work, see [`PreparedQuery`].
+    ///
+    /// Documents are memoized in the executor's query cache, so preparing the
+    /// same query text again is cheap.
+    pub fn prepare(&self, query: &str) -> Result<PreparedQuery, GraphQLError> {
+        let schema = self.loaded_schema();
+
+        if let Some(prepared) = schema.query_cache.as_ref().and_then(|c| c.get(query)) {
+            return Ok(prepared);
+        }
+
+        let prepared = self.compile(&schema, query)?;
+
+        if let Some(query_cache) = &schema.query_cache {
+            query_cache.insert(query.to_owned(), prepared.clone());
+        }
+
+        Ok(prepared)
+    }
+
+    fn compile(&self, schema: &LoadedSchema, query: &str) -> Result<PreparedQuery, GraphQLError> {
         let mut compiler = ApolloCompiler::new();
-        compiler.set_type_system_hir(self.type_system.clone());
+        compiler.set_type_system_hir(schema.type_system.clone());
 
         let compile_start = Instant::now();
         let query_file_id = compiler.add_executable(query, "query.graphql");
@@ -109,62 +506,114 @@ impl Executor {
 
         let has_errors = diags.iter().filter(|d| d.data.is_error()).count() > 0;
         if has_errors {
-            return Err(anyhow!("graphql had errors"));
+            return Err(GraphQLError::new("graphql had errors"));
         }
 
-        //TODO implement coerce variables algorithm
-        // may already be implemented in a recent apollo-rs PR
-        //https://spec.graphql.org/draft/#sec-Coercing-Variable-Values
+        Ok(PreparedQuery::new(
+            schema,
+            &self.config,
+            &compiler.db,
+            query,
+        ))
+    }
+}
 
-        let ectx = ExecCtx::new(&compiler.db, self.exec_schema.clone(), variables);
+impl LoadedSchema {
+    fn new(
+        type_system: Arc<TypeSystem>,
+        exec_schema: Arc<ExecSchema>,
+        config: &ExecutorConfig,
+    ) -> Self {
+        let query_cache = QueryCache::new(
+            config
+                .query_cache_size
+                .unwrap_or(query_cache::DEFAULT_QUERY_CACHE_SIZE),
+        );
 
-        let result_fut = tokio::spawn(async move {
-            let all_ops = compiler.db.all_operations();
-            let query_op = all_ops
-                .iter()
-                .find(|op| op.name() == operation_name.as_ref().map(|s| s.as_str()))
-                .ok_or_else(|| anyhow!("query operation not found: {:?}", operation_name))?;
+        Self {
+            type_system,
+            exec_schema,
+            query_cache,
+            introspection_cache: Arc::new(IntrospectionCache::new()),
+        }
+    }
+}
 
-            let sel_set = query_op.selection_set();
-            let query_type = query_op
-                .object_type(&compiler.db)
-                .ok_or_else(|| anyhow!("query type not found"))?;
+/// Rejects operations selecting `__schema`/`__type` when introspection has
+/// been disabled
+fn check_introspection(
+    ectx: &ExecCtx,
+    config: &ExecutorConfig,
+    root_type: &ObjectTypeDefinition,
+    sel_set: &SelectionSet,
+) -> Result<()> {
+    if !config.introspection_disabled {
+        return Ok(());
+    }
 
-            let snapshot_start = Instant::now();
-            let ts = compiler.db.type_system();
+    let root_fields = collect_fields::collect_fields(ectx, sel_set, root_type, None)?;
+    let introspects = root_fields
+        .values()
+        .flatten()
+        .any(|field| matches!(field.name(), "__schema" | "__type"));
 
-            tracing::debug!(
-                "snapshots took: {}μs",
-                Instant::now().duration_since(snapshot_start).as_micros()
-            );
+    if introspects {
+        return Err(anyhow!("introspection is disabled"));
+    }
 
-            let schema_resolver = IspRootResolver {
-                schema_def: compiler.db.schema(),
-                inner: &query_resolver,
-                ts,
-            };
+    Ok(())
+}
 
-            let query_resolver = IspObjectResolver {
-                type_def: query_type.clone(),
-                inner: &schema_resolver,
-            };
+/// Rejects operations nested deeper than the configured limits before any
+/// resolver has a chance to run
+fn check_depth(ectx: &ExecCtx, config: &ExecutorConfig, sel_set: &SelectionSet) -> Result<()> {
+    if config.max_depth.is_none() && config.max_introspection_depth.is_none() {
+        return Ok(());
+    }
 
-            let query_fut =
-                futures::ExecuteSelectionSet::new(&ectx, &query_resolver, query_type, sel_set)?;
+    let depth = collect_fields::selection_depth(ectx, sel_set)?;
 
-            let exec_start = Instant::now();
-            let result = query_fut.await;
-            tracing::info!(
-                "query took {}μs",
-                Instant::now().duration_since(exec_start).as_micros()
-            );
-            result
-        });
+    if let Some(max_depth) = config.max_depth {
+        if depth.depth > max_depth {
+            return Err(anyhow!(
+                "query depth {} exceeds maximum allowed depth of {}",
+                depth.depth,
+                max_depth
+            ));
+        }
+    }
+
+    if let Some(max_depth) = config.max_introspection_depth {
+        if depth.introspection_depth > max_depth {
+            return Err(anyhow!(
+                "introspection query depth {} exceeds maximum allowed depth of {}",
+                depth.introspection_depth,
+                max_depth
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Aborts the spawned execution task if the caller stops waiting on it (e.g.
+/// the request future is dropped by the HTTP layer) instead of leaving it
+/// running detached.
+struct AbortOnDrop<T>(JoinHandle<T>);
 
-        result_fut.await?
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
     }
 }
 
+/// The schema an operation executes against, available to resolvers and
+/// middleware through [`Ctx::schema`](crate::Ctx::schema), e.g. to read
+/// directives on type or field definitions.
+///
+/// It's an immutable snapshot: every lookup is a cheap map access that's
+/// safe to call from any resolver, and a schema swapped in with
+/// [`Executor::replace_schema`] is only seen by requests started afterwards.
 pub struct ExecSchema {
     ts: Arc<TypeSystem>,
     //TODO would rather just have a big flat map here but couldn't get a tuple string key to work immediately
@@ -178,19 +627,31 @@ impl ExecSchema {
 
         for (k, v) in db.types_definitions_by_name().iter() {
             let field_map: HashMap<String, FieldDefinition> = match v {
-                TypeDefinition::ObjectTypeDefinition(ty) => ty
-                    .fields()
-                    .chain(ty.implicit_fields(db))
-                    .cloned()
-                    .map(|f| (f.name().to_owned(), f))
-                    .collect(),
-                TypeDefinition::InterfaceTypeDefinition(ty) => ty
-                    .fields()
-                    .chain(ty.implicit_fields().iter())
-                    .cloned()
-                    .map(|f| (f.name().to_owned(), f))
-                    .collect(),
-                _ => HashMap::new(), //TODO fix
+                TypeDefinition::ObjectTypeDefinition(ty) => {
+                    let mut field_map: HashMap<_, _> = ty
+                        .fields()
+                        .chain(ty.implicit_fields(db))
+                        .cloned()
+                        .map(|f| (f.name().to_owned(), f))
+                        .collect();
+                    add_interface_fields(&ts, ty.implements_interfaces(), &mut field_map);
+                    field_map
+                }
+                TypeDefinition::InterfaceTypeDefinition(ty) => {
+                    let mut field_map: HashMap<_, _> = ty
+                        .fields()
+                        .chain(ty.implicit_fields().iter())
+                        .cloned()
+                        .map(|f| (f.name().to_owned(), f))
+                        .collect();
+                    add_interface_fields(&ts, ty.implements_interfaces(), &mut field_map);
+                    field_map
+                }
+                //nb: unions have no fields of their own, fields beneath a
+                //union-typed field are selected through fragments so their
+                //parent type is the fragment's member (or interface) type,
+                //and `__typename` is special cased in `field_type`
+                _ => HashMap::new(),
             };
 
             all_fields.insert(k.to_owned(), field_map);
@@ -198,41 +659,188 @@ impl ExecSchema {
 
         Self { ts, all_fields }
     }
+
+    /// Definition of the field `field_name` on the object or interface
+    /// `type_name`, including `__typename` and the other implicit fields
+    pub fn find_field_definition(
+        &self,
+        type_name: &str,
+        field_name: &str,
+    ) -> Option<&FieldDefinition> {
+        self.all_fields.get(type_name)?.get(field_name)
+    }
+
+    /// Definition of the named type, e.g. to read the directives on it
+    pub fn find_type_definition(&self, name: &str) -> Option<&TypeDefinition> {
+        self.ts.type_definitions_by_name.get(name)
+    }
+
+    /// The whole type system, for anything the lookups above don't cover
+    pub fn type_system(&self) -> &TypeSystem {
+        &self.ts
+    }
 }
 
+/// Adds the fields of `interfaces` (and the interfaces they implement) that
+/// aren't declared on the implementing type itself. Validation requires
+/// implementations to redeclare their interfaces' fields, but fields selected
+/// through an interface are looked up on the concrete type, so the
+/// interface's definition stands in if one is ever missing.
+fn add_interface_fields<'a>(
+    ts: &TypeSystem,
+    interfaces: impl Iterator<Item = &'a ImplementsInterface>,
+    field_map: &mut HashMap<String, FieldDefinition>,
+) {
+    for implements in interfaces {
+        let Some(interface) = ts.definitions.interfaces.get(implements.interface()) else {
+            continue;
+        };
+
+        for field in interface.fields() {
+            field_map
+                .entry(field.name().to_owned())
+                .or_insert_with(|| field.clone());
+        }
+
+        add_interface_fields(ts, interface.implements_interfaces(), field_map);
+    }
+}
+
+/// Per-request execution state, shared by every field future (and each
+/// field's [`Ctx`](crate::Ctx)) so cloning it is a single reference count bump
 #[derive(Clone)]
-pub struct ExecCtx {
+pub struct ExecCtx(Arc<ExecCtxInner>);
+
+pub struct ExecCtxInner {
     schema: Arc<ExecSchema>,
-    variables: Arc<HashMap<String, ConstValue>>,
-    fragments: HashMap<String, FragmentDefinition>,
+    scalars: Arc<HashMap<String, Arc<dyn ScalarCodec>>>,
+    scalar_validators: Arc<HashMap<String, ScalarValidator>>,
+    middleware: Arc<Vec<Arc<dyn ResolverMiddleware>>>,
+    observers: Arc<Vec<Arc<dyn ExecutionObserver>>>,
+    error_mappers: Arc<Vec<ErrorMapper>>,
+    /// Schema members carrying this directive are left out of introspection
+    hidden_directive: Option<Arc<str>>,
+    /// Limits how many resolvers of this operation run at once
+    resolver_permits: Option<Semaphore>,
+    variables: HashMap<String, ConstValue>,
+    data: Data,
+    cancellation_token: CancellationToken,
+    fragments: Arc<HashMap<String, FragmentDefinition>>,
+    source: Arc<str>,
+    /// Cost of the operation, measured when a maximum complexity is set
+    complexity: OnceLock<usize>,
+    /// Set when executing for incremental delivery, `@stream` is ignored
+    /// (lists are completed inline) otherwise
+    incremental: Option<IncrementalQueue>,
+    /// Set when the request asked for Apollo tracing
+    tracing: Option<ApolloTracing>,
+}
+
+impl Deref for ExecCtx {
+    type Target = ExecCtxInner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
 }
 
 impl ExecCtx {
-    fn new<DB: HirDatabase>(
-        db: &DB,
+    fn new(
         schema: Arc<ExecSchema>,
+        config: &ExecutorConfig,
         variables: HashMap<String, ConstValue>,
+        options: ExecOptions,
+        fragments: Arc<HashMap<String, FragmentDefinition>>,
+        source: Arc<str>,
     ) -> Self {
-        let mut fragments = HashMap::new();
-
-        for (name, frag) in db.all_fragments().iter() {
-            fragments.insert(name.clone(), frag.as_ref().clone());
-        }
-
-        Self {
+        Self(Arc::new(ExecCtxInner {
             fragments,
             schema,
-            variables: Arc::new(variables),
+            scalars: config.scalars.clone(),
+            scalar_validators: config.scalar_validators.clone(),
+            middleware: config.middleware.clone(),
+            observers: config.observers.clone(),
+            error_mappers: config.error_mappers.clone(),
+            hidden_directive: config.hidden_directive.clone(),
+            resolver_permits: config.resolver_concurrency.map(Semaphore::new),
+            variables,
+            data: options.data,
+            cancellation_token: options.cancellation_token,
+            source,
+            complexity: OnceLock::new(),
+            incremental: None,
+            tracing: options.tracing.then(ApolloTracing::new),
+        }))
+    }
+
+    fn with_incremental_delivery(mut self) -> Self {
+        Arc::get_mut(&mut self.0)
+            .expect("execution context already shared")
+            .incremental = Some(IncrementalQueue::default());
+        self
+    }
+
+    /// Attaches the location of `field` to an error raised while resolving
+    /// it. Errors that propagated out of a nested selection already point at
+    /// the field that actually failed, so they're left as-is.
+    fn locate(&self, err: GraphQLError, field: &Field) -> GraphQLError {
+        if !err.locations().is_empty() {
+            return err;
         }
+
+        err.with_location(Location::from_offset(&self.source, field.loc().offset()))
     }
 
     fn field_definition(&self, field: &Field) -> Option<&FieldDefinition> {
-        let type_name = field.parent_type_name()?;
-        self.schema.all_fields.get(type_name)?.get(field.name())
+        self.schema
+            .find_field_definition(field.parent_type_name()?, field.name())
+    }
+
+    /// Declared type of one of a field's arguments
+    pub(crate) fn argument_type(&self, field: &Field, name: &str) -> Option<&hir::Type> {
+        self.field_definition(field)?
+            .arguments()
+            .input_values()
+            .iter()
+            .find(|arg| arg.name() == name)
+            .map(|arg| arg.ty())
+    }
+
+    /// Declared type of a field. `__typename` is special cased since it's
+    /// selectable on unions, which have no field definitions of their own.
+    fn field_type(&self, field: &Field) -> Option<&hir::Type> {
+        static TYPENAME_TYPE: OnceLock<hir::Type> = OnceLock::new();
+
+        match self.field_definition(field) {
+            Some(field_def) => Some(field_def.ty()),
+            None if field.name() == "__typename" => {
+                Some(TYPENAME_TYPE.get_or_init(|| hir::Type::NonNull {
+                    ty: Box::new(hir::Type::Named {
+                        name: "String".to_owned(),
+                        loc: None,
+                    }),
+                    loc: None,
+                }))
+            }
+            None => None,
+        }
     }
 
     fn find_type_definition_by_name(&self, name: &str) -> Option<&TypeDefinition> {
-        self.schema.ts.type_definitions_by_name.get(name)
+        self.schema.find_type_definition(name)
+    }
+
+    /// True for object, interface and union types, whose values are
+    /// completed by executing a selection set
+    fn is_composite_type(&self, name: &str) -> bool {
+        matches!(
+            self.find_type_definition_by_name(name),
+            Some(
+                TypeDefinition::ObjectTypeDefinition(_)
+                    | TypeDefinition::InterfaceTypeDefinition(_)
+                    | TypeDefinition::UnionTypeDefinition(_)
+            )
+        )
     }
 
     fn find_object_type_definition(&self, name: &str) -> Option<&ObjectTypeDefinition> {
@@ -244,22 +852,80 @@ impl ExecCtx {
             .map(|o| o.as_ref())
     }
 
-    fn fragment(&self, name: &str) -> Option<&FragmentDefinition> {
+    /// Runs the [`map_error`](ExecutorBuilder::map_error) hook on an error
+    /// raised resolving `field`, `source` is what its resolver returned
+    fn map_field_error(
+        &self,
+        err: GraphQLError,
+        field: &Field,
+        path: &ResponsePath,
+        source: Option<&anyhow::Error>,
+    ) -> GraphQLError {
+        if self.error_mappers.is_empty() {
+            return err;
+        }
+
+        let path = path.to_vec();
+        let ctx = ErrorContext {
+            path: Some(&path),
+            field_name: Some(field.name()),
+            source,
+        };
+        let err = self
+            .error_mappers
+            .iter()
+            .fold(err, |err, map| map(err, &ctx));
+
+        //nb: an error the hook built from scratch would otherwise be taken
+        //for one raised by the parent field
+        self.locate(err, field)
+    }
+
+    fn scalar_codec(&self, name: &str) -> Option<&dyn ScalarCodec> {
+        self.scalars.get(name).map(|codec| codec.as_ref())
+    }
+
+    fn scalar_validator(&self, name: &str) -> Option<&ScalarValidator> {
+        self.scalar_validators.get(name)
+    }
+
+    pub(crate) fn fragment(&self, name: &str) -> Option<&FragmentDefinition> {
         self.fragments.get(name)
     }
 
+    /// True if `concrete_type` implements the interface or is a member of the
+    /// union `abstract_type`
     fn is_subtype(&self, concrete_type: &str, abstract_type: &str) -> bool {
-        if let Some(ats) = self.schema.ts.subtype_map.get(concrete_type) {
-            ats.contains(abstract_type)
-        } else {
-            false
+        //nb: answered from the abstract type's definition, the subtype map is
+        //keyed by abstract type so looking the concrete type up in it misses
+        match self.find_type_definition_by_name(abstract_type) {
+            Some(TypeDefinition::UnionTypeDefinition(union_type)) => union_type
+                .members()
+                .any(|member| member.name() == concrete_type),
+            Some(TypeDefinition::InterfaceTypeDefinition(_)) => self
+                .find_object_type_definition(concrete_type)
+                .is_some_and(|object_type| object_type.implements_interface(abstract_type)),
+            _ => false,
         }
     }
 
-    fn variables(&self) -> &HashMap<String, ConstValue> {
+    /// The schema this operation executes against
+    pub(crate) fn schema(&self) -> &ExecSchema {
+        &self.schema
+    }
+
+    pub(crate) fn variables(&self) -> &HashMap<String, ConstValue> {
         &self.variables
     }
 
+    pub(crate) fn data(&self) -> &Data {
+        &self.data
+    }
+
+    pub(crate) fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+
     // fn find_interface_type_definition(&self, name: &str) -> Option<&InterfaceTypeDefinition> {
     //     self.ts.definitions.interfaces.get(name).map(|o| o.as_ref())
     // }
@@ -0,0 +1,635 @@
+use std::{
+    collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration, time::Instant,
+};
+
+use anyhow::{anyhow, Result};
+use apollo_compiler::{
+    hir::{FragmentDefinition, ObjectTypeDefinition, OperationDefinition, Selection, TypeSystem},
+    HirDatabase, RootDatabase,
+};
+use futures::{channel::mpsc, stream::SelectAll, FutureExt, Stream, StreamExt};
+use indexmap::IndexMap;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Span};
+
+use super::{
+    check_depth, check_introspection, check_variables,
+    collect_fields::collect_fields,
+    complexity::check_complexity,
+    futures::{defer_fragments, panic_error, Completed, ExecuteSelectionSet},
+    incremental::{ClearQueueOnDrop, IncrementalPayloads, IncrementalQueue},
+    query_cache::IntrospectionCache,
+    subscription::SubscriptionEvent,
+    AbortOnDrop, ExecCtx, ExecSchema, ExecutorConfig, LoadedSchema,
+};
+use crate::{
+    data::Data,
+    error::{ErrorKind, GraphQLError, PathSegment, ResponsePath},
+    introspection::{IspObjectResolver, IspRootResolver},
+    middleware::OperationInfo,
+    resolver::{resolve_value, Ctx, ObjectResolver, SubscriptionResolver},
+    response::{IncrementalResponse, Response},
+    value::{ConstValue, Name},
+};
+
+/// A parsed and validated query document, created by [`Executor::prepare`]
+///
+/// Holds everything execution needs from the document (but not the compiler
+/// it was validated with), so it's cheap to clone and can be cached (e.g.
+/// keyed by persisted query id) and executed many times with different
+/// variables.
+///
+/// ```ignore
+/// let prepared = executor.prepare("query Person($id: Int!) { person(id: $id) { name } }")?;
+///
+/// let response = prepared
+///     .execute(QueryResolver, None, variables)
+///     .await;
+/// ```
+#[derive(Clone)]
+pub struct PreparedQuery {
+    type_system: Arc<TypeSystem>,
+    exec_schema: Arc<ExecSchema>,
+    config: ExecutorConfig,
+    source: Arc<str>,
+    operations: Arc<Vec<PreparedOperation>>,
+    fragments: Arc<HashMap<String, FragmentDefinition>>,
+    //nb: shared with every document prepared against the same schema, so
+    //repeated introspection (e.g. every GraphiQL load) is answered from here
+    introspection_cache: Arc<IntrospectionCache>,
+}
+
+/// Per-request options for executing a [`PreparedQuery`], taken from a
+/// [`Request`](crate::Request)
+#[derive(Clone, Default)]
+pub(crate) struct ExecOptions {
+    pub(crate) data: Data,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) cancellation_token: CancellationToken,
+    pub(crate) tracing: bool,
+}
+
+#[derive(Clone)]
+struct PreparedOperation {
+    definition: Arc<OperationDefinition>,
+    root_type: Option<Arc<ObjectTypeDefinition>>,
+    /// Selects nothing but introspection fields at its root
+    introspection_only: bool,
+}
+
+impl PreparedQuery {
+    pub(crate) fn new(
+        schema: &LoadedSchema,
+        config: &ExecutorConfig,
+        db: &RootDatabase,
+        source: &str,
+    ) -> Self {
+        let operations = db
+            .all_operations()
+            .iter()
+            .map(|op| PreparedOperation {
+                definition: op.clone(),
+                root_type: op.object_type(db),
+                introspection_only: op.selection_set().selection().iter().all(
+                    |sel| matches!(sel, Selection::Field(field) if field.name().starts_with("__")),
+                ),
+            })
+            .collect();
+
+        let fragments = db
+            .all_fragments()
+            .iter()
+            .map(|(name, frag)| (name.clone(), frag.as_ref().clone()))
+            .collect();
+
+        Self {
+            type_system: schema.type_system.clone(),
+            exec_schema: schema.exec_schema.clone(),
+            config: config.clone(),
+            source: source.into(),
+            operations: Arc::new(operations),
+            fragments: Arc::new(fragments),
+            introspection_cache: schema.introspection_cache.clone(),
+        }
+    }
+
+    /// Executes the document. `operation_name` selects the operation to run
+    /// when the document contains more than one.
+    pub async fn execute<R: ObjectResolver + 'static>(
+        &self,
+        query_resolver: R,
+        operation_name: Option<String>,
+        variables: HashMap<String, ConstValue>,
+    ) -> Response {
+        self.execute_with_data(query_resolver, operation_name, variables, Data::new())
+            .await
+    }
+
+    /// Like [`execute`](Self::execute), making `data` available to every
+    /// resolver through [`Ctx::data`](crate::Ctx::data)
+    pub async fn execute_with_data<R: ObjectResolver + 'static>(
+        &self,
+        query_resolver: R,
+        operation_name: Option<String>,
+        variables: HashMap<String, ConstValue>,
+        data: Data,
+    ) -> Response {
+        self.execute_with(
+            query_resolver,
+            operation_name,
+            variables,
+            ExecOptions {
+                data,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    pub(crate) async fn execute_with<R: ObjectResolver + 'static>(
+        &self,
+        query_resolver: R,
+        operation_name: Option<String>,
+        variables: HashMap<String, ConstValue>,
+        options: ExecOptions,
+    ) -> Response {
+        let cacheable =
+            self.is_cacheable_introspection(operation_name.as_deref(), &variables, &options);
+
+        if cacheable {
+            if let Some(data) = self.introspection_cache.get(&self.source, &operation_name) {
+                return Response::new(data);
+            }
+        }
+
+        let cache_key = operation_name.clone();
+        let timeout = options.timeout;
+        let ectx = self.exec_ctx(operation_name.as_deref(), variables, options);
+
+        let result_fut = tokio::spawn(self.clone().execute_operation(
+            ectx.clone(),
+            query_resolver,
+            operation_name,
+            timeout,
+        ));
+
+        let mut result_fut = AbortOnDrop(result_fut);
+        let completed = match (&mut result_fut.0).await {
+            Ok(completed) => completed,
+            //nb: resolver panics are caught per field, this is anything else
+            //that took the execution task down with it
+            Err(err) if err.is_panic() => Err(panic_error("execution", err.into_panic())),
+            Err(err) => Err(GraphQLError::new(format!("execution task failed: {}", err))),
+        };
+
+        let mut response = match completed {
+            Ok(completed) => {
+                let mut response = Response::new(completed.value.unwrap_or_default());
+                response.errors = completed.errors;
+                response
+            }
+            Err(err) => Response::from_request_error(self.config.map_request_error(err)),
+        };
+
+        if let Some(complexity) = ectx.complexity.get() {
+            response.extensions.insert(
+                Name::new("complexity"),
+                ConstValue::Object(IndexMap::from([
+                    (Name::new("cost"), ConstValue::from(*complexity)),
+                    (
+                        Name::new("max"),
+                        ConstValue::from(self.config.max_complexity.unwrap_or_default()),
+                    ),
+                ])),
+            );
+        }
+
+        if let Some(tracing) = &ectx.tracing {
+            response
+                .extensions
+                .insert(Name::new("tracing"), tracing.extension());
+        }
+
+        if cacheable && response.errors.is_empty() {
+            self.introspection_cache
+                .insert(self.source.clone(), cache_key, response.data.clone());
+        }
+
+        response
+    }
+
+    /// Introspection only depends on the schema, so an operation selecting
+    /// nothing but introspection fields is executed once and its data reused
+    /// by later requests for the same document, see [`IntrospectionCache`].
+    ///
+    /// Variables could change the result (`__type(name: $name)`) and tracing
+    /// or a complexity limit add per-request extensions, so requests with
+    /// any of them always execute. Middleware (including authorization and
+    /// directive handlers) may reject or change fields per request, so with
+    /// any registered nothing is served from the cache. Observers don't see
+    /// the fields of a cached response.
+    fn is_cacheable_introspection(
+        &self,
+        operation_name: Option<&str>,
+        variables: &HashMap<String, ConstValue>,
+        options: &ExecOptions,
+    ) -> bool {
+        variables.is_empty()
+            && !options.tracing
+            && self.config.middleware.is_empty()
+            && self.config.max_complexity.is_none()
+            && self
+                .operation(operation_name)
+                .map_or(false, |op| op.introspection_only)
+    }
+
+    /// Subscribes to a subscription operation's source stream and executes
+    /// the operation against each of its events, see
+    /// [`Executor::subscribe`](crate::Executor::subscribe)
+    pub(crate) async fn subscribe<R: SubscriptionResolver + 'static>(
+        &self,
+        subscription_resolver: R,
+        operation_name: Option<String>,
+        variables: HashMap<String, ConstValue>,
+        options: ExecOptions,
+    ) -> Result<impl Stream<Item = Response> + Send + 'static, GraphQLError> {
+        let operation = self.operation(operation_name.as_deref())?;
+
+        if !operation.definition.operation_ty().is_subscription() {
+            return Err(GraphQLError::new("operation is not a subscription"));
+        }
+
+        let root_type = operation
+            .root_type
+            .clone()
+            .ok_or_else(|| anyhow!("subscription type not found"))?;
+
+        let ectx = self.exec_ctx(
+            operation_name.as_deref(),
+            variables.clone(),
+            options.clone(),
+        );
+        let sel_set = operation.definition.selection_set();
+        check_depth(&ectx, &self.config, sel_set)?;
+        check_variables(&ectx, &operation.definition)?;
+        check_complexity(&ectx, &self.config, sel_set)?;
+
+        //nb: validation already requires a single root field, this also
+        //accounts for fields skipped with @skip/@include
+        let mut root_fields = collect_fields(&ectx, sel_set, &root_type, None)?.into_iter();
+        let (Some((response_key, fields)), None) = (root_fields.next(), root_fields.next()) else {
+            return Err(GraphQLError::new(
+                "subscription operations must select exactly one root field",
+            ));
+        };
+
+        let ctx = Ctx {
+            ectx: ectx.clone(),
+            fields: fields.into(),
+            path: ResponsePath::default().child(PathSegment::Field(Name::new(response_key))),
+        };
+
+        let events = subscription_resolver
+            .subscribe(&ctx, ctx.field_name())
+            .await
+            .map_err(|err| ectx.locate(err.into(), ctx.field()))?;
+
+        let prepared = self.clone();
+        let field_name = ctx.field_name().to_owned();
+
+        Ok(events.then(move |event| {
+            let event = SubscriptionEvent::new(field_name.clone(), event);
+
+            let prepared = prepared.clone();
+            let operation_name = operation_name.clone();
+            let variables = variables.clone();
+            let options = options.clone();
+
+            async move {
+                prepared
+                    .execute_with(event, operation_name, variables, options)
+                    .await
+            }
+        }))
+    }
+
+    /// Executes the operation, delivering the initial payload and then any
+    /// incremental results as they complete, see
+    /// [`Executor::run_incremental`](crate::Executor::run_incremental)
+    pub(crate) fn execute_incremental<R: ObjectResolver + 'static>(
+        &self,
+        query_resolver: R,
+        operation_name: Option<String>,
+        variables: HashMap<String, ConstValue>,
+        options: ExecOptions,
+    ) -> IncrementalPayloads {
+        let timeout = options.timeout;
+        let ectx = self
+            .exec_ctx(operation_name.as_deref(), variables, options)
+            .with_incremental_delivery();
+        let (payloads, receiver) = mpsc::unbounded();
+        let prepared = self.clone();
+
+        let task = tokio::spawn(async move {
+            let _clear_queue = ClearQueueOnDrop(ectx.clone());
+
+            let initial = prepared
+                .execute_operation(ectx.clone(), query_resolver, operation_name, timeout)
+                .await;
+
+            let queue = ectx
+                .incremental
+                .as_ref()
+                .expect("incremental delivery enabled");
+            let payload = match initial {
+                Ok(completed) => IncrementalResponse {
+                    data: Some(completed.value.unwrap_or_default()),
+                    errors: completed.errors,
+                    incremental: Vec::new(),
+                    has_next: !queue.is_empty(),
+                },
+                Err(err) => IncrementalResponse {
+                    data: None,
+                    errors: vec![prepared.config.map_request_error(err)],
+                    incremental: Vec::new(),
+                    has_next: false,
+                },
+            };
+
+            let has_next = payload.has_next;
+            if payloads.unbounded_send(payload).is_ok() && has_next {
+                deliver_incremental(&ectx, queue, &payloads).await;
+            }
+        });
+
+        IncrementalPayloads {
+            payloads: receiver,
+            _task: Some(AbortOnDrop(task)),
+        }
+    }
+
+    //TODO implement coerce variables algorithm
+    // may already be implemented in a recent apollo-rs PR
+    //https://spec.graphql.org/draft/#sec-Coercing-Variable-Values
+    fn exec_ctx(
+        &self,
+        operation_name: Option<&str>,
+        mut variables: HashMap<String, ConstValue>,
+        options: ExecOptions,
+    ) -> ExecCtx {
+        //nb: an unknown operation fails when it's executed, there are just no
+        //defaults to apply
+        if let Ok(operation) = self.operation(operation_name) {
+            apply_variable_defaults(&operation.definition, &mut variables);
+        }
+
+        ExecCtx::new(
+            self.exec_schema.clone(),
+            &self.config,
+            variables,
+            options,
+            self.fragments.clone(),
+            self.source.clone(),
+        )
+    }
+
+    /// Executes the selected operation up to its initial result, meant to be
+    /// spawned so it can be aborted as a whole
+    async fn execute_operation<R: ObjectResolver + 'static>(
+        self,
+        ectx: ExecCtx,
+        query_resolver: R,
+        operation_name: Option<String>,
+        timeout: Option<Duration>,
+    ) -> Result<Completed, GraphQLError> {
+        let timeout = timeout.or(self.config.timeout);
+
+        let query_op = self.operation(operation_name.as_deref())?;
+
+        let sel_set = query_op.definition.selection_set();
+        check_depth(&ectx, &self.config, sel_set)?;
+        check_variables(&ectx, &query_op.definition)?;
+        check_complexity(&ectx, &self.config, sel_set)?;
+
+        let query_type = query_op
+            .root_type
+            .clone()
+            .ok_or_else(|| anyhow!("query type not found"))?;
+
+        check_introspection(&ectx, &self.config, &query_type, sel_set)?;
+
+        let ts = self.type_system.clone();
+
+        //nb: fragments deferred at the root are executed after the initial
+        //payload, so they share ownership of the root resolver
+        let schema_resolver: Arc<dyn ObjectResolver> = Arc::new(IspRootResolver {
+            schema_def: ts.definitions.schema.clone(),
+            inner: Arc::new(query_resolver),
+            ts,
+        });
+
+        let query_resolver = IspObjectResolver {
+            type_def: query_type.clone(),
+            inner: schema_resolver.as_ref(),
+        };
+
+        let operation = OperationInfo {
+            name: query_op.definition.name(),
+            ty: query_op.definition.operation_ty(),
+            source: &self.source,
+        };
+        let spans = operation_spans(&ectx, &operation);
+
+        //nb: field spans are created along with their futures, so the
+        //selection set has to be built inside the operation's span
+        let query_fut = {
+            let _entered = spans.last().map(Span::enter);
+            let mut query_fut =
+                ExecuteSelectionSet::new(&ectx, &query_resolver, query_type.clone(), sel_set)?;
+            defer_fragments(
+                &ectx,
+                schema_resolver.clone(),
+                query_type,
+                query_fut.take_deferred(),
+                ResponsePath::default(),
+            );
+            query_fut
+        };
+
+        let exec_start = Instant::now();
+
+        //nb: on timeout or cancellation the selection set future (and every
+        //resolver future beneath it) is dropped here, so no resolver work
+        //outlives the request. That's why ExecuteSelectionSet doesn't check
+        //the token in its poll loop: `cancelled()` wakes this task as soon as
+        //the token is cancelled, even while every resolver is pending, and a
+        //dropped future is never polled again
+        let query_fut = async {
+            tokio::select! {
+                completed = query_fut => Ok(completed),
+                _ = ectx.cancellation_token().cancelled() => Err(cancelled()),
+            }
+        };
+
+        let mut query_fut: Pin<Box<dyn Future<Output = _> + Send + '_>> = Box::pin(query_fut);
+        for span in spans.into_iter().rev() {
+            query_fut = Box::pin(query_fut.instrument(span));
+        }
+
+        let completed = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, query_fut).await {
+                Ok(completed) => completed?,
+                Err(_) => {
+                    return Err(GraphQLError::new(format!(
+                        "operation timed out after {}ms",
+                        timeout.as_millis()
+                    ))
+                    .extension("code", "TIMEOUT")
+                    .with_kind(ErrorKind::Timeout))
+                }
+            },
+            None => query_fut.await?,
+        };
+
+        tracing::info!(
+            "query took {}μs",
+            Instant::now().duration_since(exec_start).as_micros()
+        );
+
+        Ok(completed)
+    }
+
+    /// Selects the operation to execute, following the spec: a name is only
+    /// needed when the document has more than one operation
+    ///
+    /// [Reference](https://spec.graphql.org/October2021/#GetOperation())
+    fn operation(&self, operation_name: Option<&str>) -> Result<&PreparedOperation> {
+        match operation_name {
+            Some(name) => self
+                .operations
+                .iter()
+                .find(|op| op.definition.name() == Some(name))
+                .ok_or_else(|| anyhow!("unknown operation named \"{}\"", name)),
+            None => match self.operations.as_slice() {
+                [operation] => Ok(operation),
+                [] => Err(anyhow!("document does not contain an operation")),
+                _ => Err(anyhow!(
+                    "document contains multiple operations, an operation name must be provided"
+                )),
+            },
+        }
+    }
+}
+
+/// Adds the default value of each variable the caller didn't provide, e.g.
+/// `10` for `query ($limit: Int = 10)`. A variable given as `null` keeps its
+/// `null`.
+///
+/// https://spec.graphql.org/October2021/#sec-Coercing-Variable-Values
+fn apply_variable_defaults(
+    operation: &OperationDefinition,
+    variables: &mut HashMap<String, ConstValue>,
+) {
+    for var_def in operation.variables() {
+        if variables.contains_key(var_def.name()) {
+            continue;
+        }
+
+        //nb: default values are constants, so there are no variables to
+        //resolve them against
+        let default = var_def
+            .default_value()
+            .and_then(|value| resolve_value(&HashMap::new(), value).ok());
+
+        if let Some(default) = default {
+            variables.insert(var_def.name().to_owned(), default);
+        }
+    }
+}
+
+/// Collects the operation spans of the registered middleware, each created
+/// inside the previous one's so they nest in registration order
+fn operation_spans(ectx: &ExecCtx, operation: &OperationInfo<'_>) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+
+    for middleware in ectx.middleware.iter() {
+        let parent = spans.last().cloned();
+        let _entered = parent.as_ref().map(Span::enter);
+
+        if let Some(span) = middleware.operation_span(operation) {
+            spans.push(span);
+        }
+    }
+
+    spans
+}
+
+/// Sends incremental results as they complete, batching the ones that
+/// complete together into one payload, until there are none left
+async fn deliver_incremental(
+    ectx: &ExecCtx,
+    queue: &IncrementalQueue,
+    payloads: &mpsc::UnboundedSender<IncrementalResponse>,
+) {
+    let mut pending = SelectAll::new();
+
+    loop {
+        pending.extend(queue.drain());
+
+        let next = tokio::select! {
+            next = pending.next() => next,
+            _ = ectx.cancellation_token().cancelled() => {
+                let _ = payloads.unbounded_send(IncrementalResponse {
+                    data: None,
+                    errors: vec![cancelled()],
+                    incremental: Vec::new(),
+                    has_next: false,
+                });
+                return;
+            }
+        };
+
+        //nb: results are queued while pending ones are polled, so anything
+        //queued since the last drain still has to be delivered
+        let Some(result) = next else {
+            if queue.is_empty() {
+                break;
+            }
+            continue;
+        };
+
+        let mut incremental = vec![result];
+        while let Some(Some(result)) = pending.next().now_or_never() {
+            incremental.push(result);
+        }
+
+        pending.extend(queue.drain());
+        let has_next = !pending.is_empty();
+
+        let payload = IncrementalResponse {
+            data: None,
+            errors: Vec::new(),
+            incremental,
+            has_next,
+        };
+
+        if payloads.unbounded_send(payload).is_err() || !has_next {
+            return;
+        }
+    }
+
+    //nb: the last results were sent before their streams were known to be
+    //finished, so the end of the response is a payload of its own
+    let _ = payloads.unbounded_send(IncrementalResponse {
+        data: None,
+        errors: Vec::new(),
+        incremental: Vec::new(),
+        has_next: false,
+    });
+}
+
+fn cancelled() -> GraphQLError {
+    GraphQLError::new("operation was cancelled")
+        .extension("code", "CANCELLED")
+        .with_kind(ErrorKind::Cancelled)
+}
@@ -0,0 +1,212 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use lru::LruCache;
+
+use super::PreparedQuery;
+use crate::value::ConstValue;
+
+/// Default number of documents kept by [`QueryCache`]
+pub(crate) const DEFAULT_QUERY_CACHE_SIZE: usize = 512;
+
+/// Number of introspection results kept by [`IntrospectionCache`], clients
+/// tend to send one of a handful of introspection queries
+const INTROSPECTION_CACHE_SIZE: usize = 16;
+
+/// LRU cache of prepared documents keyed by query text, so repeated identical
+/// queries (GraphiQL polling, client retries) skip compilation and validation.
+///
+/// Each schema loaded into an [`Executor`](super::Executor) has its own
+/// cache, so reloading the schema starts from an empty one. Entries only hold
+/// the extracted HIR, never the compiler used to validate them.
+pub(crate) struct QueryCache {
+    entries: Mutex<LruCache<String, PreparedQuery>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl QueryCache {
+    /// Creates a cache holding up to `capacity` documents, `None` if caching
+    /// is disabled (a capacity of 0)
+    pub(crate) fn new(capacity: usize) -> Option<Self> {
+        let capacity = NonZeroUsize::new(capacity)?;
+
+        Some(Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    pub(crate) fn get(&self, query: &str) -> Option<PreparedQuery> {
+        let prepared = self
+            .entries
+            .lock()
+            .expect("query cache lock poisoned")
+            .get(query)
+            .cloned();
+
+        let (hits, misses) = if prepared.is_some() {
+            (
+                self.hits.fetch_add(1, Ordering::Relaxed) + 1,
+                self.misses.load(Ordering::Relaxed),
+            )
+        } else {
+            (
+                self.hits.load(Ordering::Relaxed),
+                self.misses.fetch_add(1, Ordering::Relaxed) + 1,
+            )
+        };
+
+        tracing::debug!(
+            hit = prepared.is_some(),
+            hits,
+            misses,
+            "query cache hit rate: {:.1}%",
+            hits as f64 * 100.0 / (hits + misses) as f64
+        );
+
+        prepared
+    }
+
+    pub(crate) fn insert(&self, query: String, prepared: PreparedQuery) {
+        self.entries
+            .lock()
+            .expect("query cache lock poisoned")
+            .put(query, prepared);
+    }
+}
+
+/// LRU cache of the data of introspection-only operations, keyed by query
+/// text and operation name. Introspection only depends on the schema, so
+/// like [`QueryCache`] there's one per loaded schema, kept separately so it
+/// doesn't depend on the document being in the query cache.
+pub(crate) struct IntrospectionCache {
+    entries: Mutex<LruCache<(Arc<str>, Option<String>), ConstValue>>,
+}
+
+impl IntrospectionCache {
+    pub(crate) fn new() -> Self {
+        let capacity = NonZeroUsize::new(INTROSPECTION_CACHE_SIZE).expect("non-zero capacity");
+
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    pub(crate) fn get(
+        &self,
+        source: &Arc<str>,
+        operation_name: &Option<String>,
+    ) -> Option<ConstValue> {
+        self.entries
+            .lock()
+            .expect("introspection cache lock poisoned")
+            .get(&(source.clone(), operation_name.clone()))
+            .cloned()
+    }
+
+    pub(crate) fn insert(
+        &self,
+        source: Arc<str>,
+        operation_name: Option<String>,
+        data: ConstValue,
+    ) {
+        self.entries
+            .lock()
+            .expect("introspection cache lock poisoned")
+            .put((source, operation_name), data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Executor;
+
+    const SCHEMA: &str = "type Query { a: Int b: Int c: Int }";
+
+    /// Hits and misses of the executor's current query cache
+    fn counts(executor: &Executor) -> (u64, u64) {
+        let schema = executor.loaded_schema();
+        let cache = schema.query_cache.as_ref().expect("query cache enabled");
+
+        (
+            cache.hits.load(Ordering::Relaxed),
+            cache.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    #[test]
+    fn repeated_queries_skip_compilation() {
+        let executor = Executor::new(SCHEMA).unwrap();
+
+        executor.prepare("{ a }").unwrap();
+        assert_eq!(counts(&executor), (0, 1));
+
+        executor.prepare("{ a }").unwrap();
+        assert_eq!(counts(&executor), (1, 1));
+
+        //nb: keyed by the exact query text
+        executor.prepare("{ a b }").unwrap();
+        assert_eq!(counts(&executor), (1, 2));
+    }
+
+    #[test]
+    fn zero_size_disables_the_cache() {
+        let executor = Executor::builder()
+            .type_system(SCHEMA)
+            .query_cache(0)
+            .build()
+            .unwrap();
+
+        assert!(executor.loaded_schema().query_cache.is_none());
+        executor.prepare("{ a }").unwrap();
+        executor.prepare("{ a }").unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_documents_are_evicted() {
+        let executor = Executor::builder()
+            .type_system(SCHEMA)
+            .query_cache(2)
+            .build()
+            .unwrap();
+
+        for query in ["{ a }", "{ b }", "{ c }", "{ b }"] {
+            executor.prepare(query).unwrap();
+        }
+        assert_eq!(counts(&executor), (1, 3));
+
+        //nb: `{ a }` was evicted by `{ c }`
+        executor.prepare("{ a }").unwrap();
+        assert_eq!(counts(&executor), (1, 4));
+
+        //nb: and in turn evicted `{ c }`, since `{ b }` was used more recently
+        let schema = executor.loaded_schema();
+        let entries = schema.query_cache.as_ref().unwrap().entries.lock().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(!entries.contains("{ c }"));
+    }
+
+    #[test]
+    fn replacing_the_schema_drops_prepared_documents() {
+        let executor = Executor::new(SCHEMA).unwrap();
+
+        executor.prepare("{ c }").unwrap();
+        executor.prepare("{ c }").unwrap();
+        assert_eq!(counts(&executor), (1, 1));
+
+        //nb: `{ c }` is validated again, against a schema without `c`
+        executor
+            .replace_schema("type Query { a: Int b: Int }")
+            .unwrap();
+        assert!(executor.prepare("{ c }").is_err());
+        assert_eq!(counts(&executor), (0, 1));
+    }
+}
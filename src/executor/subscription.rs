@@ -0,0 +1,40 @@
+//! Subscriptions: executing an operation once per event of the subscribed
+//! field's stream, see [`Executor::subscribe`](crate::Executor::subscribe)
+
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::resolver::{Ctx, ObjectResolver, Resolved};
+
+/// Root resolver for executing a subscription operation against one event of
+/// its source stream, the event is the subscribed field's value
+pub(crate) struct SubscriptionEvent {
+    field_name: String,
+    event: Mutex<Option<Result<Resolved>>>,
+}
+
+impl SubscriptionEvent {
+    pub(crate) fn new(field_name: String, event: Result<Resolved>) -> Self {
+        Self {
+            field_name,
+            event: Mutex::new(Some(event)),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectResolver for SubscriptionEvent {
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        if name != self.field_name {
+            return Err(anyhow!("field not found: {}", name));
+        }
+
+        self.event
+            .lock()
+            .expect("subscription event lock poisoned")
+            .take()
+            .unwrap_or_else(|| Err(anyhow!("subscription event already resolved")))
+    }
+}
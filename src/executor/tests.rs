@@ -0,0 +1,3886 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+
+use super::Executor;
+use crate::{
+    ConstValue, Ctx, Data, DataLoader, ErrorKind, GraphQLError, Id, Location, Name, ObjectResolver,
+    PathSegment, Request, Resolved, Response, SelectedField, SyncObjectResolver,
+};
+
+const SCHEMA: &str = r#"
+schema {
+  query: Query
+}
+
+type Query {
+  person: Person!
+  maybePerson: Person
+  greet(name: String): String!
+  limit(limit: Int): Int!
+  viewer: String
+  slow: String
+  friendName(id: Int!): String
+  sum(values: [Int!]!): Int!
+  echo(values: [[Int]]): String
+  sorted(sort: Sort): String
+}
+
+enum Sort {
+  ASC
+  DESC
+}
+
+type Person {
+  firstName: String!
+  lastName: String!
+  age: Int
+  nickname: String
+  requiredNickname: String!
+  favoriteColor: String
+}
+"#;
+
+struct QueryResolver;
+
+struct Viewer(String);
+
+#[async_trait::async_trait]
+impl ObjectResolver for QueryResolver {
+    async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "person" | "maybePerson" => Ok(Resolved::object(PersonResolver)),
+            "greet" => {
+                let name = ctx.arg_opt::<String>("name")?;
+                Ok(Resolved::string(format!(
+                    "Hello, {}",
+                    name.as_deref().unwrap_or("stranger")
+                )))
+            }
+            "limit" => Ok(ConstValue::from(ctx.arg_with_default("limit", 10)).into()),
+            "sum" => {
+                let values = ctx.try_arg::<Vec<i32>>("values")?;
+                Ok(ConstValue::from(values.iter().sum::<i32>()).into())
+            }
+            "sorted" => Ok(Resolved::string_opt(
+                ctx.arg_opt::<ConstValue>("sort")?
+                    .map(|sort| sort.to_string()),
+            )),
+            "echo" => Ok(Resolved::string_opt(
+                ctx.arg_opt::<ConstValue>("values")?
+                    .map(|values| values.to_string()),
+            )),
+            "friendName" => {
+                let loader = ctx.data_unchecked::<DataLoader<i32, String>>();
+                let id = ctx.try_arg::<i32>("id")?;
+                Ok(Resolved::string_opt(loader.load(id).await))
+            }
+            "slow" => {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(Resolved::string("done"))
+            }
+            "viewer" => Ok(Resolved::string_opt(
+                ctx.data::<Viewer>().map(|viewer| viewer.0.as_str()),
+            )),
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
+
+struct PersonResolver;
+
+#[async_trait::async_trait]
+impl ObjectResolver for PersonResolver {
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "firstName" => Ok(Resolved::string("Zack")),
+            "lastName" => Ok(Resolved::string("Angelo")),
+            "age" => Ok(ConstValue::from(39).into()),
+            "nickname" | "requiredNickname" => Err(GraphQLError::new("nickname unavailable")
+                .extension("code", "UNAVAILABLE")
+                .into()),
+            "favoriteColor" => panic!("favorite color resolver blew up"),
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
+
+async fn execute(query: &str) -> Response {
+    let executor = Executor::new(SCHEMA).unwrap();
+    executor
+        .run(query, QueryResolver, None, HashMap::new())
+        .await
+}
+
+async fn run(query: &str) -> Result<ConstValue, GraphQLError> {
+    execute(query).await.into_result()
+}
+
+fn error_path(err: &GraphQLError) -> Vec<String> {
+    err.path()
+        .unwrap_or_default()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn expected(value: serde_json::Value) -> ConstValue {
+    ConstValue::from_json(value)
+}
+
+#[tokio::test]
+async fn response_keys_follow_selection_order() {
+    struct SlowGreeting;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for SlowGreeting {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            if name == "greet" {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            QueryResolver.resolve_field(ctx, name).await
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let result = executor
+        .run(
+            r#"query { greeting: greet(name: "Zack") person { lastName age } limit }"#,
+            SlowGreeting,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result.to_string(),
+        r#"{"greeting":"Hello, Zack","person":{"lastName":"Angelo","age":39},"limit":10}"#
+    );
+}
+
+#[tokio::test]
+async fn nested_and_fragment_keys_follow_selection_order() {
+    struct SlowFirstName;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for SlowFirstName {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "person" => Ok(Resolved::object(SlowFirstName)),
+                "firstName" => {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    PersonResolver.resolve_field(ctx, name).await
+                }
+                _ => PersonResolver.resolve_field(ctx, name).await,
+            }
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let result = executor
+        .run(
+            r#"
+            query {
+              person { first: firstName ...Names age }
+            }
+
+            fragment Names on Person { lastName firstName }
+            "#,
+            SlowFirstName,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result.to_string(),
+        r#"{"person":{"first":"Zack","lastName":"Angelo","firstName":"Zack","age":39}}"#
+    );
+}
+
+#[tokio::test]
+async fn serialized_values_are_completed_against_selections() {
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PersonRow {
+        first_name: &'static str,
+        last_name: &'static str,
+        age: Option<i32>,
+        password_hash: &'static str,
+    }
+
+    struct Rows;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Rows {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "person" => Resolved::from_serialize(&PersonRow {
+                    first_name: "Zack",
+                    last_name: "Angelo",
+                    age: None,
+                    password_hash: "5f4dcc3b",
+                }),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let result = executor
+        .run(
+            "query { person { __typename name: firstName age } }",
+            Rows,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "person": { "__typename": "Person", "name": "Zack", "age": null }
+        }))
+    );
+
+    let result = run_pets_with(
+        "query { allPets { name ... on Dog { barks } } }",
+        json!([
+            { "__typename": "Dog", "name": "Rex", "barks": true, "owner": "Zack" },
+            { "__typename": "Cat", "name": "Tom" }
+        ]),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "allPets": [{ "name": "Rex", "barks": true }, { "name": "Tom" }]
+        }))
+    );
+}
+
+#[tokio::test]
+async fn operations_over_the_complexity_limit_are_rejected() {
+    const SCHEMA: &str = r#"
+        directive @cost(weight: Int = 1, multipliers: [String!]) on FIELD_DEFINITION
+
+        type Query {
+          people(first: Int = 10): [Person!]!
+          search(query: String!, pageSize: Int): [Person!]! @cost(weight: 5, multipliers: ["pageSize"])
+        }
+
+        type Person {
+          name: String!
+          friends(first: Int): [Person!]!
+        }
+    "#;
+
+    struct Nobody;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Nobody {
+        async fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::Array(Vec::new()))
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .max_complexity(20)
+        .build()
+        .unwrap();
+
+    //nb: (1 + 1) * the default `first` of 10
+    let response = executor
+        .run("{ people { name } }", Nobody, None, HashMap::new())
+        .await;
+    assert!(response.is_ok());
+    assert_eq!(
+        response.extensions["complexity"],
+        expected(json!({ "cost": 20, "max": 20 }))
+    );
+
+    //nb: friends cost (1 + 1) * 3, people (1 + 1 + 6) * 2
+    let response = executor
+        .run(
+            "query ($n: Int) { people(first: $n) { name friends(first: 3) { name } } }",
+            Nobody,
+            None,
+            HashMap::from([("n".to_owned(), ConstValue::from(2))]),
+        )
+        .await;
+    assert!(response.is_ok());
+    assert_eq!(
+        response.extensions["complexity"],
+        expected(json!({ "cost": 16, "max": 20 }))
+    );
+
+    //nb: (5 + 1) * 4
+    let err = executor
+        .run(
+            r#"{ search(query: "ada", pageSize: 4) { ...Name } } fragment Name on Person { name }"#,
+            Nobody,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap_err();
+    assert_eq!(
+        err.message(),
+        "query complexity 24 exceeds maximum allowed complexity of 20"
+    );
+}
+
+#[tokio::test]
+async fn merges_selection_sets_of_repeated_fields() {
+    let result = run(r#"
+        query {
+          person { firstName }
+          person { age }
+          ... on Query { person { lastName } }
+        }
+    "#)
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "person": { "firstName": "Zack", "age": 39, "lastName": "Angelo" }
+        }))
+    );
+}
+
+#[tokio::test]
+async fn repeated_fields_must_have_the_same_arguments() {
+    let result = run(r#"query { greet(name: "Zack") ... on Query { greet(name: "Zack") } }"#)
+        .await
+        .unwrap();
+    assert_eq!(result, expected(json!({ "greet": "Hello, Zack" })));
+
+    let err = run(r#"query { greet(name: "Zack") greet(name: "Angelo") }"#)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.message(),
+        "fields with response key greet conflict because they have differing arguments"
+    );
+
+    let err = run("query { person { name: firstName name: lastName } }")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.message(),
+        "fields firstName and lastName conflict because they share the response key name"
+    );
+}
+
+const PET_SCHEMA: &str = r#"
+schema {
+  query: Query
+}
+
+type Query {
+  pet: Pet!
+  pets: [PetResult!]!
+  allPets: [Pet!]!
+}
+
+interface Pet {
+  name: String!
+}
+
+type Dog implements Pet {
+  name: String!
+  barks: Boolean!
+  friend: Pet
+}
+
+type Cat implements Pet {
+  name: String!
+}
+
+union PetResult = Dog | Cat
+
+directive @stream(initialCount: Int = 0, if: Boolean = true, label: String) on FIELD
+directive @defer(if: Boolean = true, label: String) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+"#;
+
+struct PetQueryResolver;
+
+#[async_trait::async_trait]
+impl ObjectResolver for PetQueryResolver {
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "pet" => Ok(Resolved::object(Arc::new(DogResolver))),
+            "pets" | "allPets" => Ok(Resolved::array([
+                Resolved::object(DogResolver),
+                Resolved::object(CatResolver),
+            ])),
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
+
+struct DogResolver;
+
+#[async_trait::async_trait]
+impl ObjectResolver for DogResolver {
+    async fn resolve_type_name(&self) -> Result<Option<&str>> {
+        Ok(Some("Dog"))
+    }
+
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "name" => Ok(Resolved::string("Rex")),
+            "barks" => Ok(ConstValue::from(true).into()),
+            "friend" => Ok(Resolved::null()),
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
+
+struct CatResolver;
+
+#[async_trait::async_trait]
+impl ObjectResolver for CatResolver {
+    async fn resolve_type_name(&self) -> Result<Option<&str>> {
+        Ok(Some("Cat"))
+    }
+
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match name {
+            "name" => Ok(Resolved::string("Tom")),
+            _ => Err(anyhow!("invalid field: {}", name)),
+        }
+    }
+}
+
+async fn run_pets(query: &str) -> Result<ConstValue, GraphQLError> {
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+    executor
+        .run(query, PetQueryResolver, None, HashMap::new())
+        .await
+        .into_result()
+}
+
+/// Runs `query` against [`PET_SCHEMA`] with every root field resolving to
+/// `value`
+async fn run_pets_with(query: &str, value: serde_json::Value) -> Result<ConstValue, GraphQLError> {
+    struct Fixed(serde_json::Value);
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Fixed {
+        async fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::from_json(self.0.clone()))
+        }
+    }
+
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+    executor
+        .run(query, Fixed(value), None, HashMap::new())
+        .await
+        .into_result()
+}
+
+#[tokio::test]
+async fn resolves_interface_type_through_arc() {
+    let result = run_pets("query { pet { name ... on Dog { barks } } }")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "pet": { "name": "Rex", "barks": true } }))
+    );
+}
+
+#[tokio::test]
+async fn field_errors_have_path_and_location() {
+    let err = run("query {\n  person {\n    nickname\n  }\n}")
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.message(), "nickname unavailable");
+    assert_eq!(
+        err.path(),
+        Some(
+            &[
+                PathSegment::Field(Name::new("person")),
+                PathSegment::Field(Name::new("nickname"))
+            ][..]
+        )
+    );
+    assert_eq!(err.locations(), &[Location { line: 3, column: 5 }]);
+    assert_eq!(
+        err.extensions().get("code"),
+        Some(&ConstValue::from("UNAVAILABLE"))
+    );
+}
+
+#[tokio::test]
+async fn nullable_field_error_returns_partial_data() {
+    let response = execute("query { person { firstName nickname } }").await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({ "person": { "firstName": "Zack", "nickname": null } }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(error_path(&response.errors[0]), ["person", "nickname"]);
+}
+
+#[tokio::test]
+async fn non_null_field_error_nulls_nearest_nullable_parent() {
+    let response = execute("query { person { firstName } maybePerson { requiredNickname } }").await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({ "person": { "firstName": "Zack" }, "maybePerson": null }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        error_path(&response.errors[0]),
+        ["maybePerson", "requiredNickname"]
+    );
+}
+
+#[tokio::test]
+async fn non_null_field_error_propagates_to_data() {
+    let response = execute("query { person { requiredNickname } }").await;
+
+    assert_eq!(response.data, ConstValue::Null);
+    assert_eq!(response.errors.len(), 1);
+    assert!(!response.is_ok());
+}
+
+#[tokio::test]
+async fn aliased_introspection_fields() {
+    let result = run(r#"
+        query {
+          s: __schema { query: queryType { n: name } }
+          t: __typename
+          person { kind: __typename firstName }
+        }
+    "#)
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "s": { "query": { "n": "Query" } },
+            "t": "Query",
+            "person": { "kind": "Person", "firstName": "Zack" }
+        }))
+    );
+}
+
+#[tokio::test]
+async fn typename_reports_concrete_type_of_interface() {
+    let result = run_pets("query { pet { __typename } }").await.unwrap();
+
+    assert_eq!(result, expected(json!({ "pet": { "__typename": "Dog" } })));
+}
+
+#[tokio::test]
+async fn interface_fields_resolve_on_concrete_types() {
+    let result = run_pets("query { allPets { name __typename ... on Dog { barks } } }")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "allPets": [
+                { "name": "Rex", "__typename": "Dog", "barks": true },
+                { "name": "Tom", "__typename": "Cat" }
+            ]
+        }))
+    );
+}
+
+#[tokio::test]
+async fn typename_reports_concrete_types_in_union_list() {
+    let result = run_pets(
+        r#"
+        query {
+          pets {
+            ... on Dog { __typename barks }
+            ... on Cat { __typename name }
+          }
+        }
+    "#,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "pets": [
+                { "__typename": "Dog", "barks": true },
+                { "__typename": "Cat", "name": "Tom" }
+            ]
+        }))
+    );
+}
+
+#[tokio::test]
+async fn union_member_without_type_name_fails() {
+    struct UntypedPets;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for UntypedPets {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "pets" => Ok(Resolved::array([
+                    Resolved::object(DogResolver),
+                    Resolved::object(PersonResolver),
+                ])),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+    let response = executor
+        .run(
+            "query { pets { ... on Dog { name } } }",
+            UntypedPets,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(response.data, ConstValue::Null);
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].message(),
+        "resolver did not return concrete type for PetResult"
+    );
+    assert_eq!(error_path(&response.errors[0]), ["pets", "1"]);
+}
+
+#[tokio::test]
+async fn interface_fragments_apply_to_union_members() {
+    let result = run_pets("query { pets { ... on Pet { name } ... on Dog { barks } } }")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "pets": [{ "name": "Rex", "barks": true }, { "name": "Tom" }]
+        }))
+    );
+}
+
+#[tokio::test]
+async fn union_members_resolve_their_own_fields() {
+    let result = run_pets(
+        r#"
+        query {
+          pets {
+            __typename
+            ... on Dog { barks friend { name } }
+            ... on Pet { name }
+          }
+        }
+    "#,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "pets": [
+                { "__typename": "Dog", "barks": true, "friend": null, "name": "Rex" },
+                { "__typename": "Cat", "name": "Tom" }
+            ]
+        }))
+    );
+}
+
+#[tokio::test]
+async fn union_fragments_and_possible_types() {
+    let result = run_pets(
+        r#"
+        query {
+          pets { ... on PetResult { ... on Dog { barks } ... on Cat { name } } }
+          __type(name: "PetResult") { possibleTypes { name } }
+        }
+    "#,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "pets": [{ "barks": true }, { "name": "Tom" }],
+            "__type": { "possibleTypes": [{ "name": "Dog" }, { "name": "Cat" }] }
+        }))
+    );
+}
+
+#[tokio::test]
+async fn prepared_query_executes_repeatedly() {
+    fn assert_send_sync<T: Clone + Send + Sync>(_: &T) {}
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let prepared = executor
+        .prepare(
+            r#"
+            query Name { person { firstName } }
+            query Age { person { age } }
+        "#,
+        )
+        .unwrap();
+
+    assert_send_sync(&prepared);
+
+    for _ in 0..2 {
+        let result = prepared
+            .execute(QueryResolver, Some("Age".to_owned()), HashMap::new())
+            .await
+            .into_result()
+            .unwrap();
+
+        assert_eq!(result, expected(json!({ "person": { "age": 39 } })));
+    }
+
+    let response = prepared
+        .execute(QueryResolver, Some("Missing".to_owned()), HashMap::new())
+        .await;
+    assert!(!response.is_ok());
+}
+
+#[tokio::test]
+async fn operation_is_selected_by_name() {
+    let executor = Executor::new(SCHEMA).unwrap();
+
+    let single = executor
+        .execute(
+            Request::new("query Name { person { firstName } }"),
+            QueryResolver,
+        )
+        .await;
+    assert_eq!(
+        single.into_result().unwrap(),
+        expected(json!({ "person": { "firstName": "Zack" } }))
+    );
+
+    let multi = "query Name { person { firstName } } query Age { person { age } }";
+
+    let named = executor
+        .execute(Request::new(multi).operation_name("Age"), QueryResolver)
+        .await;
+    assert_eq!(
+        named.into_result().unwrap(),
+        expected(json!({ "person": { "age": 39 } }))
+    );
+
+    let ambiguous = executor.execute(Request::new(multi), QueryResolver).await;
+    assert_eq!(ambiguous.data, ConstValue::Null);
+    assert_eq!(
+        ambiguous.errors[0].message(),
+        "document contains multiple operations, an operation name must be provided"
+    );
+
+    let unknown = executor
+        .execute(Request::new(multi).operation_name("Missing"), QueryResolver)
+        .await;
+    assert_eq!(
+        unknown.errors[0].message(),
+        "unknown operation named \"Missing\""
+    );
+}
+
+#[tokio::test]
+async fn resolver_panic_becomes_field_error() {
+    let response = execute("query { person { firstName favoriteColor } }").await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({ "person": { "firstName": "Zack", "favoriteColor": null } }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message(), "internal server error");
+    assert_eq!(
+        response.errors[0].extensions()["code"],
+        ConstValue::from("INTERNAL_SERVER_ERROR")
+    );
+    assert_eq!(error_path(&response.errors[0]), ["person", "favoriteColor"]);
+}
+
+#[tokio::test]
+async fn execution_panic_becomes_null_data_with_error() {
+    struct Exploding;
+
+    impl crate::ExecutionObserver for Exploding {
+        fn on_field_start(&self, _field: &crate::FieldInfo<'_>) {
+            panic!("observer blew up");
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .observer(Exploding)
+        .build()
+        .unwrap();
+
+    let response = executor
+        .execute(
+            Request::new("query { person { firstName } }"),
+            QueryResolver,
+        )
+        .await;
+
+    assert_eq!(response.data, ConstValue::Null);
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.into_json().unwrap(),
+        json!({
+            "data": null,
+            "errors": [{
+                "message": "internal server error",
+                "extensions": { "code": "INTERNAL_SERVER_ERROR" }
+            }]
+        })
+    );
+}
+
+#[tokio::test]
+async fn optional_arguments() {
+    let result = run(r#"
+        query {
+          named: greet(name: "Zack")
+          absent: greet
+          null: greet(name: null)
+        }
+    "#)
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "named": "Hello, Zack",
+            "absent": "Hello, stranger",
+            "null": "Hello, stranger"
+        }))
+    );
+}
+
+#[tokio::test]
+async fn raw_arguments_are_coerced_values() {
+    struct RawArgs;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for RawArgs {
+        async fn resolve_field(&self, ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::string_opt(
+                ctx.raw_arg("values")
+                    .or_else(|| ctx.raw_arg("sort"))
+                    .map(|value| format!("{:?}", value)),
+            ))
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let result = executor
+        .run(
+            r#"
+            query Raw($sort: Sort, $absent: [[Int]]) {
+              literal: echo(values: [1, [2]])
+              variable: sorted(sort: $sort)
+              absent: echo(values: $absent)
+            }
+            "#,
+            RawArgs,
+            None,
+            HashMap::from([("sort".to_owned(), ConstValue::from("ASC"))]),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    let list = ConstValue::List;
+    assert_eq!(
+        result,
+        expected(json!({
+            "literal": format!("{:?}", list(vec![list(vec![1.into()]), list(vec![2.into()])])),
+            "variable": format!("{:?}", ConstValue::Enum(Name::new("ASC"))),
+            "absent": null
+        }))
+    );
+}
+
+#[tokio::test]
+async fn argument_defaults() {
+    let result = run("query { given: limit(limit: 3) absent: limit null: limit(limit: null) }")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "given": 3, "absent": 10, "null": 10 }))
+    );
+}
+
+#[tokio::test]
+async fn disabled_introspection_rejects_schema_queries() {
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .enable_introspection(false)
+        .build()
+        .unwrap();
+
+    let err = executor
+        .run(
+            "query { __schema { queryType { name } } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap_err();
+    assert_eq!(err.message(), "introspection is disabled");
+
+    let result = executor
+        .run(
+            "query { person { __typename firstName } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(
+        result,
+        expected(json!({ "person": { "__typename": "Person", "firstName": "Zack" } }))
+    );
+}
+
+#[tokio::test]
+async fn single_values_are_coerced_to_lists() {
+    let result = run(r#"
+        query {
+          sum(values: 5)
+          single: echo(values: 1)
+          items: echo(values: [1, 2])
+          nested: echo(values: [[1], 2, null])
+        }
+    "#)
+    .await
+    .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "sum": 5,
+            "single": "[[1]]",
+            "items": "[[1],[2]]",
+            "nested": "[[1],[2],null]"
+        }))
+    );
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let result = executor
+        .run(
+            "query Sum($values: [Int!]!) { sum(values: $values) }",
+            QueryResolver,
+            None,
+            HashMap::from([("values".to_owned(), ConstValue::from(7))]),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(result, expected(json!({ "sum": 7 })));
+}
+
+#[tokio::test]
+async fn json_variables_are_coerced() {
+    let executor = Executor::new(SCHEMA).unwrap();
+    let variables = json!({ "values": [1, 2, 3], "sort": "DESC" });
+    let result = executor
+        .run_json(
+            "query Sum($values: [Int!]!, $sort: Sort) { sum(values: $values) sorted(sort: $sort) }",
+            QueryResolver,
+            None,
+            variables.as_object().unwrap().clone(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(result, expected(json!({ "sum": 6, "sorted": "\"DESC\"" })));
+}
+
+#[tokio::test]
+async fn enum_arguments_must_be_enum_values() {
+    let result = run("query { sorted(sort: DESC) }").await.unwrap();
+    assert_eq!(result, expected(json!({ "sorted": "\"DESC\"" })));
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let response = executor
+        .run(
+            "query Sorted($sort: Sort) { sorted(sort: $sort) }",
+            QueryResolver,
+            None,
+            HashMap::from([("sort".to_owned(), ConstValue::from("NOT_A_VALUE"))]),
+        )
+        .await;
+
+    assert_eq!(response.data, expected(json!({ "sorted": null })));
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].message(),
+        "invalid value NOT_A_VALUE for enum Sort, expected one of: ASC, DESC"
+    );
+}
+
+#[tokio::test]
+async fn request_data_is_visible_to_resolvers() {
+    let executor = Executor::new(SCHEMA).unwrap();
+
+    let mut data = Data::new();
+    data.insert(Viewer("zack".to_owned()));
+
+    let result = executor
+        .run_with_data(
+            "query { viewer }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+            data,
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "viewer": "zack" })));
+
+    let result = run("query { viewer }").await.unwrap();
+    assert_eq!(result, expected(json!({ "viewer": null })));
+
+    let mut data = Data::new();
+    data.insert(Viewer("prepared".to_owned()));
+
+    let result = executor
+        .prepare("query { viewer }")
+        .unwrap()
+        .execute_with_data(QueryResolver, None, HashMap::new(), data)
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "viewer": "prepared" })));
+}
+
+#[tokio::test]
+async fn middleware_runs_around_resolvers() {
+    struct HideAge;
+
+    #[async_trait::async_trait]
+    impl crate::ResolverMiddleware for HideAge {
+        async fn before_field(&self, ctx: &Ctx) -> Result<()> {
+            match ctx.field_name() {
+                "age" => Err(anyhow!("age is private")),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .middleware(HideAge)
+        .build()
+        .unwrap();
+
+    let response = executor
+        .run(
+            "query { person { firstName age } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({ "person": { "firstName": "Zack", "age": null } }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message(), "age is private");
+    assert_eq!(error_path(&response.errors[0]), ["person", "age"]);
+}
+
+#[tokio::test]
+async fn slow_operation_times_out() {
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .timeout(Duration::from_millis(20))
+        .build()
+        .unwrap();
+
+    let response = executor
+        .run("query { slow }", QueryResolver, None, HashMap::new())
+        .await;
+
+    assert_eq!(response.data, ConstValue::Null);
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].message(),
+        "operation timed out after 20ms"
+    );
+    assert_eq!(
+        response.errors[0].extensions().get("code"),
+        Some(&ConstValue::from("TIMEOUT"))
+    );
+    assert_eq!(response.errors[0].kind(), ErrorKind::Timeout);
+}
+
+#[tokio::test]
+async fn wrapper_types_are_unnamed() {
+    let result = run(r#"
+        query {
+          __schema {
+            queryType {
+              fields {
+                name
+                type { kind name description ofType { kind name } }
+              }
+            }
+          }
+        }
+    "#)
+    .await
+    .unwrap();
+
+    let ConstValue::Object(schema) = &result else {
+        panic!("expected object, got {:?}", result);
+    };
+    let fields = schema["__schema"].to_json()["queryType"]["fields"].clone();
+    let person = fields
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|field| field["name"] == "person")
+        .unwrap();
+
+    assert_eq!(
+        person["type"],
+        json!({
+            "kind": "NON_NULL",
+            "name": null,
+            "description": null,
+            "ofType": { "kind": "OBJECT", "name": "Person" }
+        })
+    );
+}
+
+#[tokio::test]
+async fn directives_are_introspected() {
+    let result = run(r#"
+        query {
+          __schema {
+            directives {
+              name
+              locations
+              args { name defaultValue type { kind name ofType { kind name } } }
+            }
+          }
+        }
+    "#)
+    .await
+    .unwrap();
+
+    let ConstValue::Object(schema) = &result else {
+        panic!("expected object, got {:?}", result);
+    };
+    let directives = schema["__schema"].to_json()["directives"].clone();
+    let skip = directives
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|directive| directive["name"] == "skip")
+        .unwrap();
+
+    assert_eq!(
+        skip["args"],
+        json!([{
+            "name": "if",
+            "defaultValue": null,
+            "type": {
+                "kind": "NON_NULL",
+                "name": null,
+                "ofType": { "kind": "SCALAR", "name": "Boolean" }
+            }
+        }])
+    );
+    assert!(skip["locations"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("FIELD")));
+
+    let deprecated = directives
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|directive| directive["name"] == "deprecated")
+        .unwrap();
+
+    assert_eq!(
+        deprecated["args"][0]["defaultValue"],
+        json!("\"No longer supported\"")
+    );
+}
+
+#[tokio::test]
+async fn deprecated_members_need_include_deprecated() {
+    struct Nothing;
+
+    impl SyncObjectResolver for Nothing {
+        fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::null())
+        }
+    }
+
+    let executor = Executor::new(
+        r#"
+        type Query {
+          pet: String
+          legacyPet: String @deprecated(reason: "use `pet`")
+          sorted(sort: Sort): String
+        }
+
+        enum Sort {
+          ASC
+          DESC
+          RANDOM @deprecated
+        }
+    "#,
+    )
+    .unwrap();
+
+    let query = r#"query ($all: Boolean) {
+        query: __type(name: "Query") { fields(includeDeprecated: $all) { name } }
+        sort: __type(name: "Sort") { enumValues(includeDeprecated: $all) { name } }
+    }"#;
+
+    let response = executor
+        .run(query, Nothing, None, HashMap::new())
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        response,
+        expected(json!({
+            "query": { "fields": [{ "name": "pet" }, { "name": "sorted" }] },
+            "sort": { "enumValues": [{ "name": "ASC" }, { "name": "DESC" }] }
+        }))
+    );
+
+    let variables = HashMap::from([("all".to_owned(), ConstValue::Boolean(true))]);
+    let response = executor
+        .run(query, Nothing, None, variables)
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        response,
+        expected(json!({
+            "query": {
+                "fields": [{ "name": "pet" }, { "name": "legacyPet" }, { "name": "sorted" }]
+            },
+            "sort": {
+                "enumValues": [{ "name": "ASC" }, { "name": "DESC" }, { "name": "RANDOM" }]
+            }
+        }))
+    );
+}
+
+#[tokio::test]
+async fn schema_directives_are_introspected() {
+    let executor = Executor::new(&format!(
+        "{}\n\"Caching hints\"\ndirective @cacheControl(maxAge: Int) repeatable on FIELD_DEFINITION | OBJECT",
+        SCHEMA
+    ))
+    .unwrap();
+
+    let result = executor
+        .run(
+            "query { __schema { directives { name description locations isRepeatable args { name } } } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    let directives = result.to_json()["__schema"]["directives"].clone();
+    let directives = directives.as_array().unwrap();
+
+    for builtin in ["skip", "include", "deprecated", "specifiedBy"] {
+        assert!(
+            directives
+                .iter()
+                .any(|directive| directive["name"] == builtin),
+            "missing built-in directive @{}",
+            builtin
+        );
+    }
+
+    let cache_control = directives
+        .iter()
+        .find(|directive| directive["name"] == "cacheControl")
+        .unwrap();
+    assert_eq!(
+        cache_control,
+        &json!({
+            "name": "cacheControl",
+            "description": "Caching hints",
+            "locations": ["FIELD_DEFINITION", "OBJECT"],
+            "isRepeatable": true,
+            "args": [{ "name": "maxAge" }]
+        })
+    );
+}
+
+#[tokio::test]
+async fn scalars_report_specified_by_url() {
+    let executor = Executor::new(
+        r#"
+        scalar DateTime @specifiedBy(url: "https://scalars.graphql.org/andimarek/date-time")
+        type Query { createdAt: DateTime!, count: Int }
+        "#,
+    )
+    .unwrap();
+
+    let result = executor
+        .run(
+            r#"
+            query {
+              dateTime: __type(name: "DateTime") { specifiedByURL }
+              int: __type(name: "Int") { specifiedByURL }
+              query: __type(name: "Query") {
+                specifiedByURL
+                fields { type { specifiedByURL ofType { specifiedByURL } } }
+              }
+            }
+            "#,
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    let url = "https://scalars.graphql.org/andimarek/date-time";
+    assert_eq!(
+        result,
+        expected(json!({
+            "dateTime": { "specifiedByURL": url },
+            "int": { "specifiedByURL": null },
+            "query": {
+                "specifiedByURL": null,
+                "fields": [
+                    { "type": { "specifiedByURL": null, "ofType": { "specifiedByURL": url } } },
+                    { "type": { "specifiedByURL": null, "ofType": null } }
+                ]
+            }
+        }))
+    );
+}
+
+#[tokio::test]
+async fn typed_results() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct PersonQuery {
+        person: Person,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Person {
+        first_name: String,
+        age: Option<i32>,
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+
+    let data: PersonQuery = executor
+        .run_typed(
+            "query { person { firstName age } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        data,
+        PersonQuery {
+            person: Person {
+                first_name: "Zack".to_owned(),
+                age: Some(39)
+            }
+        }
+    );
+
+    let err = executor
+        .run_typed::<PersonQuery, _>(
+            "query { person { firstName nickname } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err.message(), "nickname unavailable");
+}
+
+#[tokio::test]
+async fn look_ahead_expands_fragments_and_aliases() {
+    struct LookAheadResolver(std::sync::Mutex<Vec<SelectedField>>);
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for LookAheadResolver {
+        async fn resolve_field(&self, ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            *self.0.lock().unwrap() = ctx.selected_fields();
+            Ok(Resolved::array(Vec::<Resolved>::new()))
+        }
+    }
+
+    let resolver = Arc::new(LookAheadResolver(Default::default()));
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+    executor
+        .run(
+            r#"
+            query {
+              pets {
+                ...PetName
+                ... on Dog { barks pal: friend { name } }
+                ... on Cat { catName: name }
+              }
+            }
+
+            fragment PetName on Pet { name }
+        "#,
+            resolver.clone(),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    let selected = resolver.0.lock().unwrap().clone();
+    let keys = selected
+        .iter()
+        .map(SelectedField::response_key)
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys.len(), 4);
+    for key in ["name", "barks", "pal", "catName"] {
+        assert!(keys.contains(&key), "{} not selected in {:?}", key, keys);
+    }
+
+    let pal = selected.iter().find(|f| f.response_key() == "pal").unwrap();
+    assert_eq!(pal.name(), "friend");
+    assert_eq!(pal.alias(), Some("pal"));
+    assert!(pal.selects("name"));
+
+    let cat_name = selected
+        .iter()
+        .find(|f| f.response_key() == "catName")
+        .unwrap();
+    assert_eq!(cat_name.name(), "name");
+}
+
+#[tokio::test]
+async fn selected_field_names_flatten_fragments() {
+    struct Names(std::sync::Mutex<Vec<String>>);
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Names {
+        async fn resolve_field(&self, ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            *self.0.lock().unwrap() = ctx
+                .selected_field_names()
+                .into_iter()
+                .map(str::to_owned)
+                .collect();
+            Ok(Resolved::array(Vec::<Resolved>::new()))
+        }
+    }
+
+    let resolver = Arc::new(Names(Default::default()));
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+    executor
+        .run(
+            r#"
+            query {
+              pets {
+                ...PetName
+                ... on Dog { barks pal: friend { name } }
+                ... on Cat { catName: name }
+              }
+            }
+
+            fragment PetName on Pet { name }
+        "#,
+            resolver.clone(),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(*resolver.0.lock().unwrap(), ["name", "barks", "friend"]);
+}
+
+#[tokio::test]
+async fn cancelled_operation_returns_cancelled_error() {
+    let executor = Executor::new(SCHEMA).unwrap();
+    let token = tokio_util::sync::CancellationToken::new();
+
+    let cancel = {
+        let token = token.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            token.cancel();
+        }
+    };
+
+    let request = crate::Request::new("query { slow }").cancellation_token(token);
+    let (response, _) = tokio::join!(executor.execute(request, QueryResolver), cancel);
+
+    assert_eq!(response.data, ConstValue::Null);
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message(), "operation was cancelled");
+    assert_eq!(
+        response.errors[0].extensions().get("code"),
+        Some(&ConstValue::from("CANCELLED"))
+    );
+    assert!(response.errors[0].is_cancelled());
+
+    //nb: a resolver error with the same code isn't a cancellation
+    let error = GraphQLError::new("stopped").code("CANCELLED");
+    assert!(!error.is_cancelled());
+    assert_eq!(error.kind(), ErrorKind::Other);
+}
+
+#[tokio::test]
+async fn sibling_loads_share_one_batch() {
+    let batch_sizes = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let loader = {
+        let batch_sizes = batch_sizes.clone();
+        DataLoader::new(move |ids: Vec<i32>| {
+            batch_sizes.lock().unwrap().push(ids.len());
+            async move {
+                ids.into_iter()
+                    .map(|id| (id, format!("friend {}", id)))
+                    .collect()
+            }
+        })
+    };
+
+    let fields = (0..100)
+        .map(|id| format!("f{}: friendName(id: {})", id, id))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let request = crate::Request::new(format!("query {{ {} }}", fields)).data(loader);
+    let result = executor
+        .execute(request, QueryResolver)
+        .await
+        .into_result()
+        .unwrap();
+
+    let ConstValue::Object(result) = result else {
+        panic!("expected object");
+    };
+    assert_eq!(result.len(), 100);
+    assert_eq!(result["f42"], ConstValue::from("friend 42"));
+    assert_eq!(*batch_sizes.lock().unwrap(), vec![100]);
+}
+
+#[tokio::test]
+async fn replacing_schema_leaves_in_flight_requests_alone() {
+    const V1: &str = "schema { query: Query } type Query { version: Int! }";
+    const V2: &str = "schema { query: Query } type Query { version: Int! added: Boolean! }";
+
+    struct VersionResolver;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for VersionResolver {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "version" => {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(ConstValue::from(1).into())
+                }
+                "added" => Ok(ConstValue::from(true).into()),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(V1).unwrap();
+    assert!(!executor
+        .run("query { added }", VersionResolver, None, HashMap::new())
+        .await
+        .is_ok());
+
+    let in_flight = executor.run("query { version }", VersionResolver, None, HashMap::new());
+    let reload = async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        executor.replace_schema(V2).unwrap();
+    };
+    let (response, _) = tokio::join!(in_flight, reload);
+    assert_eq!(
+        response.into_result().unwrap(),
+        expected(json!({ "version": 1 }))
+    );
+
+    let result = executor
+        .clone()
+        .run(
+            "query { version added }",
+            VersionResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "version": 1, "added": true })));
+
+    assert!(executor.replace_schema("type Query {").is_err());
+    assert!(executor
+        .run("query { added }", VersionResolver, None, HashMap::new())
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn auth_directive_guards_fields() {
+    const AUTH_SCHEMA: &str = r#"
+    schema { query: Query }
+
+    directive @auth(role: String) on FIELD_DEFINITION
+
+    type Query {
+      version: Int!
+      secret: String @auth(role: "admin")
+    }
+    "#;
+
+    struct AuthResolver;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for AuthResolver {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "version" => Ok(ConstValue::from(1).into()),
+                "secret" => Ok(Resolved::string("hunter2")),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(AUTH_SCHEMA)
+        .authorization("auth", |ctx: &Ctx, role: Option<&str>| {
+            ctx.data::<Viewer>().map(|viewer| viewer.0.as_str()) == role
+        })
+        .build()
+        .unwrap();
+
+    let response = executor
+        .run(
+            "query { version secret }",
+            AuthResolver,
+            None,
+            HashMap::new(),
+        )
+        .await;
+    assert_eq!(
+        response.data,
+        expected(json!({ "version": 1, "secret": null }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].message(),
+        "not authorized to access field: secret"
+    );
+    assert_eq!(
+        response.errors[0].extensions().get("code"),
+        Some(&ConstValue::from("FORBIDDEN"))
+    );
+
+    let mut data = Data::new();
+    data.insert(Viewer("admin".to_owned()));
+    let result = executor
+        .run_with_data("query { secret }", AuthResolver, None, HashMap::new(), data)
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "secret": "hunter2" })));
+}
+
+#[tokio::test]
+async fn directive_handlers_wrap_field_resolvers() {
+    struct Truncate;
+
+    #[async_trait::async_trait]
+    impl crate::DirectiveHandler for Truncate {
+        async fn apply(&self, ctx: &Ctx, next: &dyn ObjectResolver) -> Result<Resolved> {
+            let length = ctx
+                .directive_arg::<i64>("truncate", "length")?
+                .unwrap_or_default();
+            let resolved = next.resolve_field(ctx, ctx.field_name()).await?;
+
+            Ok(resolved.map_value(|value| match value {
+                ConstValue::String(s) => s.chars().take(length as usize).collect::<String>().into(),
+                other => other,
+            }))
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(format!(
+            "{}\ndirective @truncate(length: Int!) on FIELD",
+            SCHEMA
+        ))
+        .directive("truncate", Truncate)
+        .build()
+        .unwrap();
+
+    let result = executor
+        .run(
+            r#"
+            query Truncated($length: Int!) {
+              person { firstName @truncate(length: $length) lastName }
+              short: greet(name: "Zack") @truncate(length: 5)
+            }
+            "#,
+            QueryResolver,
+            None,
+            HashMap::from([("length".to_owned(), ConstValue::from(2))]),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({
+            "person": { "firstName": "Za", "lastName": "Angelo" },
+            "short": "Hello"
+        }))
+    );
+}
+
+#[tokio::test]
+async fn middleware_reads_type_directives_from_schema() {
+    use apollo_compiler::hir::TypeDefinition;
+
+    struct Internal;
+
+    #[async_trait::async_trait]
+    impl crate::ResolverMiddleware for Internal {
+        async fn before_field(&self, ctx: &Ctx) -> Result<()> {
+            let field_def = ctx
+                .schema()
+                .find_field_definition("Query", ctx.field_name());
+            let return_type = field_def
+                .and_then(|field_def| ctx.schema().find_type_definition(&field_def.ty().name()));
+
+            match return_type {
+                Some(TypeDefinition::ObjectTypeDefinition(ty))
+                    if ty.directives().any(|d| d.name() == "internal") =>
+                {
+                    Err(anyhow!("{} is internal", ty.name()))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(
+            r#"
+            directive @internal on OBJECT
+            type Query { limit: Int!, maybePerson: Person }
+            type Person @internal { firstName: String! }
+            "#,
+        )
+        .middleware(Internal)
+        .build()
+        .unwrap();
+
+    let response = executor
+        .run(
+            "query { limit maybePerson { firstName } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({ "limit": 10, "maybePerson": null }))
+    );
+    assert_eq!(response.errors[0].message(), "Person is internal");
+}
+
+#[tokio::test]
+async fn middleware_composes_in_registration_order() {
+    type Log = Arc<std::sync::Mutex<Vec<String>>>;
+
+    struct Recorder(&'static str, Log);
+
+    #[async_trait::async_trait]
+    impl crate::ResolverMiddleware for Recorder {
+        async fn resolve(
+            &self,
+            ctx: &Ctx,
+            info: &crate::FieldInfo<'_>,
+            next: crate::Next<'_>,
+        ) -> Result<Resolved> {
+            if info.name() == "firstName" {
+                self.1.lock().unwrap().push(format!("{} before", self.0));
+            }
+            let resolved = next.run(ctx).await;
+            if info.name() == "firstName" {
+                self.1.lock().unwrap().push(format!("{} after", self.0));
+            }
+            resolved
+        }
+    }
+
+    struct Intercept;
+
+    #[async_trait::async_trait]
+    impl crate::ResolverMiddleware for Intercept {
+        async fn resolve(
+            &self,
+            ctx: &Ctx,
+            info: &crate::FieldInfo<'_>,
+            next: crate::Next<'_>,
+        ) -> Result<Resolved> {
+            match info.name() {
+                "lastName" => Ok(Resolved::string("Intercepted")),
+                _ => next.run(ctx).await,
+            }
+        }
+    }
+
+    let log = Log::default();
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .middleware(Recorder("outer", log.clone()))
+        .middleware(Recorder("inner", log.clone()))
+        .middleware(Intercept)
+        .middleware(crate::Logger)
+        .build()
+        .unwrap();
+
+    let result = executor
+        .run(
+            "query { person { firstName lastName } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "person": { "firstName": "Zack", "lastName": "Intercepted" } }))
+    );
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["outer before", "inner before", "inner after", "outer after"]
+    );
+}
+
+#[tokio::test]
+async fn middleware_sees_operation_and_field_paths() {
+    type Log = Arc<std::sync::Mutex<Vec<String>>>;
+
+    struct PathRecorder(Log);
+
+    #[async_trait::async_trait]
+    impl crate::ResolverMiddleware for PathRecorder {
+        async fn resolve(
+            &self,
+            ctx: &Ctx,
+            info: &crate::FieldInfo<'_>,
+            next: crate::Next<'_>,
+        ) -> Result<Resolved> {
+            let path = info
+                .path()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            self.0.lock().unwrap().push(path.join("."));
+            next.run(ctx).await
+        }
+
+        fn operation_span(&self, operation: &crate::OperationInfo<'_>) -> Option<tracing::Span> {
+            self.0.lock().unwrap().push(format!(
+                "{} {}",
+                operation.operation_type(),
+                operation.name().unwrap_or_default()
+            ));
+            None
+        }
+    }
+
+    let log = Log::default();
+    let executor = Executor::builder()
+        .type_system(PET_SCHEMA)
+        .middleware(PathRecorder(log.clone()))
+        .build()
+        .unwrap();
+
+    executor
+        .run(
+            "query Pets { all: pets { ... on Dog { name } ... on Cat { name } } }",
+            PetQueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    let mut log = log.lock().unwrap().clone();
+    log.sort();
+    assert_eq!(log, ["all", "all.0.name", "all.1.name", "query Pets"]);
+}
+
+#[tokio::test]
+async fn streamed_list_items_arrive_incrementally() {
+    use futures::StreamExt;
+
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+
+    let payloads = executor
+        .run_incremental(
+            Request::new(
+                r#"query { pets @stream(initialCount: 1, label: "pets") { ... on Pet { name } } }"#,
+            ),
+            PetQueryResolver,
+        )
+        .map(|payload| serde_json::to_value(payload).unwrap())
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(
+        payloads,
+        [
+            json!({ "data": { "pets": [{ "name": "Rex" }] }, "hasNext": true }),
+            json!({
+                "incremental": [{ "items": [{ "name": "Tom" }], "path": ["pets", 1], "label": "pets" }],
+                "hasNext": false
+            }),
+        ]
+    );
+
+    let payloads = executor
+        .run_incremental(
+            Request::new("query { pets @stream(if: false) { ... on Pet { name } } }"),
+            PetQueryResolver,
+        )
+        .map(|payload| serde_json::to_value(payload).unwrap())
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(
+        payloads,
+        [json!({ "data": { "pets": [{ "name": "Rex" }, { "name": "Tom" }] }, "hasNext": false })]
+    );
+
+    //nb: without incremental delivery the whole list is returned inline
+    let result = run_pets("query { pets @stream(initialCount: 1) { ... on Pet { name } } }")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "pets": [{ "name": "Rex" }, { "name": "Tom" }] }))
+    );
+}
+
+#[tokio::test]
+async fn failed_streamed_items_are_null() {
+    use futures::StreamExt;
+
+    struct Pets;
+
+    impl SyncObjectResolver for Pets {
+        fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::from_json(json!([
+                { "__typename": "Cat", "name": "Tom" },
+                { "__typename": "Cat", "name": null }
+            ])))
+        }
+    }
+
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+
+    let payloads = executor
+        .run_incremental(
+            Request::new("query { allPets @stream(initialCount: 1) { name } }"),
+            Pets,
+        )
+        .map(|payload| serde_json::to_value(payload).unwrap())
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(
+        payloads,
+        [
+            json!({ "data": { "allPets": [{ "name": "Tom" }] }, "hasNext": true }),
+            json!({
+                "incremental": [{
+                    "items": null,
+                    "path": ["allPets", 1],
+                    "errors": [{
+                        "message": "cannot return null for non-null field Pet.name",
+                        "locations": [{ "line": 1, "column": 44 }],
+                        "path": ["allPets", 1, "name"]
+                    }]
+                }],
+                "hasNext": false
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn deferred_fragments_arrive_incrementally() {
+    use futures::StreamExt;
+
+    let executor = Executor::new(PET_SCHEMA).unwrap();
+
+    let run = |query: &'static str| {
+        executor
+            .run_incremental(Request::new(query), PetQueryResolver)
+            .map(|payload| serde_json::to_value(payload).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let payloads = run(r#"query { pet { name ... on Dog @defer(label: "dog") { barks } } }"#).await;
+
+    assert_eq!(
+        payloads,
+        [
+            json!({ "data": { "pet": { "name": "Rex" } }, "hasNext": true }),
+            json!({
+                "incremental": [{ "data": { "barks": true }, "path": ["pet"], "label": "dog" }],
+                "hasNext": false
+            }),
+        ]
+    );
+
+    let payloads =
+        run("query { pet { name } ...Pets @defer } fragment Pets on Query { allPets { name } }")
+            .await;
+
+    assert_eq!(
+        payloads,
+        [
+            json!({ "data": { "pet": { "name": "Rex" } }, "hasNext": true }),
+            json!({
+                "incremental": [{
+                    "data": { "allPets": [{ "name": "Rex" }, { "name": "Tom" }] },
+                    "path": []
+                }],
+                "hasNext": false
+            }),
+        ]
+    );
+
+    let payloads =
+        run("query { pet { name ... @defer(if: false) { ... on Dog { barks } } } }").await;
+
+    assert_eq!(
+        payloads,
+        [json!({ "data": { "pet": { "name": "Rex", "barks": true } }, "hasNext": false })]
+    );
+
+    //nb: without incremental delivery deferred fragments are returned inline
+    let result = run_pets("query { pet { name ... @defer { ... on Dog { barks } } } }")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "pet": { "name": "Rex", "barks": true } }))
+    );
+}
+
+#[tokio::test]
+async fn observers_see_field_timings_and_errors() {
+    type Log = Arc<std::sync::Mutex<Vec<String>>>;
+
+    struct Recorder(Log);
+
+    impl crate::ExecutionObserver for Recorder {
+        fn on_field_start(&self, field: &crate::FieldInfo<'_>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("start {}", field.name()));
+        }
+
+        fn on_field_end(&self, field: &crate::FieldInfo<'_>, _elapsed: Duration) {
+            self.0.lock().unwrap().push(format!("end {}", field.name()));
+        }
+
+        fn on_error(&self, field: &crate::FieldInfo<'_>, error: &GraphQLError) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("error {}: {}", field.name(), error.message()));
+        }
+    }
+
+    let log = Log::default();
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .observer(Recorder(log.clone()))
+        .build()
+        .unwrap();
+
+    executor
+        .run(
+            "query { person { nickname } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "start person",
+            "start nickname",
+            "error nickname: nickname unavailable",
+            "end nickname",
+            "end person"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn tracing_extension_has_resolver_timings() {
+    let executor = Executor::new(SCHEMA).unwrap();
+
+    let untraced = executor
+        .execute(
+            Request::new("query { person { firstName } }"),
+            QueryResolver,
+        )
+        .await;
+    assert!(untraced.extensions.is_empty());
+
+    let response = executor
+        .execute(
+            Request::new("query { person { firstName } }").tracing(true),
+            QueryResolver,
+        )
+        .await;
+
+    let tracing = response.extensions["tracing"].to_json();
+    assert_eq!(tracing["version"], json!(1));
+
+    let resolvers = tracing["execution"]["resolvers"].as_array().unwrap();
+    let first_name = resolvers
+        .iter()
+        .find(|r| r["path"] == json!(["person", "firstName"]))
+        .unwrap();
+
+    assert_eq!(first_name["parentType"], json!("Person"));
+    assert_eq!(first_name["fieldName"], json!("firstName"));
+    assert_eq!(first_name["returnType"], json!("String!"));
+    assert!(first_name["duration"].is_u64());
+}
+
+#[test]
+fn sdl_prints_the_schema_in_a_stable_order() {
+    let executor = Executor::new(
+        r#"
+        """
+        Marks fields only some users can see
+        """
+        directive @restricted(role: String = "admin") on FIELD_DEFINITION
+
+        type Query {
+          "Looks up a pet"
+          pet(id: ID!, kind: Kind = DOG): Pet
+          legacyPet: Pet @deprecated(reason: "use `pet`")
+        }
+
+        union Pet = Dog | Cat
+
+        enum Kind { DOG CAT }
+
+        input Filter { kind: Kind, limit: Int = 10 }
+
+        scalar Url @specifiedBy(url: "https://url.spec.whatwg.org")
+
+        interface Named { name: String! }
+
+        type Dog implements Named { name: String! @restricted }
+        type Cat implements Named { name: String! }
+        "#,
+    )
+    .unwrap();
+
+    let expected = r#"schema {
+  query: Query
+}
+
+"""
+Marks fields only some users can see
+"""
+directive @restricted(role: String = "admin") on FIELD_DEFINITION
+
+type Cat implements Named {
+  name: String!
+}
+
+type Dog implements Named {
+  name: String! @restricted
+}
+
+input Filter {
+  kind: Kind
+  limit: Int = 10
+}
+
+enum Kind {
+  DOG
+  CAT
+}
+
+interface Named {
+  name: String!
+}
+
+union Pet = Dog | Cat
+
+type Query {
+  """
+  Looks up a pet
+  """
+  pet(id: ID!, kind: Kind = DOG): Pet
+  legacyPet: Pet @deprecated(reason: "use `pet`")
+}
+
+scalar Url @specifiedBy(url: "https://url.spec.whatwg.org")
+"#;
+
+    assert_eq!(executor.sdl(), expected);
+    assert_eq!(executor.sdl(), Executor::new(expected).unwrap().sdl());
+}
+
+#[tokio::test]
+async fn subscription_executes_each_event() {
+    use futures::StreamExt;
+
+    struct Counter;
+
+    #[async_trait::async_trait]
+    impl crate::SubscriptionResolver for Counter {
+        async fn subscribe(&self, ctx: &Ctx, name: &str) -> Result<crate::EventStream> {
+            match name {
+                "count" => {
+                    let to = ctx.try_arg::<i32>("to")?;
+                    Ok(futures::stream::iter(1..=to)
+                        .map(|n| match n {
+                            2 => Err(anyhow!("skipped a beat")),
+                            n => Ok(Resolved::Value(ConstValue::from(n))),
+                        })
+                        .boxed())
+                }
+                other => Err(anyhow!("unknown subscription field {}", other)),
+            }
+        }
+    }
+
+    let executor = Executor::new(
+        r#"
+        type Query { ok: Boolean }
+        type Subscription { count(to: Int!): Int }
+        "#,
+    )
+    .unwrap();
+
+    let responses = executor
+        .subscribe(Request::new("subscription { n: count(to: 3) }"), Counter)
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0].data, expected(json!({ "n": 1 })));
+    assert_eq!(responses[1].data, expected(json!({ "n": null })));
+    assert_eq!(responses[1].errors[0].message(), "skipped a beat");
+    assert_eq!(error_path(&responses[1].errors[0]), ["n"]);
+    assert_eq!(responses[2].data, expected(json!({ "n": 3 })));
+
+    let Err(err) = executor
+        .subscribe(Request::new("query { ok }"), Counter)
+        .await
+    else {
+        panic!("expected queries to be rejected");
+    };
+    assert_eq!(err.message(), "operation is not a subscription");
+}
+
+#[tokio::test]
+async fn full_introspection_query() {
+    let executor = Executor::new(SCHEMA).unwrap();
+    let schema = executor.introspect().await.unwrap().to_json();
+    let schema = &schema["__schema"];
+
+    assert_eq!(schema["queryType"], json!({ "name": "Query" }));
+    assert_eq!(schema["mutationType"], json!(null));
+
+    let ty = |name: &str| {
+        schema["types"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|ty| ty["name"] == name)
+            .unwrap_or_else(|| panic!("type {} not introspected", name))
+            .clone()
+    };
+
+    let person = ty("Person");
+    assert_eq!(person["kind"], json!("OBJECT"));
+    let first_name = person["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|field| field["name"] == "firstName")
+        .unwrap();
+    assert_eq!(
+        first_name["type"]["ofType"],
+        json!({ "kind": "SCALAR", "name": "String", "ofType": null })
+    );
+
+    let sort = ty("Sort");
+    assert_eq!(sort["kind"], json!("ENUM"));
+    assert_eq!(sort["enumValues"][1]["name"], json!("DESC"));
+
+    assert_eq!(ty("__Schema")["kind"], json!("OBJECT"));
+    assert!(schema["directives"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|directive| directive["name"] == "include"));
+}
+
+#[tokio::test]
+async fn schema_from_multiple_sdl_files() {
+    let dir = std::env::temp_dir().join(format!("phoebus-sdl-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let query = dir.join("query.graphql");
+    let person = dir.join("person.graphql");
+    std::fs::write(&query, "type Query { person: Person! }").unwrap();
+    std::fs::write(&person, "type Person { firstName: String! }").unwrap();
+
+    let executor = Executor::from_sdl_files(&[&query, &person]).unwrap();
+    let missing = Executor::from_sdl_files(&[&query, &dir.join("missing.graphql")]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let result = executor
+        .run(
+            "query { person { firstName } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(
+        result,
+        expected(json!({ "person": { "firstName": "Zack" } }))
+    );
+
+    let Err(err) = missing else {
+        panic!("expected a missing schema file to fail");
+    };
+    assert!(err.to_string().starts_with("failed to read schema file"));
+}
+
+#[tokio::test]
+async fn scalar_codecs_parse_inputs_and_serialize_outputs() {
+    struct Events;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Events {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "epoch" => Ok(ConstValue::from(0).into()),
+                "echo" => Ok(Resolved::string(ctx.try_arg::<String>("at")?)),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(
+            r#"
+            scalar DateTime
+            type Query { epoch: DateTime, echo(at: DateTime!): DateTime }
+            "#,
+        )
+        .scalar("DateTime", crate::DateTimeCodec)
+        .build()
+        .unwrap();
+
+    let query = "query Echo($at: DateTime!) { epoch echo(at: $at) }";
+
+    let response = executor
+        .execute(
+            Request::new(query).variables(HashMap::from([(
+                "at".to_owned(),
+                ConstValue::from("2017-07-28T14:20:32Z"),
+            )])),
+            Events,
+        )
+        .await;
+    assert_eq!(
+        response.into_result().unwrap(),
+        expected(json!({
+            "epoch": "1970-01-01T00:00:00.000Z",
+            "echo": "2017-07-28T14:20:32Z"
+        }))
+    );
+
+    let response = executor
+        .execute(
+            Request::new(query)
+                .variables(HashMap::from([("at".to_owned(), ConstValue::from("soon"))])),
+            Events,
+        )
+        .await;
+    assert_eq!(
+        response.data,
+        expected(json!({ "epoch": "1970-01-01T00:00:00.000Z", "echo": null }))
+    );
+    assert_eq!(response.errors[0].message(), "invalid DateTime: \"soon\"");
+}
+
+#[tokio::test]
+async fn input_objects_deserialize_into_structs() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    enum Species {
+        Dog,
+        Cat,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct PetInput {
+        name: String,
+        species: Species,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct CreatePersonInput {
+        name: String,
+        age: Option<i32>,
+        pets: Vec<PetInput>,
+    }
+
+    struct Mutations;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Mutations {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "createPerson" => {
+                    let input = ctx.deserialize_arg::<CreatePersonInput>("input")?;
+                    assert_eq!(
+                        input,
+                        CreatePersonInput {
+                            name: "Zack".to_owned(),
+                            age: None,
+                            pets: vec![
+                                PetInput {
+                                    name: "Rex".to_owned(),
+                                    species: Species::Dog,
+                                },
+                                PetInput {
+                                    name: "Tom".to_owned(),
+                                    species: Species::Cat,
+                                },
+                            ],
+                        }
+                    );
+                    Ok(Resolved::string(input.name))
+                }
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(
+        r#"
+        enum Species { DOG CAT }
+        input PetInput { name: String!, species: Species!, nickname: String }
+        input CreatePersonInput { name: String!, age: Int, pets: [PetInput!]! }
+        type Query { createPerson(input: CreatePersonInput!): String }
+        "#,
+    )
+    .unwrap();
+
+    let result = executor
+        .run(
+            r#"
+            query Create($petName: String!, $species: Species!) {
+              createPerson(input: {
+                name: "Zack",
+                pets: [{ name: $petName, species: DOG }, { name: "Tom", species: $species }]
+              })
+            }
+            "#,
+            Mutations,
+            None,
+            HashMap::from([
+                ("petName".to_owned(), ConstValue::from("Rex")),
+                ("species".to_owned(), ConstValue::from("CAT")),
+            ]),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "createPerson": "Zack" })));
+
+    let response = executor
+        .run(
+            r#"
+            query {
+              createPerson(input: {
+                name: "Zack",
+                pets: [{ name: "Rex", species: DOG, nickname: "Rexy" }]
+              })
+            }
+            "#,
+            Mutations,
+            None,
+            HashMap::new(),
+        )
+        .await;
+    assert_eq!(response.data, expected(json!({ "createPerson": null })));
+    assert!(response.errors[0]
+        .message()
+        .ends_with("at input.pets.0.nickname"));
+}
+
+#[tokio::test]
+async fn one_of_input_objects_take_exactly_one_field() {
+    struct Lookup;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Lookup {
+        async fn resolve_field(&self, ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::string(
+                ctx.try_arg::<ConstValue>("by")?.to_string(),
+            ))
+        }
+    }
+
+    let executor = Executor::new(
+        r#"
+        directive @oneOf on INPUT_OBJECT
+        input PersonBy @oneOf { id: Int, email: String }
+        type Query { person(by: PersonBy!): String }
+        "#,
+    )
+    .unwrap();
+
+    let lookup = |query: &'static str| {
+        let executor = executor.clone();
+        async move { executor.run(query, Lookup, None, HashMap::new()).await }
+    };
+
+    let response = lookup(r#"query { person(by: { email: "zack@example.com" }) }"#).await;
+    assert_eq!(
+        response.into_result().unwrap(),
+        expected(json!({ "person": r#"{"email":"zack@example.com"}"# }))
+    );
+
+    let response = lookup("query { person(by: {}) }").await;
+    assert_eq!(response.data, expected(json!({ "person": null })));
+    assert_eq!(
+        response.errors[0].message(),
+        "oneOf input object PersonBy must be given exactly one field, got 0"
+    );
+
+    let response = lookup(r#"query { person(by: { id: 1, email: "zack@example.com" }) }"#).await;
+    assert_eq!(
+        response.errors[0].message(),
+        "oneOf input object PersonBy must be given exactly one field, got 2"
+    );
+
+    let response = lookup("query { person(by: { id: null }) }").await;
+    assert_eq!(
+        response.errors[0].message(),
+        "field id of oneOf input object PersonBy must not be null"
+    );
+}
+
+#[tokio::test]
+async fn numbers_are_coerced_to_their_scalar_type() {
+    struct Numbers;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Numbers {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "wholeFloat" => Ok(ConstValue::from(1).into()),
+                "float" => Resolved::float(2.5),
+                "nan" => Resolved::float(f64::NAN),
+                "integralInt" => Ok(ConstValue::from(4.0).into()),
+                "fractionalInt" => Ok(ConstValue::from(4.5).into()),
+                "hugeInt" => Ok(ConstValue::from(i64::MAX).into()),
+                "half" => Ok(ConstValue::from(ctx.try_arg::<f64>("value")? / 2.0).into()),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(
+        r#"
+        type Query {
+          wholeFloat: Float
+          float: Float
+          nan: Float
+          integralInt: Int
+          fractionalInt: Int
+          hugeInt: Int
+          half(value: Float!): Float
+        }
+        "#,
+    )
+    .unwrap();
+
+    let response = executor
+        .run(
+            "query { wholeFloat float nan integralInt fractionalInt hugeInt half(value: 3) }",
+            Numbers,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        serde_json::to_string(&response.data).unwrap(),
+        r#"{"wholeFloat":1.0,"float":2.5,"nan":null,"integralInt":4,"fractionalInt":null,"hugeInt":null,"half":1.5}"#
+    );
+
+    let mut messages = response
+        .errors
+        .iter()
+        .map(|err| err.message().to_owned())
+        .collect::<Vec<_>>();
+    messages.sort();
+    assert_eq!(
+        messages,
+        [
+            "Float cannot represent value: NaN",
+            "Int cannot represent value: 4.5",
+            "Int cannot represent value: 9223372036854775807",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unknown_fields_resolve_to_null() {
+    struct Lagging;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Lagging {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "viewer" => Ok(Resolved::string("zack")),
+                "slow" => Err(crate::UnknownField(name.to_owned()).into()),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let response = executor
+        .run(
+            "query { viewer slow maybePerson { firstName } }",
+            Arc::new(Lagging),
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({ "viewer": "zack", "slow": null, "maybePerson": null }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message(), "invalid field: maybePerson");
+}
+
+#[tokio::test]
+async fn sync_resolvers_compose_with_objects() {
+    struct SyncQuery;
+    struct SyncPerson;
+
+    impl SyncObjectResolver for SyncQuery {
+        fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "person" => Ok(Resolved::object(SyncPerson)),
+                "limit" => Ok(ConstValue::from(ctx.arg_with_default("limit", 10)).into()),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    impl SyncObjectResolver for SyncPerson {
+        fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "firstName" => Ok(Resolved::string("Zack")),
+                "age" => Ok(ConstValue::from(39).into()),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let result = executor
+        .run(
+            "query { person { firstName age } limit(limit: 3) }",
+            SyncQuery,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "person": { "firstName": "Zack", "age": 39 }, "limit": 3 }))
+    );
+}
+
+#[tokio::test]
+async fn lazy_values_are_built_when_completed() {
+    struct LazyQuery;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for LazyQuery {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "person" => Ok(Resolved::lazy(|| Resolved::object(PersonResolver))),
+                "greet" => Ok(Resolved::lazy(|| {
+                    Resolved::lazy(|| Resolved::string("Hello, lazy"))
+                })),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let result = executor
+        .run(
+            "query { person { firstName } greet }",
+            LazyQuery,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "person": { "firstName": "Zack" }, "greet": "Hello, lazy" }))
+    );
+}
+
+#[tokio::test]
+async fn ids_are_strings_on_the_wire() {
+    struct Ids;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Ids {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "numeric" => Ok(ConstValue::from(42).into()),
+                "echo" => Ok(ConstValue::from(ctx.try_arg::<Id>("id")?).into()),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new("type Query { numeric: ID! echo(id: ID!): ID! }").unwrap();
+    let result = executor
+        .run(
+            r#"query { numeric literal: echo(id: 7) string: echo(id: "x7") }"#,
+            Ids,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "numeric": "42", "literal": "7", "string": "x7" }))
+    );
+}
+
+#[tokio::test]
+async fn introspection_results_are_cached() {
+    struct Counter(Arc<std::sync::atomic::AtomicUsize>);
+
+    impl crate::ExecutionObserver for Counter {
+        fn on_field_start(&self, _field: &crate::FieldInfo<'_>) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    let started = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .observer(Counter(started.clone()))
+        .build()
+        .unwrap();
+
+    let query = "query { __schema { queryType { name } } }";
+    let first = executor
+        .run(query, QueryResolver, None, HashMap::new())
+        .await
+        .into_result()
+        .unwrap();
+    let executed = started.load(std::sync::atomic::Ordering::SeqCst);
+    assert!(executed > 0);
+
+    let second = executor
+        .run(query, QueryResolver, None, HashMap::new())
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(started.load(std::sync::atomic::Ordering::SeqCst), executed);
+
+    //nb: anything besides introspection is always executed
+    executor
+        .run(
+            "query { __typename viewer }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await;
+    executor
+        .run(
+            "query { __typename viewer }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await;
+    assert_eq!(
+        started.load(std::sync::atomic::Ordering::SeqCst),
+        executed + 4
+    );
+
+    //nb: the cache belongs to the schema, not the cached document
+    let started = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .query_cache(0)
+        .observer(Counter(started.clone()))
+        .build()
+        .unwrap();
+
+    for _ in 0..2 {
+        executor
+            .run(query, QueryResolver, None, HashMap::new())
+            .await
+            .into_result()
+            .unwrap();
+    }
+    assert_eq!(started.load(std::sync::atomic::Ordering::SeqCst), executed);
+}
+
+#[tokio::test]
+async fn introspection_is_not_cached_past_middleware() {
+    struct SignedIn;
+
+    #[async_trait::async_trait]
+    impl crate::ResolverMiddleware for SignedIn {
+        async fn before_field(&self, ctx: &Ctx) -> Result<()> {
+            match ctx.data::<&str>() {
+                Some(_) => Ok(()),
+                None => Err(anyhow!("sign in to introspect")),
+            }
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .middleware(SignedIn)
+        .build()
+        .unwrap();
+
+    let query = "query { __schema { queryType { name } } }";
+    let mut data = Data::new();
+    data.insert("ada");
+
+    let signed_in = executor
+        .run_with_data(query, QueryResolver, None, HashMap::new(), data)
+        .await;
+    assert!(signed_in.is_ok());
+
+    let anonymous = executor
+        .run(query, QueryResolver, None, HashMap::new())
+        .await;
+    assert_eq!(anonymous.errors[0].message(), "sign in to introspect");
+}
+
+#[tokio::test]
+async fn scalar_validators_check_inputs_and_results() {
+    struct Emails;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Emails {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "echo" => Ok(Resolved::Value(ctx.try_arg("email")?)),
+                "broken" => Ok(Resolved::string("nobody")),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(
+            r#"
+            scalar Email
+            type Query { echo(email: Email): Email broken: Email }
+            "#,
+        )
+        .validate_scalar("Email", |v: &ConstValue| match v {
+            ConstValue::String(s) if s.contains('@') => Ok(()),
+            _ => Err(anyhow!("expected an email address")),
+        })
+        .build()
+        .unwrap();
+
+    let response = executor
+        .run(
+            r#"query { ok: echo(email: "zack@example.com") bad: echo(email: "zack") broken }"#,
+            Emails,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({ "ok": "zack@example.com", "bad": null, "broken": null }))
+    );
+    let mut messages = response
+        .errors
+        .iter()
+        .map(|err| err.message().to_owned())
+        .collect::<Vec<_>>();
+    messages.sort();
+    assert_eq!(
+        messages,
+        [
+            "invalid Email: expected an email address",
+            "invalid Email: expected an email address",
+        ]
+    );
+
+    let err = executor
+        .run(
+            "query Echo($email: Email) { echo(email: $email) }",
+            Emails,
+            None,
+            HashMap::from([("email".to_owned(), ConstValue::from("zack"))]),
+        )
+        .await
+        .into_result()
+        .unwrap_err();
+    assert_eq!(
+        err.message(),
+        "variable $email: invalid Email: expected an email address"
+    );
+}
+
+#[tokio::test]
+async fn meta_fields_are_available_on_every_root() {
+    struct Mutations;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Mutations {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            Err(anyhow!("invalid field: {}", name))
+        }
+    }
+
+    let executor = Executor::new(
+        r#"
+        schema { query: Query mutation: Mutation }
+        type Query { ok: Boolean }
+        type Mutation { touch: Boolean }
+        "#,
+    )
+    .unwrap();
+
+    let result = executor
+        .run("mutation { __typename }", Mutations, None, HashMap::new())
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "__typename": "Mutation" })));
+
+    let result = executor
+        .run(
+            r#"query { __type(name: "Mutation") { name } missing: __type(name: "Nope") { name } }"#,
+            Mutations,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(
+        result,
+        expected(json!({ "__type": { "name": "Mutation" }, "missing": null }))
+    );
+}
+
+#[tokio::test]
+async fn omitted_variables_take_their_default() {
+    let query = "query Limited($limit: Int = 3) { limit(limit: $limit) }";
+    let executor = Executor::new(SCHEMA).unwrap();
+
+    let result = executor
+        .run(query, QueryResolver, None, HashMap::new())
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "limit": 3 })));
+
+    let result = executor
+        .run(
+            query,
+            QueryResolver,
+            None,
+            HashMap::from([("limit".to_owned(), ConstValue::from(5))]),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "limit": 5 })));
+}
+
+#[tokio::test]
+async fn request_variables_accept_json_values() {
+    let executor = Executor::new(SCHEMA).unwrap();
+    let variables: HashMap<String, serde_json::Value> =
+        serde_json::from_str(r#"{ "limit": 4, "name": "Zack" }"#).unwrap();
+
+    let result = executor
+        .execute(
+            Request::new(
+                "query ($limit: Int, $name: String) { limit(limit: $limit) greet(name: $name) }",
+            )
+            .variables(variables),
+            QueryResolver,
+        )
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(
+        result,
+        expected(json!({ "limit": 4, "greet": "Hello, Zack" }))
+    );
+}
+
+#[tokio::test]
+async fn batches_run_in_order_and_fail_independently() {
+    let batch: crate::BatchRequest = serde_json::from_value(json!([
+        { "query": "query { greet(name: \"Zack\") }" },
+        { "query": "query { nope }" },
+        { "query": "query ($limit: Int) { limit(limit: $limit) }", "variables": { "limit": 2 } },
+    ]))
+    .unwrap();
+    let crate::BatchRequest::Batch(requests) = batch else {
+        panic!("expected a batch");
+    };
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .batch_concurrency(2)
+        .build()
+        .unwrap();
+    let responses = executor.run_batch(requests, || QueryResolver).await;
+
+    assert_eq!(responses.len(), 3);
+    assert_eq!(
+        responses[0].data,
+        expected(json!({ "greet": "Hello, Zack" }))
+    );
+    assert!(!responses[1].errors.is_empty());
+    assert_eq!(responses[2].data, expected(json!({ "limit": 2 })));
+
+    let single: crate::BatchRequest =
+        serde_json::from_value(json!({ "query": "{ limit }", "operationName": null })).unwrap();
+    assert!(matches!(single, crate::BatchRequest::Single(_)));
+}
+
+#[tokio::test]
+async fn errors_serialize_in_the_spec_format() {
+    let response = execute("query { person { nickname } }").await;
+
+    assert_eq!(
+        serde_json::to_string(&response).unwrap(),
+        r#"{"errors":[{"message":"nickname unavailable","locations":[{"line":1,"column":18}],"path":["person","nickname"],"extensions":{"code":"UNAVAILABLE"}}],"data":{"person":{"nickname":null}}}"#
+    );
+}
+
+#[tokio::test]
+async fn resolver_errors_keep_their_extensions() {
+    struct Missing;
+
+    impl SyncObjectResolver for Missing {
+        fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "maybePerson" => Err(crate::Error::new("person not found")
+                    .code("NOT_FOUND")
+                    .extension("id", 42)
+                    .into()),
+                "viewer" => Err(anyhow::Error::from(
+                    crate::Error::new("not signed in").code("UNAUTHENTICATED"),
+                )
+                .context("loading viewer")),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+    let response = executor
+        .run(
+            "query { maybePerson { firstName } viewer }",
+            Missing,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        serde_json::to_value(&response.errors).unwrap(),
+        json!([
+            {
+                "message": "person not found",
+                "locations": [{ "line": 1, "column": 9 }],
+                "path": ["maybePerson"],
+                "extensions": { "code": "NOT_FOUND", "id": 42 }
+            },
+            {
+                "message": "not signed in",
+                "locations": [{ "line": 1, "column": 35 }],
+                "path": ["viewer"],
+                "extensions": { "code": "UNAUTHENTICATED" }
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn error_hooks_see_and_mask_resolver_errors() {
+    struct Failing;
+
+    impl SyncObjectResolver for Failing {
+        fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "viewer" => Err(anyhow!("connection to db-1.internal refused")),
+                "slow" => Err(GraphQLError::new("too slow").code("UNAVAILABLE").into()),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let log: Arc<std::sync::Mutex<Vec<String>>> = Default::default();
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .map_error({
+            let log = log.clone();
+            move |err, ctx| {
+                log.lock().unwrap().push(format!(
+                    "{} {:?} {}",
+                    ctx.field_name.unwrap_or("-"),
+                    ctx.path.map(|path| path.len()),
+                    ctx.source.map_or("-".to_owned(), ToString::to_string)
+                ));
+                err
+            }
+        })
+        .mask_internal_errors()
+        .build()
+        .unwrap();
+
+    let response = executor
+        .execute(Request::new("query { viewer slow }"), Failing)
+        .await;
+
+    assert_eq!(
+        serde_json::to_value(&response.errors).unwrap(),
+        json!([
+            {
+                "message": "internal server error",
+                "locations": [{ "line": 1, "column": 9 }],
+                "path": ["viewer"],
+                "extensions": { "code": "INTERNAL_SERVER_ERROR" }
+            },
+            {
+                "message": "too slow",
+                "locations": [{ "line": 1, "column": 16 }],
+                "path": ["slow"],
+                "extensions": { "code": "UNAVAILABLE" }
+            }
+        ])
+    );
+
+    let mut seen = log.lock().unwrap().clone();
+    seen.sort();
+    assert_eq!(
+        seen,
+        [
+            "slow Some(1) too slow",
+            "viewer Some(1) connection to db-1.internal refused"
+        ]
+    );
+
+    //nb: request errors go through the hook too, but aren't masked
+    let response = executor
+        .execute(Request::new("query { nope }"), Failing)
+        .await;
+
+    assert_ne!(response.errors[0].message(), "internal server error");
+    assert_eq!(log.lock().unwrap().last().unwrap(), "- None -");
+}
+
+#[tokio::test]
+async fn error_hooks_see_streamed_item_errors() {
+    use futures::StreamExt;
+
+    struct Pets;
+
+    impl SyncObjectResolver for Pets {
+        fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::array([
+                Resolved::object(DogResolver),
+                Resolved::object(BrokenCat),
+                Resolved::null(),
+            ]))
+        }
+    }
+
+    struct BrokenCat;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for BrokenCat {
+        async fn resolve_type_name(&self) -> Result<Option<&str>> {
+            Ok(Some("Cat"))
+        }
+
+        async fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Err(anyhow!("connection to db-1.internal refused"))
+        }
+    }
+
+    let paths: Arc<std::sync::Mutex<Vec<String>>> = Default::default();
+    let executor = Executor::builder()
+        .type_system(PET_SCHEMA)
+        .mask_internal_errors()
+        .map_error({
+            let paths = paths.clone();
+            move |err, ctx| {
+                let path = ctx.path.unwrap_or_default().iter().map(ToString::to_string);
+                paths
+                    .lock()
+                    .unwrap()
+                    .push(path.collect::<Vec<_>>().join("."));
+                err
+            }
+        })
+        .build()
+        .unwrap();
+
+    let errors = executor
+        .run_incremental(
+            Request::new("query { allPets @stream(initialCount: 1) { name } }"),
+            Pets,
+        )
+        .flat_map(|payload| futures::stream::iter(payload.incremental))
+        .flat_map(|result| futures::stream::iter(result.errors))
+        .collect::<Vec<_>>()
+        .await;
+
+    let masked = errors
+        .iter()
+        .find(|err| err.path().map_or(false, |path| path.len() == 3))
+        .unwrap();
+    assert_eq!(masked.message(), "internal server error");
+    assert_eq!(
+        masked.locations(),
+        [Location {
+            line: 1,
+            column: 44
+        }]
+    );
+
+    //nb: a null item fails completing the item itself rather than a field
+    //beneath it
+    let null_item = errors
+        .iter()
+        .find(|err| err.path().map_or(false, |path| path.len() == 2))
+        .unwrap();
+    assert!(!null_item.locations().is_empty());
+
+    let mut paths = paths.lock().unwrap().clone();
+    paths.sort();
+    assert_eq!(paths, ["allPets.1.name", "allPets.2"]);
+}
+
+#[tokio::test]
+async fn hidden_members_are_left_out_of_introspection() {
+    const SCHEMA: &str = r#"
+        directive @internal on OBJECT | FIELD_DEFINITION | ENUM_VALUE
+
+        type Query {
+          person: String
+          secret: String @internal
+          audit: AuditLog
+          sorted(sort: Sort): String
+        }
+
+        type AuditLog @internal {
+          entries: Int
+        }
+
+        enum Sort {
+          ASC
+          DESC
+          SHUFFLE @internal
+        }
+    "#;
+
+    struct Secrets;
+
+    impl SyncObjectResolver for Secrets {
+        fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "secret" => Ok(Resolved::string("shh")),
+                _ => Ok(Resolved::null()),
+            }
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .hidden_directive("internal")
+        .build()
+        .unwrap();
+
+    let run = |query: &'static str| {
+        let executor = &executor;
+        async move {
+            executor
+                .run(query, Secrets, None, HashMap::new())
+                .await
+                .into_result()
+                .unwrap()
+        }
+    };
+
+    let introspection = run(r#"{
+        query: __type(name: "Query") { fields { name } }
+        audit: __type(name: "AuditLog") { name }
+        sort: __type(name: "Sort") { enumValues { name } }
+    }"#)
+    .await;
+
+    assert_eq!(
+        introspection,
+        expected(json!({
+            "query": { "fields": [{ "name": "person" }, { "name": "sorted" }] },
+            "audit": null,
+            "sort": { "enumValues": [{ "name": "ASC" }, { "name": "DESC" }] }
+        }))
+    );
+
+    let types = run("{ __schema { types { name } } }").await.to_json();
+    assert!(!types.to_string().contains("AuditLog"));
+
+    //nb: hidden fields still resolve for callers that know about them
+    assert_eq!(
+        run("{ secret }").await,
+        expected(json!({ "secret": "shh" }))
+    );
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .hidden_directive("internal")
+        .reject_hidden_fields()
+        .build()
+        .unwrap();
+
+    let response = executor
+        .run("{ secret }", Secrets, None, HashMap::new())
+        .await;
+
+    assert_eq!(
+        response.errors[0].message(),
+        "cannot query field secret on type Query"
+    );
+
+    let err = Executor::builder()
+        .type_system(SCHEMA)
+        .hidden_directive("private")
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "hidden directive private is not declared in the schema"
+    );
+}
+
+#[tokio::test]
+async fn resolver_concurrency_is_limited() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const SCHEMA: &str = r#"
+        type Query {
+          items: [Item!]!
+        }
+
+        type Item {
+          value: Int!
+        }
+    "#;
+
+    #[derive(Default)]
+    struct InFlight {
+        current: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    struct Items(Arc<InFlight>);
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Items {
+        async fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            Ok(Resolved::array(
+                (0..64).map(|ix| Resolved::object(Item(ix, self.0.clone()))),
+            ))
+        }
+    }
+
+    struct Item(i32, Arc<InFlight>);
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Item {
+        async fn resolve_field(&self, _ctx: &Ctx, _name: &str) -> Result<Resolved> {
+            let current = self.1.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.1.max.fetch_max(current, Ordering::SeqCst);
+            //nb: long enough that every permit is taken before one is freed
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.1.current.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(ConstValue::from(self.0).into())
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .resolver_concurrency(8)
+        .build()
+        .unwrap();
+
+    let in_flight = Arc::new(InFlight::default());
+    let data = executor
+        .run(
+            "{ items { value } }",
+            Items(in_flight.clone()),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap()
+        .to_json();
+
+    assert_eq!(data["items"].as_array().unwrap().len(), 64);
+    assert_eq!(data["items"][63], json!({ "value": 63 }));
+    assert_eq!(in_flight.max.load(Ordering::SeqCst), 8);
+}
+
+#[tokio::test]
+async fn upload_variables_reach_resolvers() {
+    use crate::Upload;
+
+    struct UploadResolver;
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for UploadResolver {
+        async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "content" => {
+                    let upload = ctx.try_arg::<Upload>("file")?;
+                    let content = upload.bytes().await?;
+                    Ok(Resolved::string(String::from_utf8(content.to_vec())?))
+                }
+                _ => Err(anyhow!("no such field")),
+            }
+        }
+    }
+
+    let executor =
+        Executor::new("scalar Upload type Query { content(file: Upload!): String }").unwrap();
+    let query = "query ($file: Upload!) { content(file: $file) }";
+
+    let chunks = futures::stream::iter([
+        Ok::<_, std::io::Error>(bytes::Bytes::from_static(b"hello ")),
+        Ok(bytes::Bytes::from_static(b"world")),
+    ]);
+    let upload = Upload::from_stream("a.txt", None, chunks, 1024)
+        .await
+        .unwrap();
+
+    let result = executor
+        .execute(
+            Request::new(query).variables([("file".to_owned(), upload)]),
+            UploadResolver,
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "content": "hello world" })));
+
+    //nb: a client can't pass off JSON as a file
+    let result = executor
+        .execute(
+            Request::new(query).variables([(
+                "file".to_owned(),
+                json!({ "filename": "a.txt", "content": [104, 105] }),
+            )]),
+            UploadResolver,
+        )
+        .await;
+    assert_eq!(result.data, expected(json!({ "content": null })));
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(
+        result.errors[0].message(),
+        "argument conversion error: invalid argument type, expected upload"
+    );
+}
+
+#[tokio::test]
+async fn custom_scalar_literals_are_not_interned() {
+    struct Echo;
+
+    impl SyncObjectResolver for Echo {
+        fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "echo" => Ok(Resolved::string(
+                    ctx.try_arg::<ConstValue>("value")?.to_string(),
+                )),
+                "paint" => Ok(Resolved::string(
+                    ctx.try_arg::<ConstValue>("filter")?.to_string(),
+                )),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(
+        r#"
+        scalar JSON
+        enum Color { RED GREEN }
+        input Filter { paintColor: Color }
+        type Query { echo(value: JSON): String, paint(filter: Filter): String }
+        "#,
+    )
+    .unwrap();
+
+    let result = executor
+        .run(
+            "{ echo(value: { clientKeyC41d: [CLIENT_VALUE_C41D] }) paint(filter: { paintColor: RED }) }",
+            Echo,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(
+        result,
+        expected(json!({
+            "echo": r#"{"clientKeyC41d":["CLIENT_VALUE_C41D"]}"#,
+            "paint": r#"{"paintColor":"RED"}"#
+        }))
+    );
+
+    //nb: a custom scalar takes any literal, so its keys and enum values are
+    //whatever the client wrote
+    assert!(!crate::value::is_interned("clientKeyC41d"));
+    assert!(!crate::value::is_interned("CLIENT_VALUE_C41D"));
+
+    //nb: input object fields and enum values are interned once they're
+    //checked against their type
+    assert!(crate::value::is_interned("paintColor"));
+    assert!(crate::value::is_interned("RED"));
+}
+
+#[tokio::test]
+async fn operations_over_the_depth_limit_are_rejected() {
+    struct Counter(Arc<std::sync::atomic::AtomicUsize>);
+
+    impl crate::ExecutionObserver for Counter {
+        fn on_field_start(&self, _field: &crate::FieldInfo<'_>) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    let started = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .max_depth(1)
+        .observer(Counter(started.clone()))
+        .build()
+        .unwrap();
+
+    //nb: fragments don't add a level, the fields inside them do
+    for query in [
+        "{ person { firstName } }",
+        "{ ...Root } fragment Root on Query { person { ...Name } } fragment Name on Person { firstName }",
+        "{ ... on Query { person { ... on Person { firstName } } } }",
+    ] {
+        let err = executor
+            .run(query, QueryResolver, None, HashMap::new())
+            .await
+            .into_result()
+            .unwrap_err();
+        assert_eq!(
+            err.message(),
+            "query depth 2 exceeds maximum allowed depth of 1"
+        );
+    }
+    //nb: rejected before any resolver ran
+    assert_eq!(started.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+    let result = executor
+        .run(
+            "{ ...Root } fragment Root on Query { ... on Query { viewer } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "viewer": null })));
+    assert_eq!(started.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn introspection_depth_is_limited_separately() {
+    //nb: introspection fields only count against max_introspection_depth, so
+    //a small max_depth doesn't break tooling
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .max_depth(1)
+        .build()
+        .unwrap();
+    let schema = executor.introspect().await.unwrap().to_json();
+    assert_eq!(schema["__schema"]["queryType"], json!({ "name": "Query" }));
+
+    let result = executor
+        .run(
+            r#"{ viewer __type(name: "Person") { fields { type { name } } } }"#,
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result();
+    assert!(result.is_ok());
+
+    let executor = Executor::builder()
+        .type_system(SCHEMA)
+        .max_introspection_depth(3)
+        .build()
+        .unwrap();
+    let result = executor
+        .run(
+            r#"{ __type(name: "Person") { fields { name } } }"#,
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result();
+    assert!(result.is_ok());
+
+    let err = executor
+        .run(
+            r#"{ __type(name: "Person") { fields { type { name } } } }"#,
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap_err();
+    assert_eq!(
+        err.message(),
+        "introspection query depth 4 exceeds maximum allowed depth of 3"
+    );
+
+    //nb: the standard introspection query spreads nested TypeRef fragments
+    let err = executor.introspect().await.unwrap_err();
+    assert!(err.message().starts_with("introspection query depth"));
+}
+
+#[tokio::test]
+async fn failed_value_transforms_are_field_errors() {
+    struct Upper;
+
+    #[async_trait::async_trait]
+    impl crate::DirectiveHandler for Upper {
+        async fn apply(&self, ctx: &Ctx, next: &dyn ObjectResolver) -> Result<Resolved> {
+            next.resolve_field(ctx, ctx.field_name())
+                .await?
+                .and_then_value(|value| match value {
+                    ConstValue::String(s) => Ok(s.to_uppercase().into()),
+                    other => Err(anyhow!("can't uppercase {}", other)),
+                })
+        }
+    }
+
+    let executor = Executor::builder()
+        .type_system(format!("{}\ndirective @upper on FIELD", SCHEMA))
+        .directive("upper", Upper)
+        .build()
+        .unwrap();
+
+    //nb: objects pass through untouched
+    let response = executor
+        .run(
+            "query { person @upper { firstName @upper age @upper lastName } }",
+            QueryResolver,
+            None,
+            HashMap::new(),
+        )
+        .await;
+
+    assert_eq!(
+        response.data,
+        expected(json!({
+            "person": { "firstName": "ZACK", "age": null, "lastName": "Angelo" }
+        }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message(), "can't uppercase 39");
+    assert_eq!(error_path(&response.errors[0]), ["person", "age"]);
+}
+
+#[tokio::test]
+async fn trait_object_resolvers_execute() {
+    struct Plugin(Arc<dyn ObjectResolver>);
+
+    #[async_trait::async_trait]
+    impl ObjectResolver for Plugin {
+        async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "person" => Ok(Resolved::object(self.0.clone())),
+                _ => Err(anyhow!("invalid field: {}", name)),
+            }
+        }
+    }
+
+    let executor = Executor::new(SCHEMA).unwrap();
+
+    let person: Arc<dyn ObjectResolver> = Arc::new(PersonResolver);
+    let query: Arc<dyn ObjectResolver> = Arc::new(Plugin(person));
+    let result = executor
+        .run(
+            "query { person { firstName lastName } }",
+            query,
+            None,
+            HashMap::new(),
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(
+        result,
+        expected(json!({ "person": { "firstName": "Zack", "lastName": "Angelo" } }))
+    );
+
+    let query: Box<dyn ObjectResolver> = Box::new(QueryResolver);
+    let result = executor
+        .run("query { person { age } }", query, None, HashMap::new())
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(result, expected(json!({ "person": { "age": 39 } })));
+}
@@ -3,6 +3,7 @@
 
 use crate::{
     resolver::{Ctx, ObjectResolver, Resolved},
+    sdl::value_literal,
     value::ConstValue,
 };
 use anyhow::anyhow;
@@ -13,6 +14,21 @@ use apollo_compiler::hir::{
 use async_trait::async_trait;
 use std::sync::Arc;
 
+/// The standard full introspection query (as sent by GraphiQL and
+/// graphql-js' `getIntrospectionQuery`), see
+/// [`Executor::introspect`](crate::Executor::introspect)
+pub(crate) const INTROSPECTION_QUERY: &str = include_str!("introspection_query.graphql");
+
+/// Root resolver for operations that only select introspection fields
+pub(crate) struct NoopResolver;
+
+#[async_trait]
+impl ObjectResolver for NoopResolver {
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        Err(anyhow!("field not found: {}", name))
+    }
+}
+
 /// ObjectResolver that adds __typename introspection to another resolver
 pub struct IspObjectResolver<'a> {
     pub(crate) type_def: Arc<ObjectTypeDefinition>, //TODO probably use reference instead
@@ -21,6 +37,10 @@ pub struct IspObjectResolver<'a> {
 
 #[async_trait]
 impl<'a> ObjectResolver for IspObjectResolver<'a> {
+    async fn resolve_type_name(&self) -> Result<Option<&str>> {
+        self.inner.resolve_type_name().await
+    }
+
     async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
         match name {
             "__typename" => Ok(Resolved::Value(ConstValue::String(
@@ -31,16 +51,20 @@ impl<'a> ObjectResolver for IspObjectResolver<'a> {
     }
 }
 
-/// ObjectResolver intended to be added to a query root to expose schema
-/// introspection fields
-pub struct IspRootResolver<'a> {
+/// ObjectResolver wrapped around the root of every operation (query, mutation
+/// and subscription alike) to expose the `__schema` and `__type` meta-fields
+pub struct IspRootResolver {
     pub(crate) ts: Arc<hir::TypeSystem>,
-    pub(crate) inner: &'a dyn ObjectResolver,
+    pub(crate) inner: Arc<dyn ObjectResolver>,
     pub(crate) schema_def: Arc<SchemaDefinition>,
 }
 
 #[async_trait]
-impl<'a> ObjectResolver for IspRootResolver<'a> {
+impl ObjectResolver for IspRootResolver {
+    async fn resolve_type_name(&self) -> Result<Option<&str>> {
+        self.inner.resolve_type_name().await
+    }
+
     async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
         match name {
             "__schema" => {
@@ -50,6 +74,15 @@ impl<'a> ObjectResolver for IspRootResolver<'a> {
                 };
                 Ok(Resolved::object(resolver))
             }
+            "__type" => {
+                let type_name = ctx.try_arg::<String>("name")?;
+                Ok(match self.ts.type_definitions_by_name.get(&type_name) {
+                    Some(_) if !ctx.ectx.is_type_hidden(&type_name) => {
+                        resolve_named_ty(&self.ts, &type_name)
+                    }
+                    _ => Resolved::null(),
+                })
+            }
             other => self.inner.resolve_field(ctx, other).await,
         }
     }
@@ -72,15 +105,16 @@ pub struct IspSchemaResolver {
 
 #[async_trait]
 impl ObjectResolver for IspSchemaResolver {
-    async fn resolve_field(&self, _: &Ctx, name: &str) -> Result<Resolved> {
+    async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
         Ok(match name {
-            "description" => todo!(),
+            "description" => Resolved::string_opt(self.schema_def.description()),
             "types" => {
                 let all_type_defs = self
                     .ts
                     .type_definitions_by_name
                     .values()
                     .filter(|ty| !ty.name().starts_with("__")) //TODO there should be a more reliable check somewhere for excluding introspection types
+                    .filter(|ty| !ctx.ectx.is_type_hidden(ty.name()))
                     .map(|ty| {
                         Resolved::object(IspTypeResolver {
                             ty: hir::Type::Named {
@@ -99,10 +133,27 @@ impl ObjectResolver for IspSchemaResolver {
                 .query()
                 .map(|query| resolve_named_ty(&self.ts, query))
                 .unwrap_or(Resolved::null()),
-            //TODO implement these other fields
-            // "mutationType" => todo!(),
-            // "subscriptionType" => todo!(),
-            // "directives" => todo!(),
+            "mutationType" => self
+                .schema_def
+                .mutation()
+                .map(|mutation| resolve_named_ty(&self.ts, mutation))
+                .unwrap_or(Resolved::null()),
+            "subscriptionType" => self
+                .schema_def
+                .subscription()
+                .map(|subscription| resolve_named_ty(&self.ts, subscription))
+                .unwrap_or(Resolved::null()),
+            "directives" => self
+                .ts
+                .definitions
+                .directives
+                .values()
+                .map(|directive_def| IspDirectiveResolver {
+                    directive_def: directive_def.clone(),
+                    ts: self.ts.clone(),
+                })
+                .collect::<Vec<_>>()
+                .into(),
             _ => Resolved::null(),
         })
     }
@@ -138,8 +189,10 @@ impl IspTypeResolver {
     async fn resolve_list_type(&self, field: &str, of_type: &hir::Type) -> Result<Resolved> {
         match field {
             "kind" => Ok(Resolved::enum_value("LIST")), //": __TypeKind!
-            "name" => Ok(Resolved::string(self.ty.name())), //: String
-            "description" => Ok(Resolved::string("")), //: String -> TODO is this shared with type definition?
+            //nb: wrapper types are unnamed, only the named type they wrap
+            //(reached through ofType) has a name and description
+            "name" => Ok(Resolved::null()),          //: String
+            "description" => Ok(Resolved::null()),   //: String
             "fields" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__Field!]
             "interfaces" => Ok(Resolved::null()), //: [__Type!]
             "possibleTypes" => Ok(Resolved::null()), //: [__Type!]
@@ -149,7 +202,7 @@ impl IspTypeResolver {
                 ty: of_type.clone(),
                 ts: self.ts.clone(),
             })), //: __Type
-            "specifiedByURL" => Ok(Resolved::null()), //: String TODO - not sure where to get this
+            "specifiedByURL" => Ok(Resolved::null()), //: String, scalars only
             _ => Err(anyhow!("invalid list type field")),
         }
     }
@@ -157,8 +210,8 @@ impl IspTypeResolver {
     async fn resolve_non_null_type(&self, field: &str, of_type: &hir::Type) -> Result<Resolved> {
         match field {
             "kind" => Ok(Resolved::enum_value("NON_NULL")), //": __TypeKind!
-            "name" => Ok(Resolved::string(self.ty.name())), //: String
-            "description" => Ok(Resolved::null()), //: String -> TODO is this shared with type definition?
+            "name" => Ok(Resolved::null()),                 //: String
+            "description" => Ok(Resolved::null()),          //: String
             "fields" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__Field!]
             "interfaces" => Ok(Resolved::null()), //: [__Type!]
             "possibleTypes" => Ok(Resolved::null()), //: [__Type!]
@@ -168,12 +221,17 @@ impl IspTypeResolver {
                 ty: of_type.clone(),
                 ts: self.ts.clone(),
             })), //: __Type
-            "specifiedByURL" => Ok(Resolved::null()), //: String TODO - not sure where to get this
-            _ => Err(anyhow!("invalid list type field")),
+            "specifiedByURL" => Ok(Resolved::null()), //: String, scalars only
+            _ => Err(anyhow!("invalid non-null type field")),
         }
     }
 
-    async fn resolve_named_type(&self, field: &str, type_name: &str) -> Result<Resolved> {
+    async fn resolve_named_type(
+        &self,
+        ctx: &Ctx,
+        field: &str,
+        type_name: &str,
+    ) -> Result<Resolved> {
         // let db = self.db.lock().await;
         let ty_def = self.ts.type_definitions_by_name.get(type_name);
 
@@ -183,19 +241,19 @@ impl IspTypeResolver {
                     self.resolve_scalar_type(field, type_def)
                 }
                 hir::TypeDefinition::ObjectTypeDefinition(type_def) => {
-                    self.resolve_object_type(field, type_def)
+                    self.resolve_object_type(ctx, field, type_def)
                 }
                 hir::TypeDefinition::InterfaceTypeDefinition(type_def) => {
-                    self.resolve_interface_type(field, type_def)
+                    self.resolve_interface_type(ctx, field, type_def)
                 }
                 hir::TypeDefinition::UnionTypeDefinition(type_def) => {
-                    self.resolve_union_type(field, type_def)
+                    self.resolve_union_type(ctx, field, type_def)
                 }
                 hir::TypeDefinition::EnumTypeDefinition(type_def) => {
-                    self.resolve_enum_type(field, type_def)
+                    self.resolve_enum_type(ctx, field, type_def)
                 }
                 hir::TypeDefinition::InputObjectTypeDefinition(type_def) => {
-                    self.resolve_input_type(field, type_def)
+                    self.resolve_input_type(ctx, field, type_def)
                 }
             },
             None => Ok(Resolved::null()),
@@ -222,6 +280,11 @@ impl IspTypeResolver {
         }
     }
 
+    /// The `url` of a scalar's `@specifiedBy` directive. Every introspected
+    /// scalar, wherever it's referenced from, is resolved through here, and
+    /// built-in scalars have no `@specifiedBy` so they're `null`. Directives
+    /// in type system documents can't use variables, so the url is always a
+    /// string literal.
     fn resolve_specified_by(&self, type_def: &hir::ScalarTypeDefinition) -> Resolved {
         Resolved::string_opt(
             type_def
@@ -236,6 +299,7 @@ impl IspTypeResolver {
 
     fn resolve_object_type(
         &self,
+        ctx: &Ctx,
         field: &str,
         type_def: &hir::ObjectTypeDefinition,
     ) -> Result<Resolved> {
@@ -243,19 +307,10 @@ impl IspTypeResolver {
             "kind" => Ok(Resolved::enum_value("OBJECT")), //": __TypeKind!
             "name" => Ok(Resolved::string(self.ty.name())), //: String
             "description" => Ok(Resolved::string_opt(type_def.description())), //: String -> TODO is this shared with type definition?
-            "fields" => Ok(Resolved::Array(
-                type_def
-                    .fields()
-                    .map(|f| {
-                        Resolved::object(IspFieldResolver {
-                            field_def: f.clone(),
-                            ts: self.ts.clone(),
-                        })
-                    })
-                    .collect(),
-            )), //(includeDeprecated: Boolean = false): [__Field!]
+            "fields" => Ok(self.resolve_fields(ctx, type_def.fields())), //(includeDeprecated: Boolean = false): [__Field!]
             "interfaces" => Ok(type_def
                 .implements_interfaces()
+                .filter(|i| !ctx.ectx.is_type_hidden(i.interface()))
                 .map(|i| IspTypeResolver {
                     ts: self.ts.clone(),
                     ty: hir::Type::Named {
@@ -265,17 +320,18 @@ impl IspTypeResolver {
                 })
                 .collect::<Vec<_>>()
                 .into()), //: [__Type!]
-            "possibleTypes" => Ok(Resolved::null()),                           //: [__Type!]
+            "possibleTypes" => Ok(Resolved::null()),                     //: [__Type!]
             "enumValues" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__EnumValue!]
             "inputFields" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__InputValue!]
             "ofType" => Ok(Resolved::null()),      //: __Type
-            "specifiedByURL" => Ok(Resolved::null()), //: String TODO - not sure where to get this
+            "specifiedByURL" => Ok(Resolved::null()), //: String, scalars only
             _ => Err(anyhow!("invalid list type field")),
         }
     }
 
     fn resolve_interface_type(
         &self,
+        ctx: &Ctx,
         field: &str,
         type_def: &hir::InterfaceTypeDefinition,
     ) -> Result<Resolved> {
@@ -283,34 +339,44 @@ impl IspTypeResolver {
             "kind" => Ok(Resolved::enum_value("INTERFACE")), //": __TypeKind!
             "name" => Ok(Resolved::string(self.ty.name())),  //: String
             "description" => Ok(Resolved::string_opt(type_def.description())), //: String -> TODO is this shared with type definition?
-            "fields" => Ok(Resolved::Array(
-                type_def
-                    .fields()
-                    .map(|f| {
-                        Resolved::object(IspFieldResolver {
-                            field_def: f.clone(),
-                            ts: self.ts.clone(),
-                        })
-                    })
-                    .collect(),
-            )), //TODO includeDeprecated arg
-            "interfaces" => Ok(Resolved::null()),                              //: [__Type!]
-            "possibleTypes" => Ok(self.resolve_impl_possible_types(type_def.name())), //: [__Type!]
+            "fields" => Ok(self.resolve_fields(ctx, type_def.fields())), //(includeDeprecated: Boolean = false): [__Field!]
+            "interfaces" => Ok(Resolved::null()),                        //: [__Type!]
+            "possibleTypes" => Ok(self.resolve_impl_possible_types(ctx, type_def.name())), //: [__Type!]
             "enumValues" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__EnumValue!]
             "inputFields" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__InputValue!]
             "ofType" => Ok(Resolved::null()),      //: __Type
-            "specifiedByURL" => Ok(Resolved::null()), //: String TODO - not sure where to get this
+            "specifiedByURL" => Ok(Resolved::null()), //: String, scalars only
             _ => Err(anyhow!("invalid list type field")),
         }
     }
 
-    fn resolve_impl_possible_types(&self, iface_name: &str) -> Resolved {
+    fn resolve_fields<'a>(
+        &self,
+        ctx: &Ctx,
+        fields: impl Iterator<Item = &'a hir::FieldDefinition>,
+    ) -> Resolved {
+        let include_deprecated = include_deprecated(ctx);
+
+        fields
+            .filter(|f| include_deprecated || !f.is_deprecated())
+            .filter(|f| !ctx.ectx.is_field_hidden(f))
+            .map(|f| IspFieldResolver {
+                field_def: f.clone(),
+                ts: self.ts.clone(),
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn resolve_impl_possible_types(&self, ctx: &Ctx, iface_name: &str) -> Resolved {
         //nb: slow but probably fine for now, maybe index in future
         self.ts
             .definitions
             .objects
             .iter()
-            .filter(|(_, ty)| ty.implements_interface(iface_name))
+            .filter(|(name, ty)| {
+                ty.implements_interface(iface_name) && !ctx.ectx.is_type_hidden(name)
+            })
             .map(|(name, _ty)| IspTypeResolver {
                 //TODO create an IspObjectTypeResolver directly when we refactor
                 ts: self.ts.clone(),
@@ -323,8 +389,22 @@ impl IspTypeResolver {
             .into()
     }
 
+    fn resolve_union_possible_types(
+        &self,
+        ctx: &Ctx,
+        union_type: &hir::UnionTypeDefinition,
+    ) -> Resolved {
+        union_type
+            .members()
+            .filter(|member| !ctx.ectx.is_type_hidden(member.name()))
+            .map(|member| resolve_named_ty(&self.ts, member.name()))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
     fn resolve_union_type(
         &self,
+        ctx: &Ctx,
         field: &str,
         type_def: &hir::UnionTypeDefinition,
     ) -> Result<Resolved> {
@@ -334,21 +414,18 @@ impl IspTypeResolver {
             "description" => Ok(Resolved::string_opt(type_def.description())), //: String -> TODO is this shared with type definition?
             "fields" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__Field!]
             "interfaces" => Ok(Resolved::null()), //: [__Type!]
-            "possibleTypes" => Ok(type_def
-                .members()
-                .map(|m| resolve_named_ty(&self.ts, m.name()))
-                .collect::<Vec<_>>()
-                .into()), //: [__Type!]
+            "possibleTypes" => Ok(self.resolve_union_possible_types(ctx, type_def)), //: [__Type!]
             "enumValues" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__EnumValue!]
             "inputFields" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__InputValue!]
             "ofType" => Ok(Resolved::null()),      //: __Type
-            "specifiedByURL" => Ok(Resolved::null()), //: String TODO - not sure where to get this
+            "specifiedByURL" => Ok(Resolved::null()), //: String, scalars only
             _ => Err(anyhow!("invalid list type field")),
         }
     }
 
     fn resolve_enum_type(
         &self,
+        ctx: &Ctx,
         field: &str,
         type_def: &hir::EnumTypeDefinition,
     ) -> Result<Resolved> {
@@ -361,6 +438,8 @@ impl IspTypeResolver {
             "possibleTypes" => Ok(Resolved::null()), //: [__Type!]
             "enumValues" => Ok(type_def
                 .values()
+                .filter(|v| include_deprecated(ctx) || !v.is_deprecated())
+                .filter(|v| !ctx.ectx.is_enum_value_hidden(v))
                 .map(|v| IspEnumValueResolver {
                     enum_value: v.clone(),
                 })
@@ -368,13 +447,14 @@ impl IspTypeResolver {
                 .into()), //(includeDeprecated: Boolean = false): [__EnumValue!]
             "inputFields" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__InputValue!]
             "ofType" => Ok(Resolved::null()),      //: __Type
-            "specifiedByURL" => Ok(Resolved::null()), //: String TODO - not sure where to get this
+            "specifiedByURL" => Ok(Resolved::null()), //: String, scalars only
             _ => Err(anyhow!("invalid list type field")),
         }
     }
 
     fn resolve_input_type(
         &self,
+        ctx: &Ctx,
         field: &str,
         type_def: &hir::InputObjectTypeDefinition,
     ) -> Result<Resolved> {
@@ -388,6 +468,8 @@ impl IspTypeResolver {
             "enumValues" => Ok(Resolved::null()), //(includeDeprecated: Boolean = false): [__EnumValue!]
             "inputFields" => Ok(type_def
                 .fields()
+                .filter(|f| include_deprecated(ctx) || !f.is_deprecated())
+                .filter(|f| !ctx.ectx.is_input_value_hidden(f))
                 .map(|f| IspInputValueResolver {
                     ts: self.ts.clone(),
                     input_value_def: f.clone(),
@@ -395,18 +477,20 @@ impl IspTypeResolver {
                 .collect::<Vec<_>>()
                 .into()), //(includeDeprecated: Boolean = false): [__InputValue!]
             "ofType" => Ok(Resolved::null()),     //: __Type
-            "specifiedByURL" => Ok(Resolved::null()), //: String TODO - not sure where to get this
+            "specifiedByURL" => Ok(Resolved::null()), //: String, scalars only
             _ => Err(anyhow!("invalid list type field")),
         }
     }
 }
 #[async_trait]
 impl ObjectResolver for IspTypeResolver {
-    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+    async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
         //TODO this match will re-run for every field, probably pre-evaluate it in a constructor
         match &self.ty {
             hir::Type::List { ty, .. } => self.resolve_list_type(name, ty.as_ref()).await,
-            hir::Type::Named { name: ty_name, .. } => self.resolve_named_type(name, ty_name).await,
+            hir::Type::Named { name: ty_name, .. } => {
+                self.resolve_named_type(ctx, name, ty_name).await
+            }
             hir::Type::NonNull { ty, .. } => self.resolve_non_null_type(name, ty).await,
         }
     }
@@ -430,21 +514,16 @@ pub struct IspFieldResolver {
 
 #[async_trait]
 impl ObjectResolver for IspFieldResolver {
-    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+    async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
         Ok(match name {
             "name" => Resolved::string(self.field_def.name()),
             "description" => Resolved::string_opt(self.field_def.description()),
-            "args" => self
-                .field_def
-                .arguments()
-                .input_values()
-                .iter()
-                .map(|iv| IspInputValueResolver {
-                    input_value_def: iv.clone(),
-                    ts: self.ts.clone(),
-                })
-                .collect::<Vec<_>>()
-                .into(), //Resolved::Array(vec![]), //TODO
+            "args" => resolve_input_values(
+                ctx,
+                &self.ts,
+                self.field_def.arguments().input_values(),
+                include_deprecated(ctx),
+            ),
             "type" => Resolved::object(IspTypeResolver {
                 ty: self.field_def.ty().clone(),
                 ts: self.ts.clone(),
@@ -476,12 +555,12 @@ impl ObjectResolver for IspInputValueResolver {
             "name" => Resolved::string(self.input_value_def.name()),
             "description" => Resolved::string_opt(self.input_value_def.description()),
             "type" => resolve_ty(&self.ts, &self.input_value_def.ty()),
-            "defaultValue" => Resolved::null(), //TODO need to figure this out
-            // Resolved::string_opt(
-            //     self.input_value_def
-            //         .default_value()
-            //         .map(|v| format!("{:?}", v)), //TODO not sure what this represenation needs to be, debug for now
-            // ),
+            "defaultValue" => Resolved::string_opt(
+                self.input_value_def
+                    .default_value()
+                    .map(value_literal)
+                    .transpose()?,
+            ),
             "isDeprecated" => self.input_value_def.resolve_is_deprecated(),
             "deprecationReason" => self.input_value_def.resolve_deprecation_reason(),
             _ => Resolved::null(),
@@ -512,6 +591,66 @@ impl ObjectResolver for IspEnumValueResolver {
     }
 }
 
+// type __Directive {
+//     name: String!
+//     description: String
+//     locations: [__DirectiveLocation!]!
+//     args(includeDeprecated: Boolean = false): [__InputValue!]!
+//     isRepeatable: Boolean!
+//   }
+pub struct IspDirectiveResolver {
+    ts: Arc<TypeSystem>,
+    directive_def: Arc<hir::DirectiveDefinition>,
+}
+
+#[async_trait]
+impl ObjectResolver for IspDirectiveResolver {
+    async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+        Ok(match name {
+            "name" => Resolved::string(self.directive_def.name()),
+            "description" => Resolved::string_opt(self.directive_def.description()),
+            "locations" => Resolved::array(
+                self.directive_def
+                    .directive_locations()
+                    .iter()
+                    .map(|location| Resolved::enum_value(location.name())),
+            ),
+            "args" => resolve_input_values(
+                ctx,
+                &self.ts,
+                self.directive_def.arguments().input_values(),
+                include_deprecated(ctx),
+            ),
+            "isRepeatable" => Resolved::Value(self.directive_def.repeatable().into()),
+            _ => Resolved::null(),
+        })
+    }
+}
+
+/// The `includeDeprecated` argument of `fields`, `args`, `enumValues` and
+/// `inputFields`
+fn include_deprecated(ctx: &Ctx) -> bool {
+    ctx.arg_with_default("includeDeprecated", false)
+}
+
+fn resolve_input_values(
+    ctx: &Ctx,
+    ts: &Arc<TypeSystem>,
+    input_values: &[InputValueDefinition],
+    include_deprecated: bool,
+) -> Resolved {
+    input_values
+        .iter()
+        .filter(|iv| include_deprecated || !iv.is_deprecated())
+        .filter(|iv| !ctx.ectx.is_input_value_hidden(iv))
+        .map(|iv| IspInputValueResolver {
+            input_value_def: iv.clone(),
+            ts: ts.clone(),
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
 fn resolve_named_ty(ts: &Arc<TypeSystem>, ty_name: &str) -> Resolved {
     resolve_ty(
         ts,
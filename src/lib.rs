@@ -1,8 +1,43 @@
+mod data;
+mod dataloader;
+mod directive;
+mod error;
 mod executor;
 mod introspection;
+mod middleware;
+mod observer;
+mod request;
 mod resolver;
+mod response;
+mod scalar;
+mod sdl;
+#[cfg(feature = "otel")]
+mod telemetry;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+mod upload;
 mod value;
 
-pub use executor::Executor;
-pub use resolver::{Ctx, ObjectResolver, Resolved};
-pub use value::{ConstValue, Name};
+pub use data::Data;
+pub use dataloader::DataLoader;
+pub use directive::DirectiveHandler;
+pub use error::{
+    Error, ErrorContext, ErrorKind, GraphQLError, Location, PathSegment, UnknownField,
+};
+pub use executor::{ExecSchema, Executor, ExecutorBuilder, PreparedQuery, SelectedField};
+pub use middleware::{FieldInfo, Logger, Next, OperationInfo, ResolverMiddleware};
+pub use observer::ExecutionObserver;
+pub use request::{BatchRequest, Request};
+pub use resolver::{
+    Ctx, EventStream, ObjectResolver, Resolved, SubscriptionResolver, SyncObjectResolver,
+};
+pub use response::{
+    BatchResponse, ExecutionResult, IncrementalResponse, IncrementalResult, Response,
+};
+pub use scalar::{DateTimeCodec, ScalarCodec};
+#[cfg(feature = "otel")]
+pub use telemetry::OpenTelemetry;
+pub use upload::{parse_multipart, Upload, UploadTooLarge};
+#[cfg(feature = "axum")]
+pub use upload::{MultipartUpload, DEFAULT_MAX_UPLOAD_SIZE};
+pub use value::{from_value, ConstValue, DeserializerError, Id, Name};
@@ -0,0 +1,190 @@
+//! Hooks run around every resolver call, for cross-cutting concerns (logging,
+//! auth checks, metrics) that shouldn't live in each resolver.
+
+use std::{sync::Arc, time::Instant};
+
+use anyhow::Result;
+use apollo_compiler::hir;
+use async_trait::async_trait;
+
+use crate::{
+    error::{PathSegment, ResponsePath},
+    resolver::{Ctx, ObjectResolver, Resolved},
+    sdl::type_ref,
+};
+
+/// Registered with [`ExecutorBuilder::middleware`](crate::ExecutorBuilder::middleware),
+/// middleware wraps every field's resolver. Middleware composes in
+/// registration order: the first registered is outermost, so its
+/// `before_field` runs first and its `after_field` runs last. An error from
+/// any hook fails the field just as if the resolver had returned it.
+///
+/// Simple checks only need one of the hooks:
+///
+/// ```ignore
+/// struct RequireUser;
+///
+/// #[async_trait]
+/// impl ResolverMiddleware for RequireUser {
+///     async fn before_field(&self, ctx: &Ctx) -> Result<()> {
+///         ctx.data::<CurrentUser>()
+///             .map(|_| ())
+///             .ok_or_else(|| anyhow!("not signed in"))
+///     }
+/// }
+/// ```
+///
+/// Middleware that needs to wrap the resolver (timing it, translating its
+/// errors, or skipping it entirely) overrides [`resolve`](Self::resolve).
+#[async_trait]
+pub trait ResolverMiddleware: Send + Sync {
+    /// Called before the field's resolver, an error skips the resolver
+    async fn before_field(&self, _ctx: &Ctx) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with the resolver's result before it's completed (i.e. before
+    /// any nested selection set is resolved)
+    async fn after_field(&self, _ctx: &Ctx, _result: &Resolved) -> Result<()> {
+        Ok(())
+    }
+
+    /// Resolves the field by calling `next`, which runs the remaining
+    /// middleware and then the resolver. Returning without calling `next`
+    /// short-circuits resolution.
+    ///
+    /// The default runs [`before_field`](Self::before_field) and
+    /// [`after_field`](Self::after_field) around `next`.
+    async fn resolve(&self, ctx: &Ctx, _info: &FieldInfo<'_>, next: Next<'_>) -> Result<Resolved> {
+        self.before_field(ctx).await?;
+        let resolved = next.run(ctx).await?;
+        self.after_field(ctx, &resolved).await?;
+
+        Ok(resolved)
+    }
+
+    /// Span the whole operation executes in, field spans created beneath it
+    /// (e.g. by [`resolve`](Self::resolve)) become its children. Spans from
+    /// several middleware nest in registration order.
+    fn operation_span(&self, _operation: &OperationInfo<'_>) -> Option<tracing::Span> {
+        None
+    }
+}
+
+/// Describes the operation being executed to
+/// [`ResolverMiddleware::operation_span`]
+pub struct OperationInfo<'a> {
+    pub(crate) name: Option<&'a str>,
+    pub(crate) ty: hir::OperationType,
+    pub(crate) source: &'a str,
+}
+
+impl<'a> OperationInfo<'a> {
+    pub fn name(&self) -> Option<&str> {
+        self.name
+    }
+
+    /// `query`, `mutation` or `subscription`
+    pub fn operation_type(&self) -> &'static str {
+        match self.ty {
+            hir::OperationType::Query => "query",
+            hir::OperationType::Mutation => "mutation",
+            hir::OperationType::Subscription => "subscription",
+        }
+    }
+
+    /// The request document the operation was selected from
+    pub fn source(&self) -> &str {
+        self.source
+    }
+}
+
+/// Describes the field being resolved to [`ResolverMiddleware::resolve`]
+pub struct FieldInfo<'a> {
+    pub(crate) field: &'a hir::Field,
+    pub(crate) ty: &'a hir::Type,
+    pub(crate) path: &'a ResponsePath,
+}
+
+impl<'a> FieldInfo<'a> {
+    pub fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    pub fn alias(&self) -> Option<&str> {
+        self.field.alias().map(|a| a.0.as_str())
+    }
+
+    /// Response path of the field, e.g. `people.0.name`
+    pub fn path(&self) -> Vec<PathSegment> {
+        self.path.to_vec()
+    }
+
+    /// Name of the type the field is defined on
+    pub fn parent_type(&self) -> Option<&str> {
+        self.field.parent_type_name()
+    }
+
+    /// The field's declared type, e.g. `[Person!]!`
+    pub fn return_type(&self) -> String {
+        type_ref(self.ty)
+    }
+}
+
+/// The rest of the middleware chain, ending in the field's resolver
+#[derive(Clone)]
+pub struct Next<'a> {
+    pub(crate) middleware: &'a [Arc<dyn ResolverMiddleware>],
+    pub(crate) resolver: &'a dyn ObjectResolver,
+    pub(crate) info: &'a FieldInfo<'a>,
+}
+
+impl<'a> Next<'a> {
+    pub async fn run(self, ctx: &Ctx) -> Result<Resolved> {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => {
+                let next = Next {
+                    middleware: rest,
+                    ..self
+                };
+
+                middleware.resolve(ctx, self.info, next).await
+            }
+            None => {
+                self.resolver
+                    .resolve_field_or_null(ctx, ctx.field_name())
+                    .await
+            }
+        }
+    }
+}
+
+/// Logs each field's resolution time, and its error if it failed
+pub struct Logger;
+
+#[async_trait]
+impl ResolverMiddleware for Logger {
+    async fn resolve(&self, ctx: &Ctx, info: &FieldInfo<'_>, next: Next<'_>) -> Result<Resolved> {
+        let start = Instant::now();
+        let resolved = next.run(ctx).await;
+        let elapsed_us = Instant::now().duration_since(start).as_micros();
+
+        match &resolved {
+            Ok(_) => tracing::info!(
+                field = info.name(),
+                parent_type = info.parent_type(),
+                elapsed_us,
+                "resolved field"
+            ),
+            Err(err) => tracing::warn!(
+                field = info.name(),
+                parent_type = info.parent_type(),
+                elapsed_us,
+                "field failed: {}",
+                err
+            ),
+        }
+
+        resolved
+    }
+}
@@ -0,0 +1,39 @@
+//! Callbacks for collecting metrics about field resolution.
+
+use std::time::Duration;
+
+use crate::{error::GraphQLError, middleware::FieldInfo};
+
+/// Registered with [`ExecutorBuilder::observer`](crate::ExecutorBuilder::observer),
+/// an observer is told when each field starts and finishes resolving and
+/// about the errors it raised, e.g. to record latency histograms and error
+/// counts in a metrics backend:
+///
+/// ```ignore
+/// struct FieldMetrics;
+///
+/// impl ExecutionObserver for FieldMetrics {
+///     fn on_field_end(&self, field: &FieldInfo<'_>, elapsed: Duration) {
+///         histogram!("graphql_field_seconds", elapsed.as_secs_f64(),
+///             "parent_type" => field.parent_type().unwrap_or_default().to_owned(),
+///             "field" => field.name().to_owned());
+///     }
+/// }
+/// ```
+///
+/// Callbacks run inline on the executor, so they should be quick. Unlike
+/// [`ResolverMiddleware`](crate::ResolverMiddleware), observers can't affect
+/// the result.
+pub trait ExecutionObserver: Send + Sync {
+    /// Called before the field's resolver (and any middleware) runs
+    fn on_field_start(&self, _field: &FieldInfo<'_>) {}
+
+    /// Called once the field is complete, `elapsed` covers its resolver and
+    /// completing the value it returned (including any nested selections)
+    fn on_field_end(&self, _field: &FieldInfo<'_>, _elapsed: Duration) {}
+
+    /// Called for each error raised resolving or completing the field, before
+    /// `on_field_end`. Errors raised by fields nested beneath it are reported
+    /// against those fields.
+    fn on_error(&self, _field: &FieldInfo<'_>, _error: &GraphQLError) {}
+}
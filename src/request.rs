@@ -0,0 +1,152 @@
+use std::{any::Any, collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Deserializer};
+use tokio_util::sync::CancellationToken;
+
+use crate::{data::Data, value::ConstValue};
+
+/// A GraphQL request to be run by an [`Executor`](crate::Executor)
+///
+/// ```ignore
+/// let request = Request::new("query { peopleCount }")
+///     .operation_name("People")
+///     .timeout(Duration::from_secs(5));
+///
+/// executor.execute(request, QueryResolver).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub(crate) query: String,
+    pub(crate) operation_name: Option<String>,
+    pub(crate) variables: HashMap<String, ConstValue>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) data: Data,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+    pub(crate) tracing: bool,
+}
+
+impl Request {
+    /// Creates a request for the specified query document
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            operation_name: None,
+            variables: HashMap::new(),
+            timeout: None,
+            data: Data::new(),
+            cancellation_token: None,
+            tracing: false,
+        }
+    }
+
+    /// Selects the operation to run when the document contains more than one
+    #[must_use]
+    pub fn operation_name(self, operation_name: impl Into<String>) -> Self {
+        Self {
+            operation_name: Some(operation_name.into()),
+            ..self
+        }
+    }
+
+    /// Sets the variable values for the operation. Values can be anything
+    /// convertible into a [`ConstValue`], e.g. the `serde_json::Value`s of a
+    /// deserialized request body.
+    #[must_use]
+    pub fn variables<V: Into<ConstValue>>(
+        self,
+        variables: impl IntoIterator<Item = (String, V)>,
+    ) -> Self {
+        Self {
+            variables: variables
+                .into_iter()
+                .map(|(name, value)| (name, value.into()))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Sets the variable values from a JSON object, e.g. the `variables` of
+    /// a GraphQL-over-HTTP request body
+    #[must_use]
+    pub fn json_variables(self, variables: serde_json::Map<String, serde_json::Value>) -> Self {
+        self.variables(variables)
+    }
+
+    /// Overrides the executor's timeout for this request only
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Stops the operation when `token` is cancelled, e.g. because the client
+    /// disconnected. Outstanding resolver futures are dropped and the
+    /// response has a single `CANCELLED` error, for which
+    /// [`GraphQLError::is_cancelled`](crate::GraphQLError::is_cancelled) is
+    /// true.
+    #[must_use]
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation_token: Some(token),
+            ..self
+        }
+    }
+
+    /// Adds per-resolver timings to the response's `tracing` extension, in
+    /// the [Apollo tracing](https://github.com/apollographql/apollo-tracing)
+    /// format. Not supported for incrementally delivered responses.
+    #[must_use]
+    pub fn tracing(self, enabled: bool) -> Self {
+        Self {
+            tracing: enabled,
+            ..self
+        }
+    }
+
+    /// Makes `value` available to resolvers through [`Ctx::data`](crate::Ctx::data)
+    #[must_use]
+    pub fn data<T: Any + Send + Sync>(mut self, value: T) -> Self {
+        self.data.insert(value);
+        self
+    }
+
+    /// Replaces all of the request's data, see [`Data`]
+    #[must_use]
+    pub fn with_data(self, data: Data) -> Self {
+        Self { data, ..self }
+    }
+}
+
+/// The body of a GraphQL-over-HTTP request
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestBody {
+    query: String,
+    operation_name: Option<String>,
+    variables: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Deserializes from a GraphQL-over-HTTP request body (`query`,
+/// `operationName` and `variables`)
+impl<'de> Deserialize<'de> for Request {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let body = RequestBody::deserialize(deserializer)?;
+        let request = Request::new(body.query).json_variables(body.variables.unwrap_or_default());
+
+        Ok(match body.operation_name {
+            Some(operation_name) => request.operation_name(operation_name),
+            None => request,
+        })
+    }
+}
+
+/// A single request, or an array of them sent as one batch. Deserializes
+/// from either shape, see [`Executor::run_batch`](crate::Executor::run_batch).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BatchRequest {
+    Single(Request),
+    Batch(Vec<Request>),
+}
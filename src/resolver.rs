@@ -1,61 +1,146 @@
-use std::{collections::HashMap, fmt::Display, sync::Arc};
-
-use crate::value::{ConstValue, Name};
+use std::{any::Any, collections::HashMap, fmt::Display, sync::Arc};
+
+use crate::{
+    error::{PathSegment, ResponsePath, UnknownField},
+    executor::{coerce_input, look_ahead, ExecCtx, ExecSchema, FieldGroup, SelectedField},
+    upload::Upload,
+    value::{from_value, ConstValue, Id, Name},
+};
 use anyhow::{anyhow, Result};
 use apollo_compiler::hir::{self, Value};
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 use indexmap::IndexMap;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Number;
+use tokio_util::sync::CancellationToken;
 
 /// Resolver context
 pub struct Ctx {
-    pub(crate) variables: Arc<HashMap<String, ConstValue>>,
-    pub(crate) field: Arc<hir::Field>,
+    pub(crate) ectx: ExecCtx,
+    pub(crate) fields: FieldGroup,
+    pub(crate) path: ResponsePath,
 }
 
 impl Ctx {
-    //FIXME this is probably wrong and also would probably be easier to do
-    // in an upstream phase that eagerly resolves all the variables first
-    fn resolve_vars(&self, arg_value: &Value) -> Result<CtxArg> {
-        let const_v: ConstValue = match arg_value {
-            Value::Variable(var) => self
-                .variables
-                .get(var.name())
-                .ok_or_else(|| anyhow!("undefined variable: {}", var.name()))?
-                .clone(),
-            Value::Object { value, .. } => {
-                let fields: IndexMap<Name, ConstValue> = value
-                    .iter()
-                    .map(|(k, v)| {
-                        (
-                            Name::new(k.clone().src().to_owned()),
-                            self.resolve_vars(v).unwrap().0, //FIXME unwrap
-                        )
-                    })
-                    .collect::<IndexMap<_, _>>();
-                ConstValue::Object(fields)
-            }
-            Value::List { value, .. } => {
-                let values = value
-                    .iter()
-                    .map(|v| self.resolve_vars(v).unwrap().0) //FIXME unwrap()
-                    .collect::<Vec<ConstValue>>();
+    /// Name of the field being resolved
+    pub fn field_name(&self) -> &str {
+        self.field().name()
+    }
 
-                ConstValue::List(values)
-            }
-            Value::Boolean { value, .. } => ConstValue::Boolean(*value),
-            Value::String { value, .. } => ConstValue::String(value.clone()),
-            Value::Int { value, .. } => {
-                ConstValue::Number(Number::from(value.to_i32_checked().unwrap()))
-            }
-            Value::Float { value, .. } => {
-                ConstValue::Number(Number::from_f64(value.get()).unwrap())
+    /// Response path of the field being resolved, e.g. `people.0.name`
+    pub fn path(&self) -> Vec<PathSegment> {
+        self.path.to_vec()
+    }
+
+    //nb: fields in a group are guaranteed by validation to have the same name
+    //and arguments, so the first one stands in for the group
+    pub(crate) fn field(&self) -> &hir::Field {
+        &self.fields[0]
+    }
+
+    /// The schema the operation executes against, e.g. for middleware that
+    /// reads custom directives on type definitions:
+    ///
+    /// ```ignore
+    /// let parent = ctx.schema().find_type_definition("Person");
+    /// ```
+    pub fn schema(&self) -> &ExecSchema {
+        self.ectx.schema()
+    }
+
+    /// Fetches a value of type `T` provided with the request, see [`Data`]
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.ectx.data().get()
+    }
+
+    /// Cancelled when the request is, long running resolvers can use it to
+    /// stop early (their futures are dropped regardless)
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        self.ectx.cancellation_token()
+    }
+
+    /// Like [`data`](Self::data), but panics if the request has no value of
+    /// type `T`. Meant for values the server always provides (e.g. a
+    /// database pool).
+    pub fn data_unchecked<T: Any + Send + Sync>(&self) -> &T {
+        self.ectx.data().get().unwrap_or_else(|| {
+            panic!(
+                "request data of type {} not found",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    /// The fields selected beneath this one, with fragments expanded. When
+    /// the field returns an interface or union, the selections made for each
+    /// possible type are merged.
+    ///
+    /// Lets resolvers avoid fetching data the client didn't ask for:
+    ///
+    /// ```ignore
+    /// let with_pets = ctx.selects("pets");
+    /// ```
+    pub fn selected_fields(&self) -> Vec<SelectedField> {
+        look_ahead(&self.ectx, &self.fields).unwrap_or_else(|err| {
+            tracing::debug!("look ahead error: {}", err);
+            Vec::new()
+        })
+    }
+
+    /// Names of the fields selected directly beneath this one, e.g. the
+    /// columns to `SELECT`. Fragments are flattened without checking their
+    /// type conditions or `@skip`/`@include`, so this may name more fields
+    /// than get resolved. See [`selected_fields`](Self::selected_fields) for
+    /// aliases, arguments and nested selections.
+    pub fn selected_field_names(&self) -> Vec<&str> {
+        fn collect<'a>(
+            ectx: &'a ExecCtx,
+            sel_set: &'a hir::SelectionSet,
+            names: &mut Vec<&'a str>,
+        ) {
+            for sel in sel_set.selection() {
+                match sel {
+                    hir::Selection::Field(field) => {
+                        if !names.contains(&field.name()) {
+                            names.push(field.name());
+                        }
+                    }
+                    hir::Selection::FragmentSpread(spread) => {
+                        if let Some(frag_def) = ectx.fragment(spread.name()) {
+                            collect(ectx, frag_def.selection_set(), names);
+                        }
+                    }
+                    hir::Selection::InlineFragment(frag) => {
+                        collect(ectx, frag.selection_set(), names);
+                    }
+                }
             }
-            Value::Enum { value, .. } => ConstValue::Enum(Name::new(value.src())),
-            Value::Null { .. } => ConstValue::Null,
-        };
+        }
 
-        Ok(CtxArg(const_v.clone()))
+        let mut names = Vec::new();
+        for field in self.fields.iter() {
+            collect(&self.ectx, field.selection_set(), &mut names);
+        }
+        names
+    }
+
+    /// True if a field named `name` is selected directly beneath this one
+    pub fn selects(&self, name: &str) -> bool {
+        self.selected_fields()
+            .iter()
+            .any(|field| field.name() == name)
+    }
+
+    fn resolve_arg(&self, arg: &hir::Argument) -> Result<CtxArg> {
+        let value = resolve_value(self.ectx.variables(), arg.value())?;
+
+        Ok(CtxArg(
+            match self.ectx.argument_type(self.field(), arg.name()) {
+                Some(ty) => coerce_input(&self.ectx, ty, value)?,
+                None => value,
+            },
+        ))
     }
 
     pub fn try_arg<T: TryFrom<CtxArg>>(&self, name: &str) -> Result<T>
@@ -63,17 +148,37 @@ impl Ctx {
         T::Error: Display,
     {
         let arg = self
-            .field
+            .field()
             .arguments()
-            .into_iter()
+            .iter()
             .find(|a| a.name() == name)
             .ok_or_else(|| anyhow!("argument not found: {}", name))?;
 
-        let arg_const_v = self.resolve_vars(arg.value())?;
+        let arg_const_v = self.resolve_arg(arg)?;
 
         T::try_from(arg_const_v).map_err(|err| anyhow!("argument conversion error: {}", err))
     }
 
+    /// Deserializes an argument into any serde type, e.g. an input object into
+    /// a struct. Enum values deserialize into Rust enums by variant name and
+    /// input fields the struct doesn't declare are an error:
+    ///
+    /// ```ignore
+    /// let input = ctx.deserialize_arg::<CreatePersonInput>("input")?;
+    /// ```
+    pub fn deserialize_arg<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        let arg = self
+            .field()
+            .arguments()
+            .iter()
+            .find(|a| a.name() == name)
+            .ok_or_else(|| anyhow!("argument not found: {}", name))?;
+
+        let CtxArg(value) = self.resolve_arg(arg)?;
+
+        from_value(value).map_err(|err| anyhow!("argument conversion error: {}", err.at(name)))
+    }
+
     pub fn arg<T: TryFrom<CtxArg>>(&self, name: &str) -> Option<T>
     where
         T::Error: Display,
@@ -81,16 +186,195 @@ impl Ctx {
         match self.try_arg(name) {
             Ok(v) => Some(v),
             Err(err) => {
-                tracing::error!("argument error: {}", err);
+                tracing::debug!("argument error: {}", err);
                 None
             } // _ => None,
         }
     }
+
+    /// Fetches an optional argument. Absent arguments (including ones bound
+    /// to a variable that wasn't provided) and explicit `null`s are `Ok(None)`,
+    /// only a value that can't be converted to `T` is an error.
+    pub fn arg_opt<T: TryFrom<CtxArg>>(&self, name: &str) -> Result<Option<T>>
+    where
+        T::Error: Display,
+    {
+        let Some(arg) = self.field().arguments().iter().find(|a| a.name() == name) else {
+            return Ok(None);
+        };
+
+        if let Value::Variable(var) = arg.value() {
+            if !self.ectx.variables().contains_key(var.name()) {
+                return Ok(None);
+            }
+        }
+
+        match self.resolve_arg(arg)? {
+            CtxArg(ConstValue::Null) => Ok(None),
+            arg_const_v => T::try_from(arg_const_v)
+                .map(Some)
+                .map_err(|err| anyhow!("argument conversion error: {}", err)),
+        }
+    }
+
+    /// Fetches an argument, falling back to `default` when it's absent, `null`
+    /// or can't be converted to `T`:
+    ///
+    /// ```ignore
+    /// let limit = ctx.arg_with_default("limit", 10);
+    /// ```
+    pub fn arg_with_default<T: TryFrom<CtxArg>>(&self, name: &str, default: T) -> T
+    where
+        T::Error: Display,
+    {
+        match self.arg_opt(name) {
+            Ok(v) => v.unwrap_or(default),
+            Err(err) => {
+                tracing::debug!("argument error: {}", err);
+                default
+            }
+        }
+    }
+
+    /// The argument's value as given in the query, with variables
+    /// substituted and coerced to the argument's declared type but without
+    /// converting it to a Rust type, e.g. to forward it to another service.
+    /// `None` if the argument is absent (including when it's bound to a
+    /// variable that wasn't provided).
+    pub fn raw_arg(&self, name: &str) -> Option<ConstValue> {
+        let arg = self.field().arguments().iter().find(|a| a.name() == name)?;
+
+        if let Value::Variable(var) = arg.value() {
+            if !self.ectx.variables().contains_key(var.name()) {
+                return None;
+            }
+        }
+
+        match self.resolve_arg(arg) {
+            Ok(CtxArg(value)) => Some(value),
+            Err(err) => {
+                tracing::debug!("argument error: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Fetches an argument of a directive used on this field in the query,
+    /// e.g. `length` in `name @truncate(length: 10)`. `Ok(None)` if the
+    /// directive or the argument is absent.
+    pub fn directive_arg<T: TryFrom<CtxArg>>(
+        &self,
+        directive: &str,
+        name: &str,
+    ) -> Result<Option<T>>
+    where
+        T::Error: Display,
+    {
+        let value = self
+            .field()
+            .directives()
+            .iter()
+            .find(|d| d.name() == directive)
+            .and_then(|d| d.argument_by_name(name));
+
+        match value {
+            Some(value) => T::try_from(CtxArg(resolve_value(self.ectx.variables(), value)?))
+                .map(Some)
+                .map_err(|err| anyhow!("directive argument conversion error: {}", err)),
+            None => Ok(None),
+        }
+    }
+}
+
+//FIXME this is probably wrong and also would probably be easier to do
+// in an upstream phase that eagerly resolves all the variables first
+//nb: a custom scalar accepts any literal, so object keys and enum values are
+//only interned by coerce_input once they're checked against their type
+pub(crate) fn resolve_value(
+    variables: &HashMap<String, ConstValue>,
+    value: &Value,
+) -> Result<ConstValue> {
+    Ok(match value {
+        Value::Variable(var) => variables
+            .get(var.name())
+            .ok_or_else(|| anyhow!("undefined variable: {}", var.name()))?
+            .clone(),
+        Value::Object { value, .. } => ConstValue::Object(
+            value
+                .iter()
+                .map(|(k, v)| Ok((Name::new(k.src()), resolve_value(variables, v)?)))
+                .collect::<Result<IndexMap<_, _>>>()?,
+        ),
+        Value::List { value, .. } => ConstValue::List(
+            value
+                .iter()
+                .map(|v| resolve_value(variables, v))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        Value::Boolean { value, .. } => ConstValue::Boolean(*value),
+        Value::String { value, .. } => ConstValue::String(value.clone()),
+        Value::Int { value, .. } => ConstValue::Number(Number::from(
+            value
+                .to_i32_checked()
+                .ok_or_else(|| anyhow!("int value out of range"))?,
+        )),
+        Value::Float { value, .. } => ConstValue::Number(
+            Number::from_f64(value.get()).ok_or_else(|| anyhow!("invalid float value"))?,
+        ),
+        Value::Enum { value, .. } => ConstValue::Enum(Name::new(value.src())),
+        Value::Null { .. } => ConstValue::Null,
+    })
 }
 
 #[repr(transparent)]
 pub struct CtxArg(ConstValue);
 
+impl TryFrom<CtxArg> for ConstValue {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CtxArg) -> std::result::Result<Self, Self::Error> {
+        Ok(value.0)
+    }
+}
+
+impl<T: TryFrom<CtxArg>> TryFrom<CtxArg> for Vec<T>
+where
+    T::Error: Display,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(value: CtxArg) -> std::result::Result<Self, Self::Error> {
+        match value.0 {
+            ConstValue::List(items) => items
+                .into_iter()
+                .map(|item| T::try_from(CtxArg(item)).map_err(|err| anyhow!("{}", err)))
+                .collect(),
+            _ => Err(anyhow!("invalid argument type, expected list")),
+        }
+    }
+}
+
+//nb: integer literals are valid `ID` input, e.g. `person(id: 4)`
+impl TryFrom<CtxArg> for Id {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CtxArg) -> std::result::Result<Self, Self::Error> {
+        match value.0 {
+            ConstValue::String(s) => Ok(Id(s)),
+            ConstValue::Number(n) if n.is_i64() || n.is_u64() => Ok(Id(n.to_string())),
+            _ => Err(anyhow!("invalid argument type, expected id")),
+        }
+    }
+}
+
+impl TryFrom<CtxArg> for Upload {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CtxArg) -> std::result::Result<Self, Self::Error> {
+        Upload::try_from(value.0)
+    }
+}
+
 impl TryFrom<CtxArg> for String {
     type Error = anyhow::Error;
 
@@ -106,13 +390,19 @@ impl TryFrom<CtxArg> for i32 {
     type Error = anyhow::Error;
 
     fn try_from(value: CtxArg) -> std::result::Result<Self, Self::Error> {
-        match value.0 {
-            ConstValue::Number(num) if num.is_i64() => {
-                let inum = num.as_i64().unwrap();
-                Ok(inum.try_into()?)
-            }
-            _ => Err(anyhow!("invalid argument type, expected integer")),
-        }
+        let inum = i64::try_from(value)?;
+        Ok(inum.try_into()?)
+    }
+}
+
+impl TryFrom<CtxArg> for i64 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CtxArg) -> std::result::Result<Self, Self::Error> {
+        value
+            .0
+            .coerce_to_int()
+            .ok_or_else(|| anyhow!("invalid argument type, expected integer"))
     }
 }
 
@@ -120,13 +410,10 @@ impl TryFrom<CtxArg> for f64 {
     type Error = anyhow::Error;
 
     fn try_from(value: CtxArg) -> std::result::Result<Self, Self::Error> {
-        match value.0 {
-            ConstValue::Number(n) if n.is_f64() => {
-                let num_f = n.as_f64().unwrap();
-                Ok(num_f)
-            }
-            _ => Err(anyhow!("invalid argument type, expected float")),
-        }
+        value
+            .0
+            .coerce_to_float()
+            .ok_or_else(|| anyhow!("invalid argument type, expected float"))
     }
 }
 
@@ -150,12 +437,65 @@ pub trait ObjectResolver: Send + Sync {
 
     /// Resolves the value of the specified field
     async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved>;
+
+    /// Called by the executor to resolve each field. Calls
+    /// [`resolve_field`](Self::resolve_field), resolving to `null` (with a
+    /// warning logged) when it returns an [`UnknownField`] error.
+    ///
+    /// Any other error still fails the field, so resolvers choose per field:
+    /// strict errors are the right default, they surface typos and missing
+    /// resolvers immediately. `UnknownField` suits resolvers that may lag
+    /// behind the schema, e.g. during a rolling deploy or behind a gateway
+    /// whose schema is updated separately. A non-null field that resolves to
+    /// `null` still fails.
+    async fn resolve_field_or_null(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+        match self.resolve_field(ctx, name).await {
+            Err(err) if err.is::<UnknownField>() => {
+                tracing::warn!(
+                    field = name,
+                    "resolver doesn't know field, resolving to null"
+                );
+                Ok(Resolved::null())
+            }
+            resolved => resolved,
+        }
+    }
+}
+
+/// Events of a subscription, each one is a value of the subscribed field
+pub type EventStream = BoxStream<'static, Result<Resolved>>;
+
+/// Resolves the root fields of subscription operations, see
+/// [`Executor::subscribe`](crate::Executor::subscribe)
+///
+/// ```ignore
+/// #[async_trait]
+/// impl SubscriptionResolver for SubscriptionRoot {
+///     async fn subscribe(&self, ctx: &Ctx, name: &str) -> Result<EventStream> {
+///         match name {
+///             "messageAdded" => {
+///                 let room = ctx.try_arg::<String>("room")?;
+///                 let messages = self.rooms.listen(&room).await?;
+///                 Ok(messages.map(|msg| Ok(Resolved::object(msg))).boxed())
+///             }
+///             _ => Err(anyhow!("unknown subscription field {}", name)),
+///         }
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait SubscriptionResolver: Send + Sync {
+    /// Creates the stream of events for the specified root field. The
+    /// operation's selection set is executed against each event, an `Err`
+    /// event becomes a field error in that event's response.
+    async fn subscribe(&self, ctx: &Ctx, name: &str) -> Result<EventStream>;
 }
 
 pub enum Resolved {
     Value(ConstValue),
     Object(Box<dyn ObjectResolver>),
     Array(Vec<Resolved>),
+    Lazy(Box<dyn FnOnce() -> Resolved + Send>),
 }
 
 impl Resolved {
@@ -189,6 +529,110 @@ impl Resolved {
             None => Self::null(),
         }
     }
+
+    /// Resolves to an `Int`, see [`ConstValue::int`]
+    pub fn int(v: i64) -> Self {
+        Self::Value(ConstValue::int(v))
+    }
+
+    /// Resolves to a `Float`. GraphQL can't represent NaN or infinite values
+    /// so they're an error, which fails the field rather than quietly
+    /// becoming `null` like [`ConstValue`]'s `From<f64>` impl.
+    pub fn float(v: f64) -> Result<Self> {
+        ConstValue::float(v)
+            .map(Self::Value)
+            .ok_or_else(|| anyhow!("Float cannot represent value: {}", v))
+    }
+
+    /// Builds a list value from anything convertible into `Resolved`, e.g.
+    /// `Resolved::array(rows.into_iter().map(PersonResolver::new))`
+    pub fn array<R: Into<Resolved>>(items: impl IntoIterator<Item = R>) -> Self {
+        Self::Array(items.into_iter().map(Into::into).collect())
+    }
+
+    /// Resolves to JSON data as-is, e.g. a document fetched from a database or
+    /// an upstream API. See [`ConstValue`]'s `From<serde_json::Value>` impl.
+    pub fn from_json(json: serde_json::Value) -> Self {
+        Self::Value(json.into())
+    }
+
+    /// Resolves to any serde serializable value (e.g. a database row or an API
+    /// response) converted with [`ConstValue::from_serialize`]. Object-typed
+    /// fields are completed against their selection set like any other
+    /// object, so keys that weren't selected are left out of the response.
+    pub fn from_serialize<T: Serialize + ?Sized>(value: &T) -> Result<Self> {
+        Ok(Self::Value(ConstValue::from_serialize(value)?))
+    }
+
+    /// Defers building the value until the executor completes the field.
+    /// Useful when building it is expensive (e.g. cloning a large structure)
+    /// and it's returned as part of something that may not be selected, like
+    /// an element of a list or the field of a [`Resolved::from_json`] object.
+    pub fn lazy(f: impl FnOnce() -> Resolved + Send + 'static) -> Self {
+        Self::Lazy(Box::new(f))
+    }
+
+    //nb: a lazy value can build another lazy value
+    pub(crate) fn force(self) -> Self {
+        let mut resolved = self;
+        while let Self::Lazy(f) = resolved {
+            resolved = f();
+        }
+        resolved
+    }
+
+    /// Transforms the value if this is a [`Resolved::Value`], objects and
+    /// arrays are passed through unchanged
+    pub fn map_value(self, f: impl FnOnce(ConstValue) -> ConstValue) -> Self {
+        match self {
+            Self::Value(v) => Self::Value(f(v)),
+            other => other,
+        }
+    }
+
+    /// Fallible version of [`map_value`](Self::map_value)
+    pub fn and_then_value(self, f: impl FnOnce(ConstValue) -> Result<ConstValue>) -> Result<Self> {
+        match self {
+            Self::Value(v) => Ok(Self::Value(f(v)?)),
+            other => Ok(other),
+        }
+    }
+}
+
+/// Resolves the fields of an object value (e.g. one from
+/// [`Resolved::from_json`]) returned for an object, interface or union typed
+/// field. The concrete type of an abstract one is read from its `__typename`
+/// key.
+pub(crate) struct ValueObject(pub(crate) IndexMap<Name, ConstValue>);
+
+#[async_trait]
+impl ObjectResolver for ValueObject {
+    async fn resolve_type_name(&self) -> Result<Option<&str>> {
+        match self.0.get("__typename") {
+            Some(ConstValue::String(name)) => Ok(Some(name.as_str())),
+            _ => Ok(None),
+        }
+    }
+
+    async fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+        Ok(self
+            .0
+            .get(name)
+            .cloned()
+            .map_or_else(Resolved::null, Resolved::Value))
+    }
+}
+
+impl FromIterator<Resolved> for Resolved {
+    fn from_iter<I: IntoIterator<Item = Resolved>>(iter: I) -> Self {
+        Self::Array(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<ConstValue> for Resolved {
+    fn from_iter<I: IntoIterator<Item = ConstValue>>(iter: I) -> Self {
+        Self::Array(iter.into_iter().map(Resolved::Value).collect())
+    }
 }
 
 impl From<ConstValue> for Resolved {
@@ -218,7 +662,86 @@ impl<R: Into<Resolved>> From<Vec<R>> for Resolved {
 
 #[async_trait]
 impl<T: ObjectResolver> ObjectResolver for Arc<T> {
+    async fn resolve_type_name(&self) -> Result<Option<&str>> {
+        T::resolve_type_name(self).await
+    }
+
     async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
         T::resolve_field(&self, ctx, name).await
     }
+
+    async fn resolve_field_or_null(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+        T::resolve_field_or_null(&self, ctx, name).await
+    }
+}
+
+/// An [`ObjectResolver`] that never needs to wait, e.g. one returning
+/// constants or data it already holds. Every `SyncObjectResolver` is an
+/// `ObjectResolver`, so it can be handed to the executor or returned from
+/// [`Resolved::object`] without `#[async_trait]`:
+///
+/// ```ignore
+/// struct PersonResolver;
+///
+/// impl SyncObjectResolver for PersonResolver {
+///     fn resolve_field(&self, _ctx: &Ctx, name: &str) -> Result<Resolved> {
+///         match name {
+///             "firstName" => Ok(Resolved::string("Zack")),
+///             _ => Err(anyhow!("invalid field: {}", name)),
+///         }
+///     }
+/// }
+/// ```
+pub trait SyncObjectResolver: Send + Sync {
+    /// Resolves the concrete type of this if it's a polymorphic type
+    fn resolve_type_name(&self) -> Result<Option<&str>> {
+        Ok(None)
+    }
+
+    /// Resolves the value of the specified field
+    fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved>;
+}
+
+#[async_trait]
+impl<T: SyncObjectResolver> ObjectResolver for T {
+    async fn resolve_type_name(&self) -> Result<Option<&str>> {
+        SyncObjectResolver::resolve_type_name(self)
+    }
+
+    async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+        SyncObjectResolver::resolve_field(self, ctx, name)
+    }
 }
+
+/// Forwards to the type-erased resolver behind a smart pointer, so dynamically
+/// dispatched resolvers (e.g. ones registered by plugins) can be handed
+/// directly to the executor or returned from [`Resolved::object`].
+macro_rules! dyn_object_resolver_impl {
+    ($($ty:ty),*) => {
+        $(
+            #[async_trait]
+            impl ObjectResolver for $ty {
+                async fn resolve_type_name(&self) -> Result<Option<&str>> {
+                    (**self).resolve_type_name().await
+                }
+
+                async fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+                    (**self).resolve_field(ctx, name).await
+                }
+
+                async fn resolve_field_or_null(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+                    (**self).resolve_field_or_null(ctx, name).await
+                }
+            }
+        )*
+    };
+}
+
+//nb: `dyn ObjectResolver` is already Send + Sync by its supertraits, but it's
+//a distinct type from `dyn ObjectResolver + Send + Sync` so both need impls
+dyn_object_resolver_impl!(
+    Arc<dyn ObjectResolver>,
+    Arc<dyn ObjectResolver + Send + Sync>,
+    Box<dyn ObjectResolver>,
+    Box<dyn ObjectResolver + Send + Sync>
+);
@@ -0,0 +1,157 @@
+use indexmap::IndexMap;
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use crate::{
+    error::{GraphQLError, PathSegment},
+    value::{ConstValue, Name},
+};
+
+/// The result of executing a [`Request`](crate::Request)
+///
+/// Serializes into the standard GraphQL response shape, so HTTP layers can
+/// return it as-is:
+///
+/// ```ignore
+/// let response = executor.execute(request, QueryResolver).await;
+/// Json(response)
+/// ```
+///
+/// When resolvers fail, `data` holds whatever could still be resolved (failed
+/// fields are `null`) and `errors` describes each failure. Executing never
+/// fails outright: if the request can't be executed at all (or execution
+/// itself crashes) `data` is `null` and `errors` says why.
+///
+/// Each error is serialized in the spec's format, with the `locations`,
+/// `path` and `extensions` it has. Errors are serialized ahead of `data`, as
+/// the spec suggests, so they stand out.
+///
+/// [Reference](https://spec.graphql.org/October2021/#sec-Response-Format)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Response {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<GraphQLError>,
+    pub data: ConstValue,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub extensions: IndexMap<Name, ConstValue>,
+}
+
+impl Response {
+    pub fn new(data: ConstValue) -> Self {
+        Self {
+            data,
+            errors: Vec::new(),
+            extensions: IndexMap::new(),
+        }
+    }
+
+    /// A response for a request that failed before execution began (e.g. a
+    /// validation error), which has no data
+    pub fn from_request_error(err: GraphQLError) -> Self {
+        Self {
+            data: ConstValue::Null,
+            errors: vec![err],
+            extensions: IndexMap::new(),
+        }
+    }
+
+    /// True if the request executed without any errors
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the data if there were no errors, otherwise the first error
+    pub fn into_result(self) -> Result<ConstValue, GraphQLError> {
+        match self.errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(self.data),
+        }
+    }
+
+    /// Attempt to convert the response into JSON
+    ///
+    /// # Errors
+    ///
+    /// Fails if the data can't be serialized (see [`ConstValue`] docs).
+    pub fn into_json(self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(self)
+    }
+
+    /// Writes the response as JSON straight to `writer`, without building a
+    /// `serde_json::Value` of the whole response first, see
+    /// [`ConstValue::write_json`]
+    pub fn write_json<W: std::io::Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, self)
+    }
+}
+
+/// Responses to a [`BatchRequest`](crate::BatchRequest), mirroring its shape
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum BatchResponse {
+    Single(Response),
+    Batch(Vec<Response>),
+}
+
+/// The spec's name for the result of executing a request, see [`Response`]
+pub type ExecutionResult = Response;
+
+/// One payload of an incrementally delivered response, see
+/// [`Executor::run_incremental`](crate::Executor::run_incremental)
+///
+/// The first payload has the initial `data` (with streamed lists holding only
+/// their initial items and deferred fragments left out), later ones carry
+/// `incremental` results. `has_next`
+/// is false on the last payload.
+///
+/// [Reference](https://github.com/graphql/graphql-spec/pull/742)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncrementalResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<ConstValue>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<GraphQLError>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub incremental: Vec<IncrementalResult>,
+    pub has_next: bool,
+}
+
+/// Part of a response delivered after the initial payload: streamed list
+/// items or the fields of a deferred fragment
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncrementalResult {
+    /// Streamed list items, `None` if an item failed and it's non-null or
+    /// this is a deferred fragment
+    pub items: Option<Vec<ConstValue>>,
+    /// Fields of a deferred fragment
+    pub data: Option<ConstValue>,
+    /// Path of the first item in `items`, or of the object `data` belongs to
+    pub path: Vec<PathSegment>,
+    pub label: Option<String>,
+    pub errors: Vec<GraphQLError>,
+}
+
+//nb: a streamed result always has `items`, `null` when its item failed, so
+//the two kinds of result can't just skip whichever is `None`
+impl Serialize for IncrementalResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        match &self.data {
+            Some(data) => map.serialize_entry("data", data)?,
+            None => map.serialize_entry("items", &self.items)?,
+        }
+
+        map.serialize_entry("path", &self.path)?;
+
+        if let Some(label) = &self.label {
+            map.serialize_entry("label", label)?;
+        }
+
+        if !self.errors.is_empty() {
+            map.serialize_entry("errors", &self.errors)?;
+        }
+
+        map.end()
+    }
+}
@@ -0,0 +1,275 @@
+//! Hooks for custom scalar types declared in the schema
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::value::ConstValue;
+
+/// Converts values of a custom scalar (e.g. `DateTime`, `UUID`) between the
+/// representation clients use and the one resolvers use. Registered by
+/// scalar name with [`ExecutorBuilder::scalar`](crate::ExecutorBuilder::scalar).
+///
+/// Closures taking and returning a [`ConstValue`] implement this trait (as
+/// `serialize`, input values are left as-is):
+///
+/// ```ignore
+/// let executor = Executor::builder()
+///     .type_system(SCHEMA)
+///     .scalar("Email", |v: ConstValue| match v {
+///         ConstValue::String(s) => Ok(s.to_lowercase().into()),
+///         v => Err(anyhow!("invalid email: {}", v)),
+///     })
+///     .build()?;
+/// ```
+pub trait ScalarCodec: Send + Sync {
+    /// Called with each non-null value provided for an argument (or input
+    /// field) of this scalar type, whether inline or through a variable,
+    /// before resolvers see it. An error fails the field.
+    fn parse(&self, value: ConstValue) -> Result<ConstValue> {
+        Ok(value)
+    }
+
+    /// Called with each non-null value a resolver returns for a field of this
+    /// scalar type, an error fails the field
+    fn serialize(&self, value: ConstValue) -> Result<ConstValue>;
+}
+
+impl<F> ScalarCodec for F
+where
+    F: Fn(ConstValue) -> Result<ConstValue> + Send + Sync,
+{
+    fn serialize(&self, value: ConstValue) -> Result<ConstValue> {
+        self(value)
+    }
+}
+
+/// Check registered with
+/// [`ExecutorBuilder::validate_scalar`](crate::ExecutorBuilder::validate_scalar)
+pub(crate) type ScalarValidator = Arc<dyn Fn(&ConstValue) -> Result<()> + Send + Sync>;
+
+/// Runs `validator` on a value of the scalar `name`, naming the scalar in the
+/// error
+pub(crate) fn validate_scalar(
+    name: &str,
+    validator: &ScalarValidator,
+    value: &ConstValue,
+) -> Result<()> {
+    validator(value).map_err(|err| anyhow!("invalid {}: {}", name, err))
+}
+
+/// Codec for a `DateTime` scalar holding RFC 3339 timestamps, e.g.
+/// `2017-07-28T14:20:32.106Z`
+///
+/// ```ignore
+/// // scalar DateTime
+/// let executor = Executor::builder()
+///     .type_system(SCHEMA)
+///     .scalar("DateTime", DateTimeCodec)
+///     .build()?;
+/// ```
+///
+/// Inputs must be RFC 3339 strings. Resolvers can return either a string or
+/// an integer number of seconds since the Unix epoch, which is written as a
+/// UTC timestamp.
+pub struct DateTimeCodec;
+
+impl ScalarCodec for DateTimeCodec {
+    fn parse(&self, value: ConstValue) -> Result<ConstValue> {
+        match value {
+            ConstValue::String(s) if is_rfc3339(&s) => Ok(ConstValue::String(s)),
+            value => Err(anyhow!("invalid DateTime: {}", value)),
+        }
+    }
+
+    fn serialize(&self, value: ConstValue) -> Result<ConstValue> {
+        match value {
+            ConstValue::String(s) if is_rfc3339(&s) => Ok(ConstValue::String(s)),
+            ConstValue::Number(n) => n
+                .as_u64()
+                .map(|secs| ConstValue::String(rfc3339(UNIX_EPOCH + Duration::from_secs(secs))))
+                .ok_or_else(|| anyhow!("invalid DateTime: {}", n)),
+            value => Err(anyhow!("invalid DateTime: {}", value)),
+        }
+    }
+}
+
+/// Formats a UTC timestamp like `2017-07-28T14:20:32.106Z`
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date, see
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// True for an RFC 3339 `date-time`, e.g. `1985-04-12T23:20:50.52+01:00`
+fn is_rfc3339(value: &str) -> bool {
+    let digits = |from: usize, len: usize| -> Option<u32> {
+        let part = value.get(from..from + len)?;
+        part.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| part.parse().ok())?
+    };
+    let separator = |at: usize, allowed: &[u8]| {
+        value
+            .as_bytes()
+            .get(at)
+            .is_some_and(|b| allowed.contains(b))
+    };
+
+    let (Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second)) = (
+        digits(0, 4),
+        digits(5, 2),
+        digits(8, 2),
+        digits(11, 2),
+        digits(14, 2),
+        digits(17, 2),
+    ) else {
+        return false;
+    };
+
+    let separators = separator(4, b"-")
+        && separator(7, b"-")
+        && separator(10, b"Tt")
+        && separator(13, b":")
+        && separator(16, b":");
+
+    //nb: a second of 60 is a leap second
+    if !separators
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return false;
+    }
+
+    let mut offset = &value[19..];
+    if let Some(fraction) = offset.strip_prefix('.') {
+        let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return false;
+        }
+        offset = &fraction[len..];
+    }
+
+    match offset.as_bytes() {
+        [b'Z' | b'z'] => true,
+        [b'+' | b'-', h1, h2, b':', m1, m2] => {
+            let offset_digits = [h1, h2, m1, m2];
+            offset_digits.iter().all(|b| b.is_ascii_digit())
+                && (h1 - b'0') * 10 + (h2 - b'0') <= 23
+                && (m1 - b'0') * 10 + (m2 - b'0') <= 59
+        }
+        _ => false,
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{is_rfc3339, rfc3339, DateTimeCodec, ScalarCodec};
+    use crate::value::ConstValue;
+
+    #[test]
+    fn formats_timestamps_as_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_millis(1_501_251_632_106)),
+            "2017-07-28T14:20:32.106Z"
+        );
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn validates_rfc3339_timestamps() {
+        for valid in [
+            "1985-04-12T23:20:50.52Z",
+            "1996-12-19T16:39:57-08:00",
+            "1990-12-31T23:59:60Z",
+            "2000-02-29t00:00:00+00:00",
+        ] {
+            assert!(is_rfc3339(valid), "{} should be valid", valid);
+        }
+
+        for invalid in [
+            "",
+            "1985-04-12",
+            "1985-04-12T23:20:50",
+            "1985-04-12 23:20:50Z",
+            "1985-13-12T23:20:50Z",
+            "1900-02-29T00:00:00Z",
+            "1985-04-12T24:00:00Z",
+            "1985-04-12T23:20:50.Z",
+            "1985-04-12T23:20:50+0100",
+            "1985-04-12T23:20:50Zjunk",
+        ] {
+            assert!(!is_rfc3339(invalid), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn date_time_codec() {
+        let codec = DateTimeCodec;
+
+        assert_eq!(
+            codec.serialize(ConstValue::from(1_501_251_632)).unwrap(),
+            ConstValue::from("2017-07-28T14:20:32.000Z")
+        );
+        assert_eq!(
+            codec
+                .parse(ConstValue::from("2017-07-28T14:20:32Z"))
+                .unwrap(),
+            ConstValue::from("2017-07-28T14:20:32Z")
+        );
+        assert!(codec.parse(ConstValue::from("yesterday")).is_err());
+        assert!(codec.parse(ConstValue::from(1_501_251_632)).is_err());
+        assert!(codec.serialize(ConstValue::from(-1)).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Prints a type system back out as SDL, see [`Executor::sdl`](crate::Executor::sdl)
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+};
+
+use anyhow::Result;
+use apollo_compiler::hir::{self, TypeDefinition, TypeSystem};
+
+use crate::resolver::resolve_value;
+
+const BUILT_IN_DIRECTIVES: [&str; 4] = ["skip", "include", "deprecated", "specifiedBy"];
+
+/// Prints every definition in `ts` that isn't built in (the spec's scalars
+/// and directives, and the introspection types).
+///
+/// The schema definition comes first, then directive definitions and then
+/// types, each sorted by name so the output is the same no matter what order
+/// the schema was written (or merged) in.
+pub(crate) fn print_type_system(ts: &TypeSystem) -> String {
+    let mut sdl = String::new();
+    write_type_system(&mut sdl, ts).expect("writing to a String can't fail");
+    sdl
+}
+
+fn write_type_system(sdl: &mut String, ts: &TypeSystem) -> fmt::Result {
+    print_schema_definition(sdl, &ts.definitions.schema)?;
+
+    let mut directives = ts
+        .definitions
+        .directives
+        .values()
+        .filter(|d| !BUILT_IN_DIRECTIVES.contains(&d.name()))
+        .collect::<Vec<_>>();
+    directives.sort_by_key(|d| d.name());
+
+    for directive in directives {
+        sdl.push('\n');
+        print_directive_definition(sdl, directive)?;
+    }
+
+    let mut types = ts
+        .type_definitions_by_name
+        .values()
+        .filter(|ty| !is_built_in_type(ty))
+        .collect::<Vec<_>>();
+    types.sort_by_key(|ty| ty.name());
+
+    for ty in types {
+        sdl.push('\n');
+        print_type_definition(sdl, ty)?;
+    }
+
+    Ok(())
+}
+
+/// Prints a type reference, e.g. `[Person!]!`
+pub(crate) fn type_ref(ty: &hir::Type) -> String {
+    match ty {
+        hir::Type::NonNull { ty, .. } => format!("{}!", type_ref(ty)),
+        hir::Type::List { ty, .. } => format!("[{}]", type_ref(ty)),
+        hir::Type::Named { name, .. } => name.clone(),
+    }
+}
+
+/// Prints a constant value as a GraphQL literal, e.g. `"name"`, `10` or
+/// `{limit: 10}`
+pub(crate) fn value_literal(value: &hir::Value) -> Result<String> {
+    let value = resolve_value(&HashMap::new(), value)?;
+    Ok(value.into_value().to_string())
+}
+
+fn is_built_in_type(ty: &TypeDefinition) -> bool {
+    match ty {
+        TypeDefinition::ScalarTypeDefinition(scalar) => scalar.is_built_in(),
+        _ => ty.name().starts_with("__"),
+    }
+}
+
+fn print_schema_definition(sdl: &mut String, schema: &hir::SchemaDefinition) -> fmt::Result {
+    print_description(sdl, schema.description(), "")?;
+    sdl.push_str("schema");
+    print_directives(sdl, schema.directives())?;
+    sdl.push_str(" {\n");
+
+    let roots = [
+        ("query", schema.query()),
+        ("mutation", schema.mutation()),
+        ("subscription", schema.subscription()),
+    ];
+
+    for (operation, root) in roots {
+        if let Some(root) = root {
+            writeln!(sdl, "  {}: {}", operation, root)?;
+        }
+    }
+
+    sdl.push_str("}\n");
+
+    Ok(())
+}
+
+fn print_directive_definition(
+    sdl: &mut String,
+    directive: &hir::DirectiveDefinition,
+) -> fmt::Result {
+    print_description(sdl, directive.description(), "")?;
+    write!(sdl, "directive @{}", directive.name())?;
+    print_arguments_definition(sdl, directive.arguments().input_values(), "")?;
+
+    if directive.repeatable() {
+        sdl.push_str(" repeatable");
+    }
+
+    let locations = directive
+        .directive_locations()
+        .iter()
+        .map(|loc| loc.name())
+        .collect::<Vec<_>>();
+    writeln!(sdl, " on {}", locations.join(" | "))
+}
+
+fn print_type_definition(sdl: &mut String, ty: &TypeDefinition) -> fmt::Result {
+    print_description(sdl, ty.description(), "")?;
+
+    match ty {
+        TypeDefinition::ScalarTypeDefinition(scalar) => {
+            write!(sdl, "scalar {}", scalar.name())?;
+            print_directives(sdl, scalar.directives())?;
+            sdl.push('\n');
+        }
+        TypeDefinition::ObjectTypeDefinition(object) => {
+            write!(sdl, "type {}", object.name())?;
+            print_implements(sdl, object.implements_interfaces())?;
+            print_directives(sdl, object.directives())?;
+            print_fields(sdl, object.fields())?;
+        }
+        TypeDefinition::InterfaceTypeDefinition(interface) => {
+            write!(sdl, "interface {}", interface.name())?;
+            print_implements(sdl, interface.implements_interfaces())?;
+            print_directives(sdl, interface.directives())?;
+            print_fields(sdl, interface.fields())?;
+        }
+        TypeDefinition::UnionTypeDefinition(union_type) => {
+            write!(sdl, "union {}", union_type.name())?;
+            print_directives(sdl, union_type.directives())?;
+
+            let members = union_type.members().map(|m| m.name()).collect::<Vec<_>>();
+            if !members.is_empty() {
+                write!(sdl, " = {}", members.join(" | "))?;
+            }
+            sdl.push('\n');
+        }
+        TypeDefinition::EnumTypeDefinition(enum_type) => {
+            write!(sdl, "enum {}", enum_type.name())?;
+            print_directives(sdl, enum_type.directives())?;
+            sdl.push_str(" {\n");
+
+            for value in enum_type.values() {
+                print_description(sdl, value.description(), "  ")?;
+                write!(sdl, "  {}", value.enum_value())?;
+                print_directives(sdl, value.directives().iter())?;
+                sdl.push('\n');
+            }
+
+            sdl.push_str("}\n");
+        }
+        TypeDefinition::InputObjectTypeDefinition(input_object) => {
+            write!(sdl, "input {}", input_object.name())?;
+            print_directives(sdl, input_object.directives())?;
+            sdl.push_str(" {\n");
+
+            for field in input_object.fields() {
+                print_input_value(sdl, field, "  ")?;
+                sdl.push('\n');
+            }
+
+            sdl.push_str("}\n");
+        }
+    }
+
+    Ok(())
+}
+
+fn print_implements<'a>(
+    sdl: &mut String,
+    interfaces: impl Iterator<Item = &'a hir::ImplementsInterface>,
+) -> fmt::Result {
+    let interfaces = interfaces.map(|i| i.interface()).collect::<Vec<_>>();
+
+    if !interfaces.is_empty() {
+        write!(sdl, " implements {}", interfaces.join(" & "))?;
+    }
+
+    Ok(())
+}
+
+fn print_fields<'a>(
+    sdl: &mut String,
+    fields: impl Iterator<Item = &'a hir::FieldDefinition>,
+) -> fmt::Result {
+    sdl.push_str(" {\n");
+
+    for field in fields {
+        print_description(sdl, field.description(), "  ")?;
+        write!(sdl, "  {}", field.name())?;
+        print_arguments_definition(sdl, field.arguments().input_values(), "  ")?;
+        write!(sdl, ": {}", type_ref(field.ty()))?;
+        print_directives(sdl, field.directives().iter())?;
+        sdl.push('\n');
+    }
+
+    sdl.push_str("}\n");
+
+    Ok(())
+}
+
+/// Prints arguments inline, or one per line if any of them is described
+fn print_arguments_definition(
+    sdl: &mut String,
+    arguments: &[hir::InputValueDefinition],
+    indent: &str,
+) -> fmt::Result {
+    if arguments.is_empty() {
+        return Ok(());
+    }
+
+    if arguments.iter().all(|arg| arg.description().is_none()) {
+        sdl.push('(');
+        for (ix, arg) in arguments.iter().enumerate() {
+            if ix > 0 {
+                sdl.push_str(", ");
+            }
+            print_input_value(sdl, arg, "")?;
+        }
+        sdl.push(')');
+        return Ok(());
+    }
+
+    let arg_indent = format!("{}  ", indent);
+    sdl.push_str("(\n");
+    for arg in arguments {
+        print_input_value(sdl, arg, &arg_indent)?;
+        sdl.push('\n');
+    }
+    write!(sdl, "{})", indent)
+}
+
+fn print_input_value(
+    sdl: &mut String,
+    value: &hir::InputValueDefinition,
+    indent: &str,
+) -> fmt::Result {
+    print_description(sdl, value.description(), indent)?;
+    write!(sdl, "{}{}: {}", indent, value.name(), type_ref(value.ty()))?;
+
+    //nb: type system validation already rejected defaults that aren't
+    //constant, so there's nothing to print for one that fails to resolve
+    if let Some(default) = value.default_value().and_then(|v| value_literal(v).ok()) {
+        write!(sdl, " = {}", default)?;
+    }
+
+    print_directives(sdl, value.directives().iter())
+}
+
+fn print_directives<'a>(
+    sdl: &mut String,
+    directives: impl Iterator<Item = &'a hir::Directive>,
+) -> fmt::Result {
+    for directive in directives {
+        write!(sdl, " @{}", directive.name())?;
+
+        let arguments = directive
+            .arguments()
+            .iter()
+            .filter_map(|arg| {
+                let value = value_literal(arg.value()).ok()?;
+                Some(format!("{}: {}", arg.name(), value))
+            })
+            .collect::<Vec<_>>();
+
+        if !arguments.is_empty() {
+            write!(sdl, "({})", arguments.join(", "))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a description as a block string on the lines before the definition
+fn print_description(sdl: &mut String, description: Option<&str>, indent: &str) -> fmt::Result {
+    let Some(description) = description else {
+        return Ok(());
+    };
+
+    writeln!(sdl, "{}\"\"\"", indent)?;
+    for line in description.replace("\"\"\"", "\\\"\"\"").lines() {
+        if line.is_empty() {
+            sdl.push('\n');
+        } else {
+            writeln!(sdl, "{}{}", indent, line)?;
+        }
+    }
+    writeln!(sdl, "{}\"\"\"", indent)
+}
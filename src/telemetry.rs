@@ -0,0 +1,95 @@
+//! OpenTelemetry spans for operations and fields, enabled with the `otel`
+//! feature.
+//!
+//! Spans are plain `tracing` spans named and attributed after the
+//! OpenTelemetry GraphQL conventions, they're exported by installing a
+//! `tracing-opentelemetry` layer with whichever exporter the server uses.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tracing::{field::Empty, Instrument, Span};
+
+use crate::{
+    middleware::{FieldInfo, Next, OperationInfo, ResolverMiddleware},
+    resolver::{Ctx, Resolved},
+};
+
+/// Middleware that traces each operation, with a child span for every
+/// resolved field
+///
+/// ```ignore
+/// let executor = Executor::builder()
+///     .type_system(SCHEMA)
+///     .middleware(OpenTelemetry)
+///     .build()?;
+/// ```
+///
+/// Operation spans carry `graphql.operation.name`, `graphql.operation.type`
+/// and `graphql.document.hash` (a hash of the request document, for grouping
+/// traces of the same query without recording its text). Field spans carry
+/// `graphql.field.name`, `graphql.field.path`, `graphql.field.parent_type`
+/// and `graphql.field.type`, and have an error status if the field failed.
+pub struct OpenTelemetry;
+
+#[async_trait]
+impl ResolverMiddleware for OpenTelemetry {
+    async fn resolve(&self, ctx: &Ctx, info: &FieldInfo<'_>, next: Next<'_>) -> Result<Resolved> {
+        let path = info
+            .path()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+
+        let span = tracing::info_span!(
+            "graphql.field",
+            otel.name = %path,
+            otel.kind = "internal",
+            otel.status_code = Empty,
+            otel.status_message = Empty,
+            graphql.field.name = info.name(),
+            graphql.field.path = %path,
+            graphql.field.parent_type = info.parent_type(),
+            graphql.field.type = %info.return_type(),
+        );
+
+        let resolved = next.run(ctx).instrument(span.clone()).await;
+
+        if let Err(err) = &resolved {
+            record_error(&span, err);
+        }
+
+        resolved
+    }
+
+    fn operation_span(&self, operation: &OperationInfo<'_>) -> Option<Span> {
+        let name = operation.name().unwrap_or_default();
+
+        Some(tracing::info_span!(
+            "graphql.operation",
+            otel.name = %format!("{} {}", operation.operation_type(), name).trim_end(),
+            otel.kind = "server",
+            graphql.operation.name = name,
+            graphql.operation.type = operation.operation_type(),
+            graphql.document.hash = %document_hash(operation.source()),
+        ))
+    }
+}
+
+fn record_error(span: &Span, err: &anyhow::Error) {
+    span.record("otel.status_code", "ERROR");
+    span.record("otel.status_message", tracing::field::display(err));
+}
+
+//nb: only meant to group traces, it's stable for a given build but not
+//guaranteed to be across compiler versions
+fn document_hash(source: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
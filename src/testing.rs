@@ -0,0 +1,178 @@
+//! Helpers for testing schemas and resolvers, enabled by the `test-utils`
+//! feature
+//!
+//! ```ignore
+//! let executor = TestExecutor::new(SCHEMA);
+//! let result = executor
+//!     .execute("query ($id: ID!) { person(id: $id) { name } }", json!({ "id": 1 }), QueryResolver)
+//!     .await;
+//!
+//! assert_error_count!(result, 0);
+//! assert_data!(result, json!({ "person": { "name": "Ada" } }));
+//! ```
+
+use crate::{ExecutionResult, Executor, ObjectResolver, Request};
+
+/// Wraps an [`Executor`], panicking instead of returning errors so tests
+/// stay short
+pub struct TestExecutor {
+    executor: Executor,
+}
+
+impl TestExecutor {
+    /// Compiles `schema`, panicking if it's invalid
+    #[track_caller]
+    pub fn new(schema: &str) -> Self {
+        match Executor::new(schema) {
+            Ok(executor) => Self { executor },
+            Err(err) => panic!("invalid test schema: {:#}", err),
+        }
+    }
+
+    /// The wrapped executor, for anything not covered here
+    pub fn executor(&self) -> &Executor {
+        &self.executor
+    }
+
+    /// Executes `query` with `variables`, a JSON object (or `null` for none)
+    pub async fn execute<R: ObjectResolver + 'static>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+        resolver: R,
+    ) -> ExecutionResult {
+        let variables = match variables {
+            serde_json::Value::Object(variables) => variables,
+            serde_json::Value::Null => serde_json::Map::new(),
+            other => panic!("test variables must be an object, got {}", other),
+        };
+
+        self.executor
+            .execute(Request::new(query).json_variables(variables), resolver)
+            .await
+    }
+}
+
+impl From<Executor> for TestExecutor {
+    fn from(executor: Executor) -> Self {
+        Self { executor }
+    }
+}
+
+/// Asserts that the data of an [`ExecutionResult`] equals a JSON value,
+/// printing both (and any errors) when it doesn't
+#[macro_export]
+macro_rules! assert_data {
+    ($result:expr, $expected:expr $(,)?) => {
+        $crate::testing::check_data(&$result, $expected)
+    };
+}
+
+/// Asserts that an [`ExecutionResult`] has `n` errors, printing them when it
+/// doesn't
+#[macro_export]
+macro_rules! assert_error_count {
+    ($result:expr, $n:expr $(,)?) => {
+        $crate::testing::check_error_count(&$result, $n)
+    };
+}
+
+pub use crate::{assert_data, assert_error_count};
+
+#[doc(hidden)]
+#[track_caller]
+pub fn check_data(result: &ExecutionResult, expected: serde_json::Value) {
+    let actual = result.data.to_json();
+
+    if actual != expected {
+        panic!(
+            "response data doesn't match\nexpected: {}\n  actual: {}{}",
+            pretty(&expected),
+            pretty(&actual),
+            describe_errors(result)
+        );
+    }
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn check_error_count(result: &ExecutionResult, n: usize) {
+    if result.errors.len() != n {
+        panic!(
+            "expected {} error(s), got {}{}",
+            n,
+            result.errors.len(),
+            describe_errors(result)
+        );
+    }
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+fn describe_errors(result: &ExecutionResult) -> String {
+    result
+        .errors
+        .iter()
+        .map(|err| format!("\n  - {}", err))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::AssertUnwindSafe;
+
+    use super::*;
+    use crate::{Ctx, Resolved, SyncObjectResolver};
+    use anyhow::{anyhow, Result};
+    use serde_json::json;
+
+    struct Greeter;
+
+    impl SyncObjectResolver for Greeter {
+        fn resolve_field(&self, ctx: &Ctx, name: &str) -> Result<Resolved> {
+            match name {
+                "greet" => Ok(Resolved::string(format!(
+                    "hello {}",
+                    ctx.try_arg::<String>("name")?
+                ))),
+                _ => Err(anyhow!("no such field")),
+            }
+        }
+    }
+
+    const SCHEMA: &str = "type Query { greet(name: String!): String broken: String }";
+
+    #[tokio::test]
+    async fn executes_with_json_variables() {
+        let executor = TestExecutor::new(SCHEMA);
+        let result = executor
+            .execute(
+                "query ($name: String!) { greet(name: $name) }",
+                json!({ "name": "ada" }),
+                Greeter,
+            )
+            .await;
+
+        assert_error_count!(result, 0);
+        assert_data!(result, json!({ "greet": "hello ada" }));
+    }
+
+    #[tokio::test]
+    async fn failures_describe_the_errors() {
+        let result = TestExecutor::new(SCHEMA)
+            .execute("{ broken }", json!(null), Greeter)
+            .await;
+
+        assert_data!(result, json!({ "broken": null }));
+
+        let panic = std::panic::catch_unwind(AssertUnwindSafe(|| assert_error_count!(result, 0)))
+            .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert_eq!(
+            message,
+            "expected 0 error(s), got 1\n  - no such field (at broken)"
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//! File uploads following the GraphQL multipart request spec
+//!
+//! https://github.com/jaydenseric/graphql-multipart-request-spec
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    io,
+    sync::Arc,
+};
+
+use crate::{
+    request::{BatchRequest, Request},
+    value::ConstValue,
+};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use futures::{pin_mut, Stream, StreamExt};
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+
+#[cfg(feature = "axum")]
+mod multipart;
+
+#[cfg(feature = "axum")]
+pub use multipart::{MultipartUpload, DEFAULT_MAX_UPLOAD_SIZE};
+
+/// A file uploaded for a variable of the `Upload` scalar. Declare the scalar
+/// in the schema and read it like any other argument:
+///
+/// ```ignore
+/// // scalar Upload
+/// // type Mutation { uploadFile(file: Upload!): Int! }
+/// let upload = ctx.try_arg::<Upload>("file")?;
+/// let mut reader = upload.reader().await?;
+/// ```
+///
+/// Uploads streamed in with [`Upload::from_stream`] are spooled to a
+/// temporary file, deleted once the last clone of the upload is dropped.
+/// Within variables an upload is a [`ConstValue::Upload`], it can't be given
+/// as JSON.
+#[derive(Clone)]
+pub struct Upload {
+    pub filename: String,
+    pub content_type: Option<String>,
+    content: Content,
+    size: u64,
+}
+
+#[derive(Clone)]
+enum Content {
+    Memory(Bytes),
+    File(Arc<NamedTempFile>),
+}
+
+impl Upload {
+    /// An upload held in memory
+    pub fn new(
+        filename: impl Into<String>,
+        content_type: Option<String>,
+        content: impl Into<Bytes>,
+    ) -> Self {
+        let content = content.into();
+
+        Self {
+            filename: filename.into(),
+            content_type,
+            size: content.len() as u64,
+            content: Content::Memory(content),
+        }
+    }
+
+    /// Spools `chunks` to a temporary file, failing with [`UploadTooLarge`]
+    /// as soon as more than `max_size` bytes arrive
+    pub async fn from_stream<S, E>(
+        filename: impl Into<String>,
+        content_type: Option<String>,
+        chunks: S,
+        max_size: u64,
+    ) -> Result<Self>
+    where
+        S: Stream<Item = std::result::Result<Bytes, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let (file, writer) = tokio::task::spawn_blocking(|| {
+            let file = NamedTempFile::new()?;
+            let writer = file.reopen()?;
+            io::Result::Ok((file, writer))
+        })
+        .await??;
+
+        let mut writer = tokio::fs::File::from_std(writer);
+        let mut size = 0u64;
+
+        pin_mut!(chunks);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+
+            size += chunk.len() as u64;
+            if size > max_size {
+                return Err(UploadTooLarge { max_size }.into());
+            }
+
+            writer.write_all(&chunk).await?;
+        }
+
+        //nb: tokio finishes writes in the background, flush waits for them
+        writer.flush().await?;
+
+        Ok(Self {
+            filename: filename.into(),
+            content_type,
+            content: Content::File(Arc::new(file)),
+            size,
+        })
+    }
+
+    /// Size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Reads the file from the start, each reader has its own position
+    pub async fn reader(&self) -> io::Result<Box<dyn AsyncRead + Send + Unpin>> {
+        Ok(match &self.content {
+            Content::Memory(bytes) => Box::new(io::Cursor::new(bytes.clone())),
+            Content::File(file) => Box::new(tokio::fs::File::open(file.path()).await?),
+        })
+    }
+
+    /// Reads the whole file into memory
+    pub async fn bytes(&self) -> io::Result<Bytes> {
+        match &self.content {
+            Content::Memory(bytes) => Ok(bytes.clone()),
+            Content::File(file) => tokio::fs::read(file.path()).await.map(Bytes::from),
+        }
+    }
+}
+
+impl fmt::Debug for Upload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upload")
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+//nb: spooled files are only equal to themselves (or a clone), their
+//content isn't compared
+impl PartialEq for Upload {
+    fn eq(&self, other: &Self) -> bool {
+        let same_content = match (&self.content, &other.content) {
+            (Content::Memory(a), Content::Memory(b)) => a == b,
+            (Content::File(a), Content::File(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        };
+
+        same_content && self.filename == other.filename && self.content_type == other.content_type
+    }
+}
+
+impl Eq for Upload {}
+
+impl From<Upload> for ConstValue {
+    fn from(upload: Upload) -> Self {
+        ConstValue::Upload(upload)
+    }
+}
+
+impl TryFrom<ConstValue> for Upload {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ConstValue) -> Result<Self> {
+        match value {
+            ConstValue::Upload(upload) => Ok(upload),
+            _ => Err(anyhow!("invalid argument type, expected upload")),
+        }
+    }
+}
+
+/// Returned by [`Upload::from_stream`] when a file is larger than allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadTooLarge {
+    pub max_size: u64,
+}
+
+impl Display for UploadTooLarge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "upload exceeds maximum size of {} bytes", self.max_size)
+    }
+}
+
+impl std::error::Error for UploadTooLarge {}
+
+/// Builds the requests of a multipart request from its `operations` and
+/// `map` fields and its file parts, keyed by field name. Each file replaces
+/// the `null` at the paths the map lists for it, e.g. `variables.file` (or
+/// `0.variables.file` when the operations are a batch).
+///
+/// With the `axum` feature [`MultipartUpload`] does this for axum, other
+/// HTTP frameworks parse the multipart body themselves:
+///
+/// ```ignore
+/// let (mut operations, mut map, mut files) = (None, None, HashMap::new());
+///
+/// while let Some(field) = multipart.next_field().await? {
+///     match field.name() {
+///         Some("operations") => operations = Some(field.text().await?),
+///         Some("map") => map = Some(field.text().await?),
+///         Some(name) => {
+///             let name = name.to_owned();
+///             let filename = field.file_name().unwrap_or_default().to_owned();
+///             let content_type = field.content_type().map(ToOwned::to_owned);
+///             let upload = Upload::from_stream(filename, content_type, field, MAX_SIZE).await?;
+///             files.insert(name, upload);
+///         }
+///         None => {}
+///     }
+/// }
+///
+/// let operations = parse_multipart(&operations?, &map?, files)?;
+/// ```
+pub fn parse_multipart(
+    operations: &str,
+    map: &str,
+    mut files: HashMap<String, Upload>,
+) -> Result<BatchRequest> {
+    let operations = serde_json::from_str::<BatchRequest>(operations)
+        .map_err(|err| anyhow!("invalid multipart operations: {}", err))?;
+    let map = serde_json::from_str::<HashMap<String, Vec<String>>>(map)
+        .map_err(|err| anyhow!("invalid multipart map: {}", err))?;
+
+    let (mut requests, batched) = match operations {
+        BatchRequest::Single(request) => (vec![request], false),
+        BatchRequest::Batch(requests) => (requests, true),
+    };
+
+    for (key, paths) in map {
+        let upload = files
+            .remove(&key)
+            .ok_or_else(|| anyhow!("multipart map refers to missing file {}", key))?;
+
+        for path in paths {
+            let mut segments = path.split('.');
+
+            let ix = if batched {
+                segments
+                    .next()
+                    .and_then(|ix| ix.parse::<usize>().ok())
+                    .ok_or_else(|| anyhow!("invalid multipart map path {}", path))?
+            } else {
+                0
+            };
+
+            let request = requests
+                .get_mut(ix)
+                .ok_or_else(|| anyhow!("invalid multipart map path {}", path))?;
+
+            splice(request, segments, upload.clone())
+                .map_err(|err| anyhow!("invalid multipart map path {}: {}", path, err))?;
+        }
+    }
+
+    Ok(if batched {
+        BatchRequest::Batch(requests)
+    } else {
+        BatchRequest::Single(requests.remove(0))
+    })
+}
+
+fn splice<'a>(
+    request: &mut Request,
+    mut segments: impl Iterator<Item = &'a str>,
+    upload: Upload,
+) -> Result<()> {
+    if segments.next() != Some("variables") {
+        return Err(anyhow!("files can only be placed in variables"));
+    }
+
+    let name = segments
+        .next()
+        .ok_or_else(|| anyhow!("missing variable name"))?;
+    let mut target = request
+        .variables
+        .get_mut(name)
+        .ok_or_else(|| anyhow!("unknown variable {}", name))?;
+
+    for segment in segments {
+        target = match target {
+            ConstValue::List(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|ix| items.get_mut(ix)),
+            ConstValue::Object(fields) => fields.get_mut(segment),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("nothing at {}", segment))?;
+    }
+
+    if *target != ConstValue::Null {
+        return Err(anyhow!("files must replace a null"));
+    }
+
+    *target = ConstValue::Upload(upload);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(filename: &str) -> Upload {
+        Upload::new(
+            filename,
+            Some("text/plain".to_owned()),
+            Bytes::from_static(b"hello"),
+        )
+    }
+
+    fn chunks(
+        chunks: &[&'static [u8]],
+    ) -> impl Stream<Item = std::result::Result<Bytes, io::Error>> {
+        futures::stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok(Bytes::from_static(chunk)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn streams_are_spooled_to_a_temp_file() {
+        use tokio::io::AsyncReadExt;
+
+        let upload = Upload::from_stream("a.txt", None, chunks(&[b"hel", b"lo"]), 5)
+            .await
+            .unwrap();
+        assert_eq!(upload.size(), 5);
+
+        let Content::File(file) = &upload.content else {
+            panic!("expected a temp file");
+        };
+        let path = file.path().to_owned();
+
+        //nb: every reader starts from the beginning
+        for _ in 0..2 {
+            let mut content = String::new();
+            let mut reader = upload.reader().await.unwrap();
+            reader.read_to_string(&mut content).await.unwrap();
+            assert_eq!(content, "hello");
+        }
+
+        assert_eq!(upload.bytes().await.unwrap(), "hello");
+
+        let clone = upload.clone();
+        drop(upload);
+        assert!(path.exists());
+        drop(clone);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn streams_over_the_size_limit_are_rejected() {
+        let err = Upload::from_stream("a.txt", None, chunks(&[b"hel", b"lo"]), 4)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<UploadTooLarge>(),
+            Some(&UploadTooLarge { max_size: 4 })
+        );
+        assert_eq!(err.to_string(), "upload exceeds maximum size of 4 bytes");
+    }
+
+    #[test]
+    fn uploads_cant_be_given_as_json() {
+        let value = ConstValue::from(serde_json::json!({
+            "filename": "a.txt",
+            "content": [104, 105],
+        }));
+
+        assert!(Upload::try_from(value).is_err());
+        assert_eq!(
+            ConstValue::from(upload("a.txt")).to_json(),
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn splices_files_into_variables() {
+        let operations = parse_multipart(
+            r#"{ "query": "mutation ($file: Upload!, $files: [Upload!]!) { a }", "variables": { "file": null, "files": [null, null] } }"#,
+            r#"{ "0": ["variables.file"], "1": ["variables.files.0", "variables.files.1"] }"#,
+            HashMap::from([("0".to_owned(), upload("a.txt")), ("1".to_owned(), upload("b.txt"))]),
+        )
+        .unwrap();
+
+        let BatchRequest::Single(request) = operations else {
+            panic!("expected a single operation");
+        };
+
+        assert_eq!(
+            Upload::try_from(request.variables["file"].clone()).unwrap(),
+            upload("a.txt")
+        );
+        assert_eq!(
+            request.variables["files"],
+            ConstValue::List(vec![upload("b.txt").into(), upload("b.txt").into()])
+        );
+    }
+
+    #[test]
+    fn batched_paths_start_with_the_operation_index() {
+        let operations = parse_multipart(
+            r#"[{ "query": "{ a }" }, { "query": "mutation ($file: Upload) { a }", "variables": { "file": null } }]"#,
+            r#"{ "0": ["1.variables.file"] }"#,
+            HashMap::from([("0".to_owned(), upload("a.txt"))]),
+        )
+        .unwrap();
+
+        let BatchRequest::Batch(requests) = operations else {
+            panic!("expected a batch");
+        };
+
+        assert!(requests[0].variables.is_empty());
+        assert_eq!(requests[1].variables["file"], upload("a.txt").into());
+    }
+
+    #[test]
+    fn rejects_missing_files_and_bad_paths() {
+        let operations = r#"{ "query": "{ a }", "variables": { "file": null } }"#;
+
+        let err = parse_multipart(operations, r#"{ "0": ["variables.file"] }"#, HashMap::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "multipart map refers to missing file 0");
+
+        let err = parse_multipart(
+            operations,
+            r#"{ "0": ["query"] }"#,
+            HashMap::from([("0".to_owned(), upload("a.txt"))]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid multipart map path query: files can only be placed in variables"
+        );
+    }
+}
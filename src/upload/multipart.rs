@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use axum::{
+    async_trait,
+    body::{Bytes, HttpBody},
+    extract::{FromRequest, Multipart},
+    http::{Request, StatusCode},
+    BoxError,
+};
+
+use super::{parse_multipart, Upload, UploadTooLarge};
+use crate::BatchRequest;
+
+/// Limit on the combined size of a request's files unless
+/// [`MultipartUpload`] is given another
+pub const DEFAULT_MAX_UPLOAD_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Extracts the operations of a multipart request (see the
+/// [spec](https://github.com/jaydenseric/graphql-multipart-request-spec)),
+/// with each file spooled to a temporary file and placed in the variables
+/// the `map` field lists it under. Requests whose files add up to more than
+/// `MAX_SIZE` bytes are rejected with `413 Payload Too Large`.
+///
+/// ```ignore
+/// const MAX_SIZE: u64 = 100 * 1024 * 1024;
+///
+/// let app = Router::new()
+///     .route("/graphql/upload", post(graphql_upload))
+///     .layer(DefaultBodyLimit::max(MAX_SIZE as usize + 64 * 1024));
+///
+/// async fn graphql_upload(MultipartUpload(batch): MultipartUpload<MAX_SIZE>) -> Json<BatchResponse> {
+///     // execute the batch
+/// }
+/// ```
+///
+/// axum's [`DefaultBodyLimit`](axum::extract::DefaultBodyLimit) applies to
+/// the whole body as well, raise or disable it on the route for limits over
+/// 2MB.
+pub struct MultipartUpload<const MAX_SIZE: u64 = DEFAULT_MAX_UPLOAD_SIZE>(pub BatchRequest);
+
+#[async_trait]
+impl<S, B, const MAX_SIZE: u64> FromRequest<S, B> for MultipartUpload<MAX_SIZE>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let bad_request = |err: &dyn std::fmt::Display| (StatusCode::BAD_REQUEST, err.to_string());
+
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(|e| bad_request(&e))?;
+
+        let (mut operations, mut map, mut files) = (None, None, HashMap::new());
+        let mut total_size = 0u64;
+
+        while let Some(field) = multipart.next_field().await.map_err(|e| bad_request(&e))? {
+            match field.name() {
+                Some("operations") => {
+                    operations = Some(field.text().await.map_err(|e| bad_request(&e))?)
+                }
+                Some("map") => map = Some(field.text().await.map_err(|e| bad_request(&e))?),
+                Some(name) => {
+                    let name = name.to_owned();
+                    let filename = field.file_name().unwrap_or_default().to_owned();
+                    let content_type = field.content_type().map(ToOwned::to_owned);
+
+                    //nb: each file may use whatever the previous ones left
+                    let upload =
+                        Upload::from_stream(filename, content_type, field, MAX_SIZE - total_size)
+                            .await
+                            .map_err(|err| match err.downcast_ref::<UploadTooLarge>() {
+                                Some(_) => (
+                                    StatusCode::PAYLOAD_TOO_LARGE,
+                                    UploadTooLarge { max_size: MAX_SIZE }.to_string(),
+                                ),
+                                None => bad_request(&err),
+                            })?;
+
+                    total_size += upload.size();
+                    files.insert(name, upload);
+                }
+                None => {}
+            }
+        }
+
+        let (Some(operations), Some(map)) = (operations, map) else {
+            return Err(bad_request(&"operations and map fields are required"));
+        };
+
+        let batch = parse_multipart(&operations, &map, files).map_err(|e| bad_request(&e))?;
+
+        Ok(Self(batch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+    use crate::ConstValue;
+
+    fn multipart_request(file: &str) -> Request<Body> {
+        let body = format!(
+            "--BOUNDARY\r\n\
+             Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+             {{ \"query\": \"mutation ($file: Upload!) {{ a }}\", \"variables\": {{ \"file\": null }} }}\r\n\
+             --BOUNDARY\r\n\
+             Content-Disposition: form-data; name=\"map\"\r\n\r\n\
+             {{ \"0\": [\"variables.file\"] }}\r\n\
+             --BOUNDARY\r\n\
+             Content-Disposition: form-data; name=\"0\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             {}\r\n\
+             --BOUNDARY--\r\n",
+            file
+        );
+
+        Request::builder()
+            .method("POST")
+            .header("content-type", "multipart/form-data; boundary=BOUNDARY")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn extracts_operations_with_their_files() {
+        let MultipartUpload(batch) =
+            MultipartUpload::<5>::from_request(multipart_request("hello"), &())
+                .await
+                .unwrap();
+
+        let BatchRequest::Single(request) = batch else {
+            panic!("expected a single operation");
+        };
+        let ConstValue::Upload(upload) = &request.variables["file"] else {
+            panic!("expected an upload");
+        };
+
+        assert_eq!(upload.filename, "a.txt");
+        assert_eq!(upload.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(upload.bytes().await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn files_over_the_size_limit_are_rejected() {
+        let rejection = MultipartUpload::<4>::from_request(multipart_request("hello"), &())
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            rejection,
+            (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "upload exceeds maximum size of 4 bytes".to_owned()
+            )
+        );
+    }
+}
@@ -0,0 +1,422 @@
+use std::{error::Error, fmt};
+
+use indexmap::IndexMap;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+
+use super::{ConstValue, Name};
+
+/// This type represents errors that can occur when deserializing.
+#[derive(Debug)]
+pub struct DeserializerError {
+    message: String,
+    path: Vec<String>,
+}
+
+impl DeserializerError {
+    /// Path to the value that failed to deserialize, object keys and list
+    /// indices from the outermost value inwards.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Prefixes the path with the key or index of the enclosing value
+    pub(crate) fn at(mut self, segment: impl fmt::Display) -> Self {
+        self.path.insert(0, segment.to_string());
+        self
+    }
+}
+
+impl fmt::Display for DeserializerError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            fmt.write_str(&self.message)
+        } else {
+            write!(fmt, "{} at {}", self.message, self.path.join("."))
+        }
+    }
+}
+
+impl Error for DeserializerError {
+    fn description(&self) -> &str {
+        "ConstValue deserializer error"
+    }
+}
+
+impl de::Error for DeserializerError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeserializerError {
+            message: msg.to_string(),
+            path: Vec::new(),
+        }
+    }
+}
+
+/// Interpret a `ConstValue` as an instance of type `T`.
+///
+/// Enum values (and strings) deserialize into Rust enums by variant name and
+/// object keys the target struct doesn't declare are an error.
+#[inline]
+pub fn from_value<T: DeserializeOwned>(value: ConstValue) -> Result<T, DeserializerError> {
+    T::deserialize(value)
+}
+
+impl ConstValue {
+    fn unexpected(&self) -> Unexpected {
+        match self {
+            ConstValue::Null => Unexpected::Unit,
+            ConstValue::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(v), _) => Unexpected::Unsigned(v),
+                (_, Some(v)) => Unexpected::Signed(v),
+                _ => Unexpected::Float(n.as_f64().unwrap_or_default()),
+            },
+            ConstValue::String(s) => Unexpected::Str(s),
+            ConstValue::Boolean(b) => Unexpected::Bool(*b),
+            ConstValue::Binary(bytes) => Unexpected::Bytes(bytes),
+            ConstValue::Enum(name) => Unexpected::Str(name),
+            ConstValue::List(_) => Unexpected::Seq,
+            ConstValue::Object(_) => Unexpected::Map,
+            ConstValue::Upload(_) => Unexpected::Other("upload"),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ConstValue {
+    type Error = DeserializerError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            ConstValue::Null => visitor.visit_unit(),
+            ConstValue::Number(n) => {
+                if let Some(v) = n.as_u64() {
+                    visitor.visit_u64(v)
+                } else if let Some(v) = n.as_i64() {
+                    visitor.visit_i64(v)
+                } else {
+                    visitor.visit_f64(n.as_f64().unwrap_or_default())
+                }
+            }
+            ConstValue::String(s) => visitor.visit_string(s),
+            ConstValue::Boolean(b) => visitor.visit_bool(b),
+            ConstValue::Binary(bytes) => visitor.visit_byte_buf(bytes.to_vec()),
+            ConstValue::Enum(name) => visitor.visit_str(&name),
+            ConstValue::List(items) => visit_list(items, visitor),
+            ConstValue::Object(map) => visit_object(map, visitor),
+            ConstValue::Upload(_) => Err(de::Error::invalid_type(
+                Unexpected::Other("upload"),
+                &visitor,
+            )),
+        }
+    }
+
+    #[inline]
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            ConstValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self {
+            ConstValue::Object(map) => {
+                if let Some(key) = map.keys().find(|key| !fields.contains(&key.as_str())) {
+                    return Err(
+                        <DeserializerError as de::Error>::unknown_field(key, fields).at(key)
+                    );
+                }
+                visit_object(map, visitor)
+            }
+            _ => de::Deserializer::deserialize_any(self, visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (variant, value) = match self {
+            ConstValue::Enum(name) => (name.to_string(), None),
+            ConstValue::String(s) => (s, None),
+            ConstValue::Object(map) if map.len() == 1 => {
+                let (name, value) = map.into_iter().next().expect("single entry object");
+                (name.to_string(), Some(value))
+            }
+            other => {
+                return Err(de::Error::invalid_type(other.unexpected(), &"enum value"));
+            }
+        };
+
+        visitor.visit_enum(EnumDeserializer { variant, value })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier
+        ignored_any
+    }
+}
+
+fn visit_list<'de, V: Visitor<'de>>(
+    items: Vec<ConstValue>,
+    visitor: V,
+) -> Result<V::Value, DeserializerError> {
+    let len = items.len();
+    let mut seq = SeqDeserializer {
+        iter: items.into_iter().enumerate(),
+    };
+    let value = visitor.visit_seq(&mut seq)?;
+
+    if seq.iter.len() == 0 {
+        Ok(value)
+    } else {
+        Err(de::Error::invalid_length(len, &"fewer elements in list"))
+    }
+}
+
+fn visit_object<'de, V: Visitor<'de>>(
+    map: IndexMap<Name, ConstValue>,
+    visitor: V,
+) -> Result<V::Value, DeserializerError> {
+    let len = map.len();
+    let mut object = MapDeserializer {
+        iter: map.into_iter(),
+        value: None,
+    };
+    let value = visitor.visit_map(&mut object)?;
+
+    if object.iter.len() == 0 {
+        Ok(value)
+    } else {
+        Err(de::Error::invalid_length(len, &"fewer elements in object"))
+    }
+}
+
+struct SeqDeserializer {
+    iter: std::iter::Enumerate<std::vec::IntoIter<ConstValue>>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = DeserializerError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        match self.iter.next() {
+            Some((index, value)) => seed
+                .deserialize(value)
+                .map(Some)
+                .map_err(|err| err.at(index)),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: indexmap::map::IntoIter<Name, ConstValue>,
+    value: Option<(Name, ConstValue)>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = DeserializerError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                let deserialized = seed.deserialize(ConstValue::String(key.to_string()))?;
+                self.value = Some((key, value));
+                Ok(Some(deserialized))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+
+        seed.deserialize(value).map_err(|err| err.at(key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// An enum value is the variant name, a single entry object is a variant with
+/// data (`{ circle: { radius: 1 } }`)
+struct EnumDeserializer {
+    variant: String,
+    value: Option<ConstValue>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = DeserializerError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(ConstValue::String(self.variant))?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<ConstValue>,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = DeserializerError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.value {
+            None | Some(ConstValue::Null) => Ok(()),
+            Some(value) => Err(de::Error::invalid_type(value.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Some(ConstValue::List(items)) => visit_list(items, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"tuple variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Some(ConstValue::Object(map)) => visit_object(map, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"struct variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    enum Species {
+        Dog,
+        Cat,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Pet {
+        name: String,
+        species: Species,
+        age: Option<i32>,
+    }
+
+    #[test]
+    fn nested_values() {
+        let value = ConstValue::from_json(serde_json::json!([
+            { "name": "Rex", "species": "DOG", "age": 3 },
+            { "name": "Tom", "species": "CAT", "age": null },
+        ]));
+
+        assert_eq!(
+            from_value::<Vec<Pet>>(value).unwrap(),
+            vec![
+                Pet {
+                    name: "Rex".to_owned(),
+                    species: Species::Dog,
+                    age: Some(3),
+                },
+                Pet {
+                    name: "Tom".to_owned(),
+                    species: Species::Cat,
+                    age: None,
+                },
+            ]
+        );
+        assert_eq!(
+            from_value::<Species>(ConstValue::Enum(Name::new("CAT"))).unwrap(),
+            Species::Cat
+        );
+    }
+
+    #[test]
+    fn errors_have_paths() {
+        let value = ConstValue::from_json(serde_json::json!([
+            { "name": "Rex", "species": "DOG" },
+            { "name": "Tom", "species": "CAT", "color": "grey" },
+        ]));
+        let err = from_value::<Vec<Pet>>(value).unwrap_err();
+        assert_eq!(err.path(), ["1", "color"]);
+
+        let value = ConstValue::from_json(serde_json::json!({ "name": "Rex", "species": "BIRD" }));
+        let err = from_value::<Pet>(value).unwrap_err();
+        assert_eq!(err.path(), ["species"]);
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt::{self, Display, Formatter};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::ConstValue;
+
+/// A GraphQL `ID`. Always a string on the wire, even when it's built from a
+/// numeric database key.
+///
+/// [Reference](https://spec.graphql.org/October2021/#sec-ID)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Id(pub String);
+
+impl Id {
+    /// The id as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// An opaque id naming both a type and its key, e.g. a Relay global
+    /// object id or a pagination cursor: `Id::from_parts("Person", 42)`
+    pub fn from_parts(type_name: &str, key: impl Display) -> Self {
+        Self::encode(&format!("{}:{}", type_name, key))
+    }
+
+    /// The type name and key of an id built by [`from_parts`](Self::from_parts),
+    /// `None` if it wasn't built that way
+    pub fn parts(&self) -> Option<(String, String)> {
+        let decoded = self.decode()?;
+        let (type_name, key) = decoded.split_once(':')?;
+        Some((type_name.to_owned(), key.to_owned()))
+    }
+
+    /// Hides `raw` behind base64 so clients treat the id as opaque
+    pub fn encode(raw: &str) -> Self {
+        Self(STANDARD.encode(raw))
+    }
+
+    /// The raw string of an id built by [`encode`](Self::encode), `None` if
+    /// it isn't valid base64 encoded UTF-8
+    pub fn decode(&self) -> Option<String> {
+        let bytes = STANDARD.decode(&self.0).ok()?;
+        String::from_utf8(bytes).ok()
+    }
+}
+
+impl Display for Id {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for Id {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&str> for Id {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl From<i64> for Id {
+    fn from(id: i64) -> Self {
+        Self(id.to_string())
+    }
+}
+
+impl From<Id> for String {
+    fn from(id: Id) -> Self {
+        id.0
+    }
+}
+
+impl From<Id> for ConstValue {
+    fn from(id: Id) -> Self {
+        ConstValue::String(id.0)
+    }
+}
+
+impl Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+//nb: like `ID` input coercion, integers are accepted as well as strings
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl<'de> de::Visitor<'de> for IdVisitor {
+            type Value = Id;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("a string or integer id")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Id, E> {
+                Ok(Id::from(v))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Id, E> {
+                Ok(Id(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Id, E> {
+                Ok(Id::from(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Id, E> {
+                Ok(Id(v.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(IdVisitor)
+    }
+}
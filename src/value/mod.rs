@@ -4,7 +4,8 @@
 // #![allow(clippy::uninlined_format_args)]
 #![forbid(unsafe_code)]
 
-// mod deserializer;
+mod deserializer;
+mod id;
 // mod macros;
 mod serializer;
 mod value_serde;
@@ -14,25 +15,68 @@ use std::{
     borrow::{Borrow, Cow},
     fmt::{self, Display, Formatter, Write},
     ops::Deref,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use bytes::Bytes;
-// pub use deserializer::{from_value, DeserializerError};
+use dashmap::DashSet;
+pub use deserializer::{from_value, DeserializerError};
+pub use id::Id;
 #[doc(hidden)]
 pub use indexmap;
 use indexmap::IndexMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub use serde_json::Number;
-// pub use serializer::{to_value, SerializerError};
+pub use serializer::{to_value, SerializerError};
+
+use crate::upload::Upload;
 // pub use variables::Variables;
 
 /// A GraphQL name.
 ///
+/// Names taken from the schema (field names, enum values, argument names)
+/// are interned: the executor hands back the same shared allocation every
+/// time, so the field names and response keys produced on every execution
+/// don't allocate once they've been seen. Other names, e.g. the keys of JSON
+/// variables or serialized maps, are allocated as usual.
+///
 /// [Reference](https://spec.graphql.org/June2018/#Name).
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Name(Arc<str>);
 
+/// Process-wide intern table backing [`Name::interned`].
+///
+/// Entries are never evicted, so only names bounded by the schema may be
+/// interned. Anything a client controls (variable keys, aliases, map keys of
+/// resolved data) must go through [`Name::new`] instead or the table grows
+/// without limit.
+static INTERNED_NAMES: OnceLock<DashSet<Arc<str>>> = OnceLock::new();
+
+fn intern(name: &str) -> Arc<str> {
+    let names = INTERNED_NAMES.get_or_init(DashSet::new);
+
+    if let Some(interned) = names.get(name) {
+        return interned.clone();
+    }
+
+    names.insert(name.into());
+
+    //nb: re-read instead of returning the Arc we just built so that two threads
+    //racing to intern the same name still end up sharing one allocation
+    names
+        .get(name)
+        .map(|interned| interned.clone())
+        .expect("interned name missing")
+}
+
+/// True if `name` is in [`INTERNED_NAMES`]
+#[cfg(test)]
+pub(crate) fn is_interned(name: &str) -> bool {
+    INTERNED_NAMES
+        .get()
+        .is_some_and(|names| names.contains(name))
+}
+
 impl Serialize for Name {
     fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
         serializer.serialize_str(&self.0)
@@ -45,6 +89,12 @@ impl Name {
         Self(name.as_ref().into())
     }
 
+    /// Create a name that's defined by the schema, reusing the interned
+    /// allocation if it has been created before. See [`INTERNED_NAMES`].
+    pub(crate) fn interned(name: impl AsRef<str>) -> Self {
+        Self(intern(name.as_ref()))
+    }
+
     /// Get the name as a string.
     #[must_use]
     pub fn as_str(&self) -> &str {
@@ -111,9 +161,7 @@ impl<'a> PartialEq<Name> for &'a str {
 
 impl<'de> Deserialize<'de> for Name {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        Ok(Self(
-            String::deserialize(deserializer)?.into_boxed_str().into(),
-        ))
+        Ok(Self::new(String::deserialize(deserializer)?))
     }
 }
 
@@ -142,6 +190,8 @@ pub enum ConstValue {
     List(Vec<ConstValue>),
     /// An object. This is a map of keys to values.
     Object(IndexMap<Name, ConstValue>),
+    /// A file uploaded with a multipart request.
+    Upload(Upload),
 }
 
 impl PartialEq for ConstValue {
@@ -155,6 +205,7 @@ impl PartialEq for ConstValue {
             (ConstValue::String(a), ConstValue::Enum(b)) => a == b,
             (ConstValue::Enum(a), ConstValue::Enum(b)) => a == b,
             (ConstValue::Binary(a), ConstValue::Binary(b)) => a == b,
+            (ConstValue::Upload(a), ConstValue::Upload(b)) => a == b,
             (ConstValue::List(a), ConstValue::List(b)) => {
                 if a.len() != b.len() {
                     return false;
@@ -295,27 +346,105 @@ impl ConstValue {
                     .map(|(key, value)| (key, value.into_value()))
                     .collect(),
             ),
+            Self::Upload(upload) => Value::Upload(upload),
         }
     }
 
-    /// Attempt to convert the value into JSON. This is equivalent to the
-    /// `TryFrom` implementation.
-    ///
-    /// # Errors
-    ///
-    /// Fails if serialization fails (see enum docs for more info).
+    /// Convert the value into JSON. This is equivalent to the `From`
+    /// implementation, it never fails and only returns a `Result` for
+    /// compatibility.
     pub fn into_json(self) -> serde_json::Result<serde_json::Value> {
-        self.try_into()
+        Ok(self.into())
     }
 
-    /// Attempt to convert JSON into a value. This is equivalent to the
-    /// `TryFrom` implementation.
+    /// Converts the value into JSON as it appears in a response: enums become
+    /// their name as a string, binary becomes an array of bytes and `null` is
+    /// preserved. Uploads have no JSON form and become `null`.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Null => serde_json::Value::Null,
+            Self::Number(num) => serde_json::Value::Number(num.clone()),
+            Self::String(s) => serde_json::Value::String(s.clone()),
+            Self::Boolean(b) => serde_json::Value::Bool(*b),
+            Self::Binary(bytes) => bytes.iter().copied().collect(),
+            Self::Enum(name) => serde_json::Value::String(name.to_string()),
+            Self::List(items) => items.iter().map(Self::to_json).collect(),
+            Self::Object(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_json()))
+                    .collect(),
+            ),
+            Self::Upload(_) => serde_json::Value::Null,
+        }
+    }
+
+    /// Converts any serde serializable value: structs and maps become
+    /// objects, sequences become lists, numbers keep their integer or float
+    /// representation and unit enum variants become strings. See
+    /// [`to_value`].
     ///
     /// # Errors
     ///
-    /// Fails if deserialization fails (see enum docs for more info).
-    pub fn from_json(json: serde_json::Value) -> serde_json::Result<Self> {
-        json.try_into()
+    /// Fails for values GraphQL can't represent (e.g. `char`s or maps with
+    /// non-string keys).
+    pub fn from_serialize<T: Serialize + ?Sized>(value: &T) -> Result<Self, SerializerError> {
+        to_value(value)
+    }
+
+    /// Writes the value as JSON (the same JSON [`to_json`](Self::to_json)
+    /// builds) straight to `writer`, so large values are never copied into a
+    /// `serde_json::Value` first. Wrap unbuffered writers (files, sockets)
+    /// in a `BufWriter`.
+    pub fn write_json<W: std::io::Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, self)
+    }
+
+    /// Convert JSON into a value. This is equivalent to the `From`
+    /// implementation.
+    #[must_use]
+    pub fn from_json(json: serde_json::Value) -> Self {
+        json.into()
+    }
+
+    /// An integer number, serialized without a decimal point (`39`)
+    #[must_use]
+    pub fn int(v: i64) -> Self {
+        Self::Number(v.into())
+    }
+
+    /// A float number, serialized with a decimal point even when it's
+    /// integral (`39.0`). `None` for NaN and infinite values, which GraphQL
+    /// can't represent.
+    #[must_use]
+    pub fn float(v: f64) -> Option<Self> {
+        Number::from_f64(v).map(Self::Number)
+    }
+
+    /// Coerces a number to a float. Integers are accepted wherever a `Float`
+    /// is expected.
+    ///
+    /// [Reference](https://spec.graphql.org/October2021/#sec-Float.Input-Coercion).
+    #[must_use]
+    pub fn coerce_to_float(&self) -> Option<f64> {
+        match self {
+            Self::Number(num) => num.as_f64(),
+            _ => None,
+        }
+    }
+
+    /// Coerces a number to an integer. Floats are only accepted if they have
+    /// no fractional part and fit in an `i64` (e.g. `4.0` from a JSON client).
+    #[must_use]
+    pub fn coerce_to_int(&self) -> Option<i64> {
+        match self {
+            Self::Number(num) => num.as_i64().or_else(|| {
+                let f = num.as_f64()?;
+                (f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64)
+                    .then_some(f as i64)
+            }),
+            _ => None,
+        }
     }
 }
 
@@ -325,33 +454,64 @@ impl Default for ConstValue {
     }
 }
 
+/// Formats the value as JSON (see [`ConstValue::to_json`]), the alternate
+/// flag (`{:#}`) pretty prints it.
 impl Display for ConstValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Number(num) => write!(f, "{}", *num),
-            Self::String(val) => write_quoted(val, f),
-            Self::Boolean(true) => f.write_str("true"),
-            Self::Boolean(false) => f.write_str("false"),
-            Self::Binary(bytes) => write_binary(bytes, f),
-            Self::Null => f.write_str("null"),
-            Self::Enum(name) => f.write_str(name),
-            Self::List(items) => write_list(items, f),
-            Self::Object(map) => write_object(map, f),
+        let json = self.to_json();
+        let formatted = if f.alternate() {
+            serde_json::to_string_pretty(&json)
+        } else {
+            serde_json::to_string(&json)
+        };
+
+        f.write_str(&formatted.map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Objects map to [`ConstValue::Object`], arrays to [`ConstValue::List`] and
+/// numbers keep their integer or float representation. JSON has no enums, so
+/// strings always map to [`ConstValue::String`].
+impl From<serde_json::Value> for ConstValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Self::Null,
+            serde_json::Value::Bool(b) => Self::Boolean(b),
+            serde_json::Value::Number(num) => Self::Number(num),
+            serde_json::Value::String(s) => Self::String(s),
+            serde_json::Value::Array(items) => {
+                Self::List(items.into_iter().map(Into::into).collect())
+            }
+            serde_json::Value::Object(map) => Self::Object(
+                map.into_iter()
+                    .map(|(key, value)| (Name::new(key), value.into()))
+                    .collect(),
+            ),
         }
     }
 }
 
-impl TryFrom<serde_json::Value> for ConstValue {
-    type Error = serde_json::Error;
-    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
-        Self::deserialize(value)
-    }
-}
-
-impl TryFrom<ConstValue> for serde_json::Value {
-    type Error = serde_json::Error;
-    fn try_from(value: ConstValue) -> Result<Self, Self::Error> {
-        serde_json::to_value(value)
+/// Converts the value into JSON as it appears in a response, see
+/// [`ConstValue::to_json`]: enums become their name as a string and binary
+/// becomes an array of bytes, so this direction can't fail. Numbers keep
+/// their integer or float representation and uploads become `null`.
+impl From<ConstValue> for serde_json::Value {
+    fn from(value: ConstValue) -> Self {
+        match value {
+            ConstValue::Null => Self::Null,
+            ConstValue::Number(num) => Self::Number(num),
+            ConstValue::String(s) => Self::String(s),
+            ConstValue::Boolean(b) => Self::Bool(b),
+            ConstValue::Binary(bytes) => bytes.iter().copied().collect(),
+            ConstValue::Enum(name) => Self::String(name.to_string()),
+            ConstValue::List(items) => items.into_iter().map(Self::from).collect(),
+            ConstValue::Object(map) => Self::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key.to_string(), value.into()))
+                    .collect(),
+            ),
+            ConstValue::Upload(_) => Self::Null,
+        }
     }
 }
 
@@ -383,6 +543,8 @@ pub enum Value {
     List(Vec<Value>),
     /// An object. This is a map of keys to values.
     Object(IndexMap<Name, Value>),
+    /// A file uploaded with a multipart request.
+    Upload(Upload),
 }
 
 impl Value {
@@ -418,6 +580,7 @@ impl Value {
                     .map(|(key, value)| Ok((key, value.into_const_with_mut(f)?)))
                     .collect::<Result<_, _>>()?,
             ),
+            Self::Upload(upload) => ConstValue::Upload(upload),
         })
     }
 
@@ -469,6 +632,8 @@ impl Display for Value {
             Self::Enum(name) => f.write_str(name),
             Self::List(items) => write_list(items, f),
             Self::Object(map) => write_object(map, f),
+            //nb: uploads only come from variables, they have no literal
+            Self::Upload(_) => f.write_str("null"),
         }
     }
 }
@@ -501,7 +666,7 @@ fn write_quoted(s: &str, f: &mut Formatter<'_>) -> fmt::Result {
             '\t' => f.write_str("\\t"),
             '"' => f.write_str("\\\""),
             '\\' => f.write_str("\\\\"),
-            c if c.is_control() => write!(f, "\\u{:04}", c as u32),
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32),
             c => f.write_char(c),
         }?
     }
@@ -549,3 +714,207 @@ fn write_object<K: Display, V: Display>(
     }
     f.write_char('}')
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_interning() {
+        let a = Name::interned("firstName");
+        let b = Name::interned(String::from("firstName"));
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+
+        let c = Name::interned("lastName");
+        assert!(!Arc::ptr_eq(&a.0, &c.0));
+
+        //nb: names that aren't from the schema never enter the table
+        let d = Name::new("notInterned");
+        assert!(!Arc::ptr_eq(&d.0, &Name::new("notInterned").0));
+        assert!(!INTERNED_NAMES.get().unwrap().contains("notInterned"));
+    }
+
+    #[test]
+    fn id_parts() {
+        let id = Id::from_parts("Person", 42);
+        assert_eq!(id.as_str(), "UGVyc29uOjQy");
+        assert_eq!(id.parts(), Some(("Person".to_owned(), "42".to_owned())));
+        assert_eq!(Id::from("42").parts(), None);
+
+        assert_eq!(ConstValue::from(Id::from(42)), ConstValue::from("42"));
+        assert_eq!(
+            serde_json::from_str::<Id>("42").unwrap(),
+            serde_json::from_str::<Id>(r#""42""#).unwrap()
+        );
+    }
+
+    #[test]
+    fn int_and_float_numbers() {
+        assert_eq!(ConstValue::int(39).to_json().to_string(), "39");
+        assert_eq!(
+            ConstValue::float(39.0).unwrap().to_json().to_string(),
+            "39.0"
+        );
+        assert_eq!(ConstValue::float(f64::NAN), None);
+
+        assert_eq!(ConstValue::int(4).coerce_to_int(), Some(4));
+        assert_eq!(ConstValue::float(4.5).unwrap().coerce_to_int(), None);
+    }
+
+    #[test]
+    fn from_json() {
+        let value = ConstValue::from(serde_json::json!({
+            "name": "Zack",
+            "age": 39,
+            "height": 1.8,
+            "pets": ["Rex", null],
+            "verified": true
+        }));
+
+        let ConstValue::Object(map) = &value else {
+            panic!("expected object, got {:?}", value);
+        };
+
+        assert_eq!(map["name"], ConstValue::String("Zack".to_owned()));
+        assert!(matches!(&map["age"], ConstValue::Number(n) if n.is_i64()));
+        assert!(matches!(&map["height"], ConstValue::Number(n) if n.is_f64()));
+        assert_eq!(
+            map["pets"],
+            ConstValue::List(vec!["Rex".into(), ConstValue::Null])
+        );
+        assert_eq!(map["verified"], ConstValue::Boolean(true));
+    }
+
+    #[test]
+    fn to_json() {
+        let value = ConstValue::Object(IndexMap::from([
+            (Name::new("status"), ConstValue::Enum(Name::new("ACTIVE"))),
+            (Name::new("nickname"), ConstValue::Null),
+            (Name::new("tags"), ConstValue::List(vec!["a\"b".into()])),
+        ]));
+
+        assert_eq!(
+            value.to_json(),
+            serde_json::json!({ "status": "ACTIVE", "nickname": null, "tags": ["a\"b"] })
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{"status":"ACTIVE","nickname":null,"tags":["a\"b"]}"#
+        );
+        assert_eq!(
+            format!("{:#}", ConstValue::List(vec![1.into()])),
+            "[\n  1\n]"
+        );
+    }
+
+    #[test]
+    fn from_serialize() {
+        #[derive(Serialize)]
+        #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+        enum Species {
+            Dog,
+        }
+
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Pet {
+            name: &'static str,
+            species: Species,
+            nick_name: Option<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct Person {
+            name: &'static str,
+            age: u32,
+            height: f64,
+            pets: Vec<Pet>,
+        }
+
+        let value = ConstValue::from_serialize(&Person {
+            name: "Zack",
+            age: 39,
+            height: 1.8,
+            pets: vec![Pet {
+                name: "Rex",
+                species: Species::Dog,
+                nick_name: None,
+            }],
+        })
+        .unwrap();
+
+        assert_eq!(
+            value,
+            ConstValue::from(serde_json::json!({
+                "name": "Zack",
+                "age": 39,
+                "height": 1.8,
+                "pets": [{ "name": "Rex", "species": "DOG", "nickName": null }]
+            }))
+        );
+
+        let ConstValue::Object(map) = &value else {
+            panic!("expected object, got {:?}", value);
+        };
+        assert!(matches!(&map["age"], ConstValue::Number(n) if n.is_u64()));
+
+        assert!(ConstValue::from_serialize(&'x').is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = serde_json::json!({
+            "signed": -3,
+            "unsigned": u64::MAX,
+            "float": 2.5,
+            "nothing": null,
+            "nested": [[1, "two"], { "three": true }]
+        });
+
+        let value = ConstValue::from(json.clone());
+        assert!(
+            matches!(&value, ConstValue::Object(map) if map["unsigned"] == ConstValue::from(u64::MAX))
+        );
+        assert_eq!(serde_json::Value::from(value), json);
+
+        assert_eq!(
+            serde_json::Value::from(ConstValue::Binary(vec![1u8, 2].into())),
+            serde_json::json!([1, 2])
+        );
+        assert_eq!(
+            serde_json::Value::from(ConstValue::Enum(Name::new("ASC"))),
+            serde_json::json!("ASC")
+        );
+    }
+
+    #[test]
+    fn write_json_matches_to_json() {
+        let value = ConstValue::List(vec![
+            ConstValue::from(serde_json::json!({ "name": "Zack", "age": 39, "height": 1.8 })),
+            ConstValue::Enum(Name::new("ASC")),
+            ConstValue::Binary(vec![1u8, 2].into()),
+            ConstValue::Null,
+        ]);
+
+        let mut written = Vec::new();
+        value.write_json(&mut written).unwrap();
+
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&written).unwrap(),
+            value.to_json()
+        );
+    }
+
+    #[test]
+    fn number_coercion() {
+        assert_eq!(ConstValue::from(3).coerce_to_float(), Some(3.0));
+        assert_eq!(ConstValue::from(2.5).coerce_to_float(), Some(2.5));
+        assert_eq!(ConstValue::from("3").coerce_to_float(), None);
+
+        assert_eq!(ConstValue::from(3).coerce_to_int(), Some(3));
+        assert_eq!(ConstValue::from(4.0).coerce_to_int(), Some(4));
+        assert_eq!(ConstValue::from(2.5).coerce_to_int(), None);
+        assert_eq!(ConstValue::from(u64::MAX).coerce_to_int(), None);
+        assert_eq!(ConstValue::from(1e300).coerce_to_int(), None);
+    }
+}
@@ -3,7 +3,7 @@ use std::fmt::{self, Formatter};
 use indexmap::IndexMap;
 use serde::{
     de::{Error as DeError, MapAccess, SeqAccess, Visitor},
-    ser::SerializeMap,
+    ser::{Error as SerError, SerializeMap},
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
@@ -20,6 +20,7 @@ impl Serialize for ConstValue {
             ConstValue::Enum(v) => serializer.serialize_str(v),
             ConstValue::List(v) => v.serialize(serializer),
             ConstValue::Object(v) => v.serialize(serializer),
+            ConstValue::Upload(_) => Err(SerError::custom("uploads can't be serialized")),
         }
     }
 }
@@ -174,6 +175,7 @@ impl Serialize for Value {
             Value::Enum(v) => serializer.serialize_str(v),
             Value::List(v) => v.serialize(serializer),
             Value::Object(v) => v.serialize(serializer),
+            Value::Upload(_) => Err(SerError::custom("uploads can't be serialized")),
         }
     }
 }